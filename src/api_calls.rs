@@ -1,161 +1,403 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
 use color_eyre::eyre::{Context, Result};
-use reqwest::Client;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, RETRY_AFTER};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
 
+use crate::api_error::ApiError;
+use crate::comments;
+use crate::labels;
 use crate::projects;
 use crate::sections;
+use crate::sync;
 use crate::tasks;
 use crate::tasks::Task;
-use crate::TaskResult;
 
-pub async fn fetch_projects(client: &Client) -> Result<Vec<projects::Project>> {
-    let response = client
-        .get("https://api.todoist.com/rest/v2/projects")
-        .send()
-        .await
-        .context("Failed to send request to fetch projects")?;
+pub const DEFAULT_BASE_URL: &str = "https://api.todoist.com/rest/v2";
+pub const DEFAULT_SYNC_URL: &str = "https://api.todoist.com/sync/v9/sync";
+
+/// Header the REST API uses to recognise a repeated request.
+pub const REQUEST_ID: &str = "X-Request-Id";
+
+/// How often a failed request is retried before the error is returned.
+const MAX_RETRIES: u32 = 3;
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// HTTP client bound to the REST endpoint the app talks to. The base URL is
+/// the real Todoist API by default, but can point at a proxy or the bundled
+/// mock server instead.
+#[derive(Debug, Clone)]
+pub struct ApiClient {
+    pub client: reqwest::Client,
+    pub base_url: String,
+    pub sync_url: String,
+}
+
+impl ApiClient {
+    pub fn new(bearer_token: &str, base_url: String, sync_url: String) -> Result<ApiClient> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", bearer_token))
+                .context("Token contains characters that are not valid in a header")?,
+        );
+
+        let client = reqwest::Client::builder()
+            .default_headers(headers)
+            .build()
+            .context("Failed to build HTTP client")?;
+
+        Ok(ApiClient {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            sync_url,
+        })
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url, path)
+    }
+
+    /// Sends a request and maps non-2xx responses to an `ApiError`. Transient
+    /// failures are retried with exponential backoff, honouring `Retry-After`
+    /// when rate limited. Requests that are not `idempotent` are only retried
+    /// when they are known not to have reached the server.
+    async fn send(&self, request: RequestBuilder, idempotent: bool) -> Result<Response, ApiError> {
+        let mut attempt = 0;
+        loop {
+            let Some(this_attempt) = request.try_clone() else {
+                // Streaming bodies cannot be replayed
+                return check_status(request.send().await?).await;
+            };
+
+            let result = match this_attempt.send().await {
+                Ok(response) => check_status(response).await,
+                Err(e) => Err(ApiError::from(e)),
+            };
 
-    if response.status().is_client_error() {
-        return Err(color_eyre::eyre::eyre!("Received a 400 error: {:?}. This would most likely be because of an incorrect token. Check your config file for token.", response.status()));
+            match result {
+                Err(e)
+                    if attempt < MAX_RETRIES
+                        && e.is_transient()
+                        && (idempotent || e.is_unprocessed()) =>
+                {
+                    let backoff = e.retry_after().unwrap_or_else(|| {
+                        INITIAL_BACKOFF.saturating_mul(2u32.pow(attempt)).min(MAX_BACKOFF)
+                    });
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, ApiError> {
+        let response = self.send(self.client.get(self.url(path)), true).await?;
+        decode(response).await
     }
 
-    let response_text = response
-        .text()
-        .await
-        .context("Failed to read response text")?;
+    async fn post<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        json: &serde_json::Value,
+        idempotent: bool,
+    ) -> Result<T, ApiError> {
+        let request = self.client.post(self.url(path)).json(json);
+        let response = self.send(request, idempotent).await?;
+        decode(response).await
+    }
 
-    let serialized: Vec<projects::Project> = serde_json::from_str(&response_text)
-        .context("Failed to deserialize response into Vec<Project>")?;
-    Ok(serialized)
+    async fn delete(&self, path: &str) -> Result<(), ApiError> {
+        match self.send(self.client.delete(self.url(path)), true).await {
+            // A retried delete may find the resource already gone
+            Ok(_) | Err(ApiError::NotFound) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
 }
 
-pub async fn fetch_tasks(
-    client: &reqwest::Client,
-) -> Result<Vec<tasks::Task>, Box<dyn std::error::Error>> {
-    let response = client
-        .get("https://api.todoist.com/rest/v2/tasks")
-        .send()
-        .await
-        .unwrap()
-        .text()
-        .await
-        .unwrap();
+async fn check_status(response: Response) -> Result<Response, ApiError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
 
-    let serialized: Vec<tasks::Task> = serde_json::from_str(&response).unwrap();
-    Ok(serialized)
+    let retry_after = response
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(Duration::from_secs);
+    let body = response.text().await.unwrap_or_default();
+
+    Err(match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ApiError::Auth(status),
+        StatusCode::NOT_FOUND => ApiError::NotFound,
+        StatusCode::TOO_MANY_REQUESTS => ApiError::RateLimited { retry_after },
+        status if status.is_server_error() => ApiError::Server { status, body },
+        status => ApiError::Client { status, body },
+    })
+}
+
+async fn decode<T: DeserializeOwned>(response: Response) -> Result<T, ApiError> {
+    let response_text = response.text().await?;
+    serde_json::from_str(&response_text)
+        .map_err(|e| ApiError::Decode(format!("{} (Response was: {})", e, response_text)))
 }
 
-pub async fn fetch_sections(
-    client: &reqwest::Client,
-) -> Result<Vec<sections::Section>, Box<dyn std::error::Error>> {
-    let response = client
-        .get("https://api.todoist.com/rest/v2/sections")
-        .send()
-        .await
-        .unwrap()
-        .text()
-        .await
-        .unwrap();
+pub async fn fetch_projects(api: &ApiClient) -> Result<Vec<projects::Project>, ApiError> {
+    api.get("projects").await
+}
+
+pub async fn sync(api: &ApiClient, sync_token: &str) -> Result<sync::SyncResponse, ApiError> {
+    sync_resources(
+        api,
+        sync_token,
+        &["projects", "sections", "items", "labels", "filters", "user"],
+    )
+    .await
+}
 
-    let serialized: Vec<sections::Section> = serde_json::from_str(&response).unwrap();
-    Ok(serialized)
+/// Filters and the user's settings, which the REST API does not have.
+pub async fn sync_settings(api: &ApiClient) -> Result<sync::SyncResponse, ApiError> {
+    sync_resources(api, sync::FULL_SYNC_TOKEN, &["filters", "user"]).await
+}
+
+async fn sync_resources(
+    api: &ApiClient,
+    sync_token: &str,
+    resource_types: &[&str],
+) -> Result<sync::SyncResponse, ApiError> {
+    let request = api.client.post(&api.sync_url).json(&serde_json::json!({
+        "sync_token": sync_token,
+        "resource_types": resource_types,
+    }));
+    // Reading with a sync token has no side effects, so it is safe to repeat
+    let response = api.send(request, true).await?;
+    decode(response).await
+}
+
+pub async fn fetch_tasks(api: &ApiClient) -> Result<Vec<tasks::Task>, ApiError> {
+    api.get("tasks").await
+}
+
+pub async fn fetch_task(api: &ApiClient, task_id: String) -> Result<Task, ApiError> {
+    api.get(&format!("tasks/{}", task_id)).await
+}
+
+pub async fn fetch_sections(api: &ApiClient) -> Result<Vec<sections::Section>, ApiError> {
+    api.get("sections").await
+}
+
+pub async fn fetch_labels(api: &ApiClient) -> Result<Vec<labels::Label>, ApiError> {
+    api.get("labels").await
+}
+
+pub async fn fetch_shared_labels(api: &ApiClient) -> Result<Vec<String>, ApiError> {
+    api.get("labels/shared").await
 }
 
 pub async fn update_task(
-    client: &reqwest::Client,
+    api: &ApiClient,
     json: serde_json::Value,
     task_id: String,
-    tx: std::sync::mpsc::Sender<TaskResult>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let response = match client
-        .post(format!("https://api.todoist.com/rest/v2/tasks/{}", task_id))
-        .json(&json)
-        .send()
-        .await
-    {
-        Ok(resp) => resp,
-        Err(e) => {
-            let error_msg = format!("Network error: {}", e);
-            tx.send(TaskResult::Error(error_msg)).unwrap();
-            return Err(e.into());
-        }
-    };
-
-    let response_ref = &response;
-
-    match response_ref.error_for_status_ref() {
-        Ok(resp) => resp,
-        Err(e) => {
-            let status_code = e.status().unwrap_or_default();
-            let response_text = response.text().await.unwrap_or_default();
-            let error_msg = format!("API error: {} ({}) \n\n {}", status_code, e, response_text);
-            tx.send(TaskResult::Error(error_msg)).unwrap();
-            return Err(e.into());
-        }
-    };
-
-    let response_text = match response.text().await {
-        Ok(text) => text,
-        Err(e) => {
-            let error_msg = format!("Failed to read response: {}", e);
-            tx.send(TaskResult::Error(error_msg)).unwrap();
-            return Err(e.into());
-        }
-    };
+) -> Result<Task, ApiError> {
+    // Setting the same fields twice has the same effect as setting them once
+    api.post(&format!("tasks/{}", task_id), &json, true).await
+}
 
-    match serde_json::from_str::<Task>(&response_text) {
-        Ok(serialized) => {
-            tx.send(TaskResult::Task(serialized)).unwrap();
-            Ok(())
-        }
-        Err(e) => {
-            let error_msg = format!(
-                "Failed to parse response: {} (Response was: {})",
-                e, response_text
-            );
-            tx.send(TaskResult::Error(error_msg)).unwrap();
-            Err(e.into())
-        }
-    }
+/// Closes a task with the Sync API's `item_close`. Closing a recurring task
+/// moves it to its next date, so the command goes out under the `uuid` kept
+/// with the queued close: the server applies it once however often it is
+/// resent.
+pub async fn close_task(api: &ApiClient, task_id: String, uuid: &str) -> Result<(), ApiError> {
+    send_command(api, uuid, "item_close", serde_json::json!({ "id": task_id })).await
 }
 
-pub async fn close_task(
-    client: &reqwest::Client,
+pub async fn reopen_task(api: &ApiClient, task_id: String) -> Result<(), ApiError> {
+    let url = api.url(&format!("tasks/{}/reopen", task_id));
+    api.send(api.client.post(url), true).await?;
+    Ok(())
+}
+
+pub async fn delete_task(api: &ApiClient, task_id: String) -> Result<(), ApiError> {
+    api.delete(&format!("tasks/{}", task_id)).await
+}
+
+/// Moves a task and its subtasks. The REST API cannot change where a task
+/// lives, so this goes through the Sync API's `item_move`.
+pub async fn move_task(
+    api: &ApiClient,
     task_id: String,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let url = format!("https://api.todoist.com/rest/v2/tasks/{}/close", task_id);
+    json: serde_json::Value,
+) -> Result<(), ApiError> {
+    let mut args = json;
+    args["id"] = serde_json::Value::String(task_id);
+    command(api, "item_move", args).await
+}
 
-    let _response = client.post(url).send().await?;
+/// Saves the order of sibling tasks with a single `item_reorder` command.
+pub async fn reorder_tasks(api: &ApiClient, json: serde_json::Value) -> Result<(), ApiError> {
+    command(api, "item_reorder", json).await
+}
 
-    Ok(())
+/// A uuid for a new Sync API command. The counter keeps commands made within
+/// the same clock tick apart.
+pub fn command_uuid(command_type: &str) -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    format!(
+        "{}-{}-{}",
+        command_type,
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
 }
 
-pub async fn delete_task(
-    client: &reqwest::Client,
-    task_id: String,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let url = format!("https://api.todoist.com/rest/v2/tasks/{}", task_id);
+async fn command(api: &ApiClient, command_type: &str, args: serde_json::Value) -> Result<(), ApiError> {
+    send_command(api, &command_uuid(command_type), command_type, args).await
+}
 
-    let _response = client.delete(url).send().await?;
+/// Sends a single Sync API command and turns a failed `sync_status` into an
+/// error. A retry reuses the command's uuid, which the server only applies
+/// once, so it is safe to repeat.
+async fn send_command(
+    api: &ApiClient,
+    uuid: &str,
+    command_type: &str,
+    args: serde_json::Value,
+) -> Result<(), ApiError> {
+    let request = api.client.post(&api.sync_url).json(&serde_json::json!({
+        "commands": [{ "type": command_type, "uuid": uuid, "args": args }],
+    }));
+    let response: sync::CommandResponse = decode(api.send(request, true).await?).await?;
 
-    Ok(())
+    match response.sync_status.get(uuid) {
+        Some(status) if status == "ok" => Ok(()),
+        Some(status) if status["http_code"] == 404 => Err(ApiError::NotFound),
+        Some(status) => Err(ApiError::Client {
+            status: StatusCode::BAD_REQUEST,
+            body: status["error"].as_str().map_or_else(|| status.to_string(), str::to_string),
+        }),
+        None => Err(ApiError::Decode(format!("No status for command {}", uuid))),
+    }
+}
+
+/// Creates a task. The server makes only one task per `request_id`, so a
+/// create whose response was lost can be sent again.
+pub async fn create_task(
+    api: &ApiClient,
+    json: serde_json::Value,
+    request_id: &str,
+) -> Result<Task, ApiError> {
+    let request = api.client.post(api.url("tasks")).header(REQUEST_ID, request_id).json(&json);
+    decode(api.send(request, true).await?).await
+}
+
+pub async fn create_project(
+    api: &ApiClient,
+    json: serde_json::Value,
+) -> Result<projects::Project, ApiError> {
+    api.post("projects", &json, false).await
 }
 
-pub async fn create_task<'a>(
-    client: &reqwest::Client,
+pub async fn update_project(
+    api: &ApiClient,
     json: serde_json::Value,
-    tx: std::sync::mpsc::Sender<TaskResult>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let response = client
-        .post("https://api.todoist.com/rest/v2/tasks")
-        .json(&json)
-        .send()
-        .await
-        .unwrap()
-        .text()
-        .await
-        .unwrap();
-
-    let serialized: Task = serde_json::from_str(&response).unwrap();
-    tx.send(TaskResult::Task(serialized)).unwrap();
+    project_id: String,
+) -> Result<projects::Project, ApiError> {
+    api.post(&format!("projects/{}", project_id), &json, true).await
+}
+
+pub async fn delete_project(api: &ApiClient, project_id: String) -> Result<(), ApiError> {
+    api.delete(&format!("projects/{}", project_id)).await
+}
+
+pub async fn fetch_archived_projects(api: &ApiClient) -> Result<Vec<projects::Project>, ApiError> {
+    api.get("projects/archived").await
+}
+
+pub async fn archive_project(api: &ApiClient, project_id: String) -> Result<(), ApiError> {
+    let url = api.url(&format!("projects/{}/archive", project_id));
+    api.send(api.client.post(url), true).await?;
     Ok(())
 }
+
+pub async fn unarchive_project(api: &ApiClient, project_id: String) -> Result<(), ApiError> {
+    let url = api.url(&format!("projects/{}/unarchive", project_id));
+    api.send(api.client.post(url), true).await?;
+    Ok(())
+}
+
+pub async fn create_section(
+    api: &ApiClient,
+    json: serde_json::Value,
+) -> Result<sections::Section, ApiError> {
+    api.post("sections", &json, false).await
+}
+
+pub async fn update_section(
+    api: &ApiClient,
+    json: serde_json::Value,
+    section_id: String,
+) -> Result<sections::Section, ApiError> {
+    api.post(&format!("sections/{}", section_id), &json, true).await
+}
+
+pub async fn delete_section(api: &ApiClient, section_id: String) -> Result<(), ApiError> {
+    api.delete(&format!("sections/{}", section_id)).await
+}
+
+/// Saves the order of a project's sections with a single `section_reorder`
+/// command.
+pub async fn reorder_sections(api: &ApiClient, json: serde_json::Value) -> Result<(), ApiError> {
+    command(api, "section_reorder", json).await
+}
+
+pub async fn create_label(api: &ApiClient, json: serde_json::Value) -> Result<labels::Label, ApiError> {
+    api.post("labels", &json, false).await
+}
+
+pub async fn update_label(
+    api: &ApiClient,
+    json: serde_json::Value,
+    label_id: String,
+) -> Result<labels::Label, ApiError> {
+    api.post(&format!("labels/{}", label_id), &json, true).await
+}
+
+pub async fn delete_label(api: &ApiClient, label_id: String) -> Result<(), ApiError> {
+    api.delete(&format!("labels/{}", label_id)).await
+}
+
+pub async fn fetch_comments(
+    api: &ApiClient,
+    task_id: String,
+) -> Result<Vec<comments::Comment>, ApiError> {
+    api.get(&format!("comments?task_id={}", task_id)).await
+}
+
+pub async fn create_comment(
+    api: &ApiClient,
+    json: serde_json::Value,
+) -> Result<comments::Comment, ApiError> {
+    api.post("comments", &json, false).await
+}
+
+pub async fn update_comment(
+    api: &ApiClient,
+    json: serde_json::Value,
+    comment_id: String,
+) -> Result<comments::Comment, ApiError> {
+    api.post(&format!("comments/{}", comment_id), &json, true).await
+}
+
+pub async fn delete_comment(api: &ApiClient, comment_id: String) -> Result<(), ApiError> {
+    api.delete(&format!("comments/{}", comment_id)).await
+}
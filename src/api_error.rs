@@ -0,0 +1,111 @@
+use std::{fmt, time::Duration};
+
+use reqwest::StatusCode;
+
+use crate::store::StoreError;
+
+/// Everything that can go wrong talking to the Todoist API (or the local
+/// store standing in for it).
+#[derive(Debug)]
+pub enum ApiError {
+    /// 401/403: the token is missing, wrong or revoked.
+    Auth(StatusCode),
+    /// 404: the resource does not exist (any more).
+    NotFound,
+    /// 429: too many requests; `retry_after` comes from the `Retry-After` header.
+    RateLimited { retry_after: Option<Duration> },
+    /// Any other 4xx: the server rejected the request itself.
+    Client { status: StatusCode, body: String },
+    /// 5xx: the server failed to handle a valid request.
+    Server { status: StatusCode, body: String },
+    /// The request never got a response (DNS, connection, timeout, ...).
+    Network(reqwest::Error),
+    /// The response could not be read or parsed.
+    Decode(String),
+    /// The local task store could not be read or written.
+    Storage(String),
+}
+
+impl ApiError {
+    /// Whether sending the same request again may succeed.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            ApiError::RateLimited { .. } | ApiError::Server { .. } | ApiError::Network(_)
+        )
+    }
+
+    /// Whether the request is known not to have reached the server, so that
+    /// even a non-idempotent request can safely be sent again.
+    pub fn is_unprocessed(&self) -> bool {
+        match self {
+            ApiError::RateLimited { .. } => true,
+            ApiError::Network(e) => e.is_connect(),
+            _ => false,
+        }
+    }
+
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            ApiError::RateLimited { retry_after } => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Auth(status) => write!(
+                f,
+                "Authentication failed ({}). Check the token in your config file.",
+                status
+            ),
+            ApiError::NotFound => write!(f, "Not found. It may have been deleted elsewhere."),
+            ApiError::RateLimited {
+                retry_after: Some(retry_after),
+            } => write!(
+                f,
+                "Rate limited by Todoist, retry in {} seconds",
+                retry_after.as_secs()
+            ),
+            ApiError::RateLimited { retry_after: None } => write!(f, "Rate limited by Todoist"),
+            ApiError::Client { status, body } => write!(f, "Request rejected ({}) \n\n {}", status, body),
+            ApiError::Server { status, body } => write!(f, "Todoist server error ({}) \n\n {}", status, body),
+            ApiError::Network(e) => write!(f, "Network error: {}", e),
+            ApiError::Decode(message) => write!(f, "Failed to parse response: {}", message),
+            ApiError::Storage(message) => write!(f, "Local store error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_decode() {
+            ApiError::Decode(e.to_string())
+        } else {
+            ApiError::Network(e)
+        }
+    }
+}
+
+impl From<StoreError> for ApiError {
+    fn from(e: StoreError) -> Self {
+        match e {
+            StoreError::NotFound => ApiError::NotFound,
+            StoreError::Invalid(body) => ApiError::Client {
+                status: StatusCode::BAD_REQUEST,
+                body,
+            },
+            StoreError::Storage(message) => ApiError::Storage(message),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for ApiError {
+    fn from(e: rusqlite::Error) -> Self {
+        ApiError::Storage(e.to_string())
+    }
+}
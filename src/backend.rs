@@ -0,0 +1,430 @@
+use std::{fmt, fs, path::PathBuf, sync::Arc};
+
+use async_trait::async_trait;
+use color_eyre::eyre::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{
+    api_calls::{self, ApiClient},
+    api_error::ApiError,
+    comments::Comment,
+    labels::Label,
+    projects::Project,
+    sections::Section,
+    store::{JsonStore, Resource, Storage, StoreError},
+    sync::{SyncResponse, FULL_SYNC_TOKEN},
+    tasks::Task,
+};
+
+/// Where the TUI reads and writes its data. Request bodies are the JSON the
+/// Todoist REST API takes, so every backend understands the same fields.
+#[async_trait]
+pub trait TaskBackend: Send + Sync + fmt::Debug {
+    async fn list_projects(&self) -> Result<Vec<Project>, ApiError>;
+    async fn create_project(&self, json: Value) -> Result<Project, ApiError>;
+    async fn update_project(&self, project_id: String, json: Value) -> Result<Project, ApiError>;
+    async fn delete_project(&self, project_id: String) -> Result<(), ApiError>;
+    async fn list_archived_projects(&self) -> Result<Vec<Project>, ApiError>;
+    async fn archive_project(&self, project_id: String) -> Result<(), ApiError>;
+    async fn unarchive_project(&self, project_id: String) -> Result<(), ApiError>;
+
+    async fn list_sections(&self) -> Result<Vec<Section>, ApiError>;
+    async fn create_section(&self, json: Value) -> Result<Section, ApiError>;
+    async fn update_section(&self, section_id: String, json: Value) -> Result<Section, ApiError>;
+    async fn delete_section(&self, section_id: String) -> Result<(), ApiError>;
+    /// Sets the order of a project's sections in one go, like the Sync API's
+    /// `section_reorder`: `json` holds `sections`, each with an `id` and
+    /// `section_order`.
+    async fn reorder_sections(&self, json: Value) -> Result<(), ApiError>;
+
+    async fn list_labels(&self) -> Result<Vec<Label>, ApiError>;
+    /// Names of labels used on shared tasks, which need not be personal labels.
+    async fn list_shared_labels(&self) -> Result<Vec<String>, ApiError>;
+    async fn create_label(&self, json: Value) -> Result<Label, ApiError>;
+    async fn update_label(&self, label_id: String, json: Value) -> Result<Label, ApiError>;
+    async fn delete_label(&self, label_id: String) -> Result<(), ApiError>;
+
+    /// Open tasks only, like `GET /tasks`.
+    async fn list_tasks(&self) -> Result<Vec<Task>, ApiError>;
+    async fn get_task(&self, task_id: String) -> Result<Task, ApiError>;
+    /// `request_id` makes repeating a create safe: the server only acts on
+    /// the first one.
+    async fn create_task(&self, json: Value, request_id: String) -> Result<Task, ApiError>;
+    async fn update_task(&self, task_id: String, json: Value) -> Result<Task, ApiError>;
+    /// `uuid` identifies this close, so sending it again after a lost
+    /// response does not move a recurring task on a second time.
+    async fn close_task(&self, task_id: String, uuid: String) -> Result<(), ApiError>;
+    async fn reopen_task(&self, task_id: String) -> Result<(), ApiError>;
+    async fn delete_task(&self, task_id: String) -> Result<(), ApiError>;
+    /// Moves a task with its subtasks, like the Sync API's `item_move`: `json`
+    /// holds one of `parent_id`, `section_id` or `project_id`.
+    async fn move_task(&self, task_id: String, json: Value) -> Result<(), ApiError>;
+    /// Sets the order of sibling tasks in one go, like the Sync API's
+    /// `item_reorder`: `json` holds `items`, each with an `id` and `child_order`.
+    async fn reorder_tasks(&self, json: Value) -> Result<(), ApiError>;
+
+    async fn list_comments(&self, task_id: String) -> Result<Vec<Comment>, ApiError>;
+    async fn create_comment(&self, json: Value) -> Result<Comment, ApiError>;
+    async fn update_comment(&self, comment_id: String, json: Value) -> Result<Comment, ApiError>;
+    async fn delete_comment(&self, comment_id: String) -> Result<(), ApiError>;
+
+    /// Changes since `sync_token`, in the shape of the Sync API.
+    async fn sync(&self, sync_token: String) -> Result<SyncResponse, ApiError>;
+
+    /// Everything the list endpoints above do not cover, such as filters and
+    /// the user's settings, without the rest of a full sync.
+    async fn sync_settings(&self) -> Result<SyncResponse, ApiError> {
+        let response = self.sync(FULL_SYNC_TOKEN.to_string()).await?;
+        Ok(SyncResponse {
+            projects: Vec::new(),
+            sections: Vec::new(),
+            items: Vec::new(),
+            labels: Vec::new(),
+            ..response
+        })
+    }
+}
+
+pub type Backend = Arc<dyn TaskBackend>;
+
+/// The Todoist cloud (or anything speaking its REST API, like the mock server).
+#[derive(Debug)]
+pub struct RestBackend(pub ApiClient);
+
+#[async_trait]
+impl TaskBackend for RestBackend {
+    async fn list_projects(&self) -> Result<Vec<Project>, ApiError> {
+        api_calls::fetch_projects(&self.0).await
+    }
+
+    async fn create_project(&self, json: Value) -> Result<Project, ApiError> {
+        api_calls::create_project(&self.0, json).await
+    }
+
+    async fn update_project(&self, project_id: String, json: Value) -> Result<Project, ApiError> {
+        api_calls::update_project(&self.0, json, project_id).await
+    }
+
+    async fn delete_project(&self, project_id: String) -> Result<(), ApiError> {
+        api_calls::delete_project(&self.0, project_id).await
+    }
+
+    async fn list_archived_projects(&self) -> Result<Vec<Project>, ApiError> {
+        api_calls::fetch_archived_projects(&self.0).await
+    }
+
+    async fn archive_project(&self, project_id: String) -> Result<(), ApiError> {
+        api_calls::archive_project(&self.0, project_id).await
+    }
+
+    async fn unarchive_project(&self, project_id: String) -> Result<(), ApiError> {
+        api_calls::unarchive_project(&self.0, project_id).await
+    }
+
+    async fn list_sections(&self) -> Result<Vec<Section>, ApiError> {
+        api_calls::fetch_sections(&self.0).await
+    }
+
+    async fn create_section(&self, json: Value) -> Result<Section, ApiError> {
+        api_calls::create_section(&self.0, json).await
+    }
+
+    async fn update_section(&self, section_id: String, json: Value) -> Result<Section, ApiError> {
+        api_calls::update_section(&self.0, json, section_id).await
+    }
+
+    async fn delete_section(&self, section_id: String) -> Result<(), ApiError> {
+        api_calls::delete_section(&self.0, section_id).await
+    }
+
+    async fn reorder_sections(&self, json: Value) -> Result<(), ApiError> {
+        api_calls::reorder_sections(&self.0, json).await
+    }
+
+    async fn list_labels(&self) -> Result<Vec<Label>, ApiError> {
+        api_calls::fetch_labels(&self.0).await
+    }
+
+    async fn list_shared_labels(&self) -> Result<Vec<String>, ApiError> {
+        api_calls::fetch_shared_labels(&self.0).await
+    }
+
+    async fn create_label(&self, json: Value) -> Result<Label, ApiError> {
+        api_calls::create_label(&self.0, json).await
+    }
+
+    async fn update_label(&self, label_id: String, json: Value) -> Result<Label, ApiError> {
+        api_calls::update_label(&self.0, json, label_id).await
+    }
+
+    async fn delete_label(&self, label_id: String) -> Result<(), ApiError> {
+        api_calls::delete_label(&self.0, label_id).await
+    }
+
+    async fn list_tasks(&self) -> Result<Vec<Task>, ApiError> {
+        api_calls::fetch_tasks(&self.0).await
+    }
+
+    async fn get_task(&self, task_id: String) -> Result<Task, ApiError> {
+        api_calls::fetch_task(&self.0, task_id).await
+    }
+
+    async fn create_task(&self, json: Value, request_id: String) -> Result<Task, ApiError> {
+        api_calls::create_task(&self.0, json, &request_id).await
+    }
+
+    async fn update_task(&self, task_id: String, json: Value) -> Result<Task, ApiError> {
+        api_calls::update_task(&self.0, json, task_id).await
+    }
+
+    async fn close_task(&self, task_id: String, uuid: String) -> Result<(), ApiError> {
+        api_calls::close_task(&self.0, task_id, &uuid).await
+    }
+
+    async fn reopen_task(&self, task_id: String) -> Result<(), ApiError> {
+        api_calls::reopen_task(&self.0, task_id).await
+    }
+
+    async fn delete_task(&self, task_id: String) -> Result<(), ApiError> {
+        api_calls::delete_task(&self.0, task_id).await
+    }
+
+    async fn move_task(&self, task_id: String, json: Value) -> Result<(), ApiError> {
+        api_calls::move_task(&self.0, task_id, json).await
+    }
+
+    async fn reorder_tasks(&self, json: Value) -> Result<(), ApiError> {
+        api_calls::reorder_tasks(&self.0, json).await
+    }
+
+    async fn list_comments(&self, task_id: String) -> Result<Vec<Comment>, ApiError> {
+        api_calls::fetch_comments(&self.0, task_id).await
+    }
+
+    async fn create_comment(&self, json: Value) -> Result<Comment, ApiError> {
+        api_calls::create_comment(&self.0, json).await
+    }
+
+    async fn update_comment(&self, comment_id: String, json: Value) -> Result<Comment, ApiError> {
+        api_calls::update_comment(&self.0, json, comment_id).await
+    }
+
+    async fn delete_comment(&self, comment_id: String) -> Result<(), ApiError> {
+        api_calls::delete_comment(&self.0, comment_id).await
+    }
+
+    async fn sync(&self, sync_token: String) -> Result<SyncResponse, ApiError> {
+        api_calls::sync(&self.0, &sync_token).await
+    }
+
+    async fn sync_settings(&self) -> Result<SyncResponse, ApiError> {
+        api_calls::sync_settings(&self.0).await
+    }
+}
+
+/// Tasks kept in a JSON file on this machine and never sent anywhere. The
+/// whole file is rewritten after every change.
+#[derive(Debug)]
+pub struct LocalBackend {
+    path: PathBuf,
+    store: std::sync::Mutex<JsonStore>,
+}
+
+impl LocalBackend {
+    /// Opens the store at `path`, starting an empty one with just an inbox if
+    /// the file does not exist yet.
+    pub fn open(path: PathBuf) -> Result<LocalBackend> {
+        let store = if path.exists() {
+            let json = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            serde_json::from_str(&json)
+                .with_context(|| format!("{} is not a valid task store", path.display()))?
+        } else {
+            JsonStore::new()
+        };
+
+        let backend = LocalBackend {
+            path,
+            store: std::sync::Mutex::new(store),
+        };
+        backend.save(&backend.lock())?;
+        Ok(backend)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, JsonStore> {
+        self.store.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn read<T: DeserializeOwned>(
+        &self,
+        read: impl FnOnce(&JsonStore) -> Result<Value, StoreError>,
+    ) -> Result<T, ApiError> {
+        decode(read(&self.lock())?)
+    }
+
+    /// Applies a change and writes the store back to disk. The change is made
+    /// to a copy that only replaces the store once it is saved, so a change
+    /// that fails halfway, or cannot be saved, leaves nothing behind.
+    fn write<T: DeserializeOwned>(
+        &self,
+        write: impl FnOnce(&mut JsonStore) -> Result<Value, StoreError>,
+    ) -> Result<T, ApiError> {
+        let mut store = self.lock();
+        let mut changed = store.clone();
+        let value = write(&mut changed)?;
+        self.save(&changed)
+            .map_err(|e| ApiError::Storage(format!("{:#}", e)))?;
+        *store = changed;
+        decode(value)
+    }
+
+    fn save(&self, store: &JsonStore) -> Result<()> {
+        let path = &self.path;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Write next to the store and rename, so a crash never leaves half a file
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string_pretty(store)?)
+            .with_context(|| format!("Failed to write {}", temp_path.display()))?;
+        fs::rename(&temp_path, path)
+            .with_context(|| format!("Failed to replace {}", path.display()))?;
+        Ok(())
+    }
+}
+
+/// Reads a resource stored as REST JSON into its typed form.
+pub fn decode<T: DeserializeOwned>(value: Value) -> Result<T, ApiError> {
+    serde_json::from_value(value).map_err(|e| ApiError::Decode(e.to_string()))
+}
+
+#[async_trait]
+impl TaskBackend for LocalBackend {
+    async fn list_projects(&self) -> Result<Vec<Project>, ApiError> {
+        self.read(|store| store.list(Resource::Projects).map(Value::from))
+    }
+
+    async fn create_project(&self, json: Value) -> Result<Project, ApiError> {
+        self.write(|store| store.create(Resource::Projects, &json))
+    }
+
+    async fn update_project(&self, project_id: String, json: Value) -> Result<Project, ApiError> {
+        self.write(|store| store.update(Resource::Projects, &project_id, &json))
+    }
+
+    async fn delete_project(&self, project_id: String) -> Result<(), ApiError> {
+        self.write(|store| store.delete(Resource::Projects, &project_id).map(|_| Value::Null))
+    }
+
+    async fn list_archived_projects(&self) -> Result<Vec<Project>, ApiError> {
+        self.read(|store| store.archived_projects().map(Value::from))
+    }
+
+    async fn archive_project(&self, project_id: String) -> Result<(), ApiError> {
+        self.write(|store| store.set_archived(&project_id, true).map(|_| Value::Null))
+    }
+
+    async fn unarchive_project(&self, project_id: String) -> Result<(), ApiError> {
+        self.write(|store| store.set_archived(&project_id, false).map(|_| Value::Null))
+    }
+
+    async fn list_sections(&self) -> Result<Vec<Section>, ApiError> {
+        self.read(|store| store.list(Resource::Sections).map(Value::from))
+    }
+
+    async fn create_section(&self, json: Value) -> Result<Section, ApiError> {
+        self.write(|store| store.create(Resource::Sections, &json))
+    }
+
+    async fn update_section(&self, section_id: String, json: Value) -> Result<Section, ApiError> {
+        self.write(|store| store.update(Resource::Sections, &section_id, &json))
+    }
+
+    async fn delete_section(&self, section_id: String) -> Result<(), ApiError> {
+        self.write(|store| store.delete(Resource::Sections, &section_id).map(|_| Value::Null))
+    }
+
+    async fn reorder_sections(&self, json: Value) -> Result<(), ApiError> {
+        self.write(|store| store.reorder(Resource::Sections, &json).map(|_| Value::Null))
+    }
+
+    async fn list_labels(&self) -> Result<Vec<Label>, ApiError> {
+        self.read(|store| store.list(Resource::Labels).map(Value::from))
+    }
+
+    async fn list_shared_labels(&self) -> Result<Vec<String>, ApiError> {
+        self.read(|store| store.shared_labels().map(Value::from))
+    }
+
+    async fn create_label(&self, json: Value) -> Result<Label, ApiError> {
+        self.write(|store| store.create(Resource::Labels, &json))
+    }
+
+    async fn update_label(&self, label_id: String, json: Value) -> Result<Label, ApiError> {
+        self.write(|store| store.update(Resource::Labels, &label_id, &json))
+    }
+
+    async fn delete_label(&self, label_id: String) -> Result<(), ApiError> {
+        self.write(|store| store.delete(Resource::Labels, &label_id).map(|_| Value::Null))
+    }
+
+    async fn list_tasks(&self) -> Result<Vec<Task>, ApiError> {
+        self.read(|store| store.list(Resource::Tasks).map(Value::from))
+    }
+
+    async fn get_task(&self, task_id: String) -> Result<Task, ApiError> {
+        self.read(|store| store.get(Resource::Tasks, &task_id)?.ok_or(StoreError::NotFound))
+    }
+
+    async fn create_task(&self, json: Value, _request_id: String) -> Result<Task, ApiError> {
+        self.write(|store| store.create(Resource::Tasks, &json))
+    }
+
+    async fn update_task(&self, task_id: String, json: Value) -> Result<Task, ApiError> {
+        self.write(|store| store.update(Resource::Tasks, &task_id, &json))
+    }
+
+    async fn close_task(&self, task_id: String, _uuid: String) -> Result<(), ApiError> {
+        self.write(|store| store.set_completed(&task_id, true).map(|_| Value::Null))
+    }
+
+    async fn reopen_task(&self, task_id: String) -> Result<(), ApiError> {
+        self.write(|store| store.set_completed(&task_id, false).map(|_| Value::Null))
+    }
+
+    async fn delete_task(&self, task_id: String) -> Result<(), ApiError> {
+        match self.write(|store| store.delete(Resource::Tasks, &task_id).map(|_| Value::Null)) {
+            Ok(()) | Err(ApiError::NotFound) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn move_task(&self, task_id: String, json: Value) -> Result<(), ApiError> {
+        self.write(|store| store.move_task(&task_id, &json).map(|_| Value::Null))
+    }
+
+    async fn reorder_tasks(&self, json: Value) -> Result<(), ApiError> {
+        self.write(|store| store.reorder(Resource::Tasks, &json).map(|_| Value::Null))
+    }
+
+    async fn list_comments(&self, task_id: String) -> Result<Vec<Comment>, ApiError> {
+        self.read(|store| store.comments_for(&task_id).map(Value::from))
+    }
+
+    async fn create_comment(&self, json: Value) -> Result<Comment, ApiError> {
+        self.write(|store| store.create(Resource::Comments, &json))
+    }
+
+    async fn update_comment(&self, comment_id: String, json: Value) -> Result<Comment, ApiError> {
+        self.write(|store| store.update(Resource::Comments, &comment_id, &json))
+    }
+
+    async fn delete_comment(&self, comment_id: String) -> Result<(), ApiError> {
+        self.write(|store| store.delete(Resource::Comments, &comment_id).map(|_| Value::Null))
+    }
+
+    async fn sync(&self, sync_token: String) -> Result<SyncResponse, ApiError> {
+        self.read(|store| Ok(store.sync(sync_token.parse().ok())))
+    }
+}
@@ -0,0 +1,67 @@
+use std::ops::Range;
+
+use crate::{projects::ListType, tasks::Filter, App};
+
+/// A column of the board: the tasks without a section, a section, or an
+/// included sub-project, as a range of `display_tasks`.
+#[derive(Debug)]
+pub struct Column {
+    pub name: String,
+    pub section_id: Option<String>,
+    /// Whether cards can be moved into and out of the column. Sub-projects
+    /// only show their tasks.
+    pub movable: bool,
+    pub tasks: Range<usize>,
+}
+
+/// Whether the task pane shows a project whose view style is a board.
+pub fn showing(app: &App) -> bool {
+    let Filter::ProjectId(project_id) = &app.tasks.filter else {
+        return false;
+    };
+    app.projects
+        .projects
+        .iter()
+        .any(|project| project.id == *project_id && project.view_style == ListType::Board)
+}
+
+/// The columns in the order of the task list's headings, which come from
+/// the project's sections and then its included sub-projects.
+pub fn columns(app: &App) -> Vec<Column> {
+    let Filter::ProjectId(project_id) = &app.tasks.filter else {
+        return Vec::new();
+    };
+    let sections = app.tasks.sections.for_project(project_id);
+    let headings = &app.tasks.headings;
+    let end = |heading: usize| {
+        headings
+            .get(heading)
+            .map_or(app.tasks.display_tasks.len(), |(before, _)| *before)
+    };
+
+    let mut columns = vec![Column {
+        name: "(No section)".to_string(),
+        section_id: None,
+        movable: true,
+        tasks: 0..end(0),
+    }];
+    for (heading, (before, name)) in headings.iter().enumerate() {
+        let section = sections.get(heading);
+        columns.push(Column {
+            name: name.clone(),
+            section_id: section.map(|section| section.id.clone()),
+            movable: section.is_some(),
+            tasks: *before..end(heading + 1),
+        });
+    }
+    columns
+}
+
+/// The column holding the selected task, or the first one.
+pub fn selected_column(app: &App, columns: &[Column]) -> usize {
+    app.tasks
+        .state
+        .selected()
+        .and_then(|selected| columns.iter().position(|column| column.tasks.contains(&selected)))
+        .unwrap_or(0)
+}
@@ -4,33 +4,77 @@ use std::fs::{self, File};
 use std::io::BufReader;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use crate::filters::SavedFilter;
+use crate::labels::Label;
 use crate::projects::Project;
+use crate::queue::TEMP_ID_PREFIX;
 use crate::sections::Section;
 use crate::tasks::Task;
+use crate::App;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CacheData {
     pub projects: Vec<Project>,
     pub tasks: Vec<Task>,
     pub sections: Vec<Section>,
+    #[serde(default)]
+    pub labels: Vec<Label>,
+    #[serde(default)]
+    pub shared_labels: Vec<String>,
     pub timestamp: u64,
     pub cursor_position: Option<usize>,
     pub selected_project_id: Option<String>,
+    #[serde(default)]
+    pub selected_label: Option<String>,
+    /// The account's saved filters; local ones live in the config.
+    #[serde(default)]
+    pub filters: Vec<SavedFilter>,
+    #[serde(default)]
+    pub selected_filter: Option<String>,
+    /// The account's first day of the week, as Todoist's `start_day`.
+    #[serde(default)]
+    pub start_day: Option<u8>,
 }
 
 impl CacheData {
-    pub fn new(projects: Vec<Project>, tasks: Vec<Task>, sections: Vec<Section>) -> Self {
-        Self {
-            projects,
-            tasks,
-            sections,
-            timestamp: SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
-            cursor_position: None,
-            selected_project_id: None,
+    /// Snapshot of what the app is showing. Data that is itself still from the
+    /// cache keeps its original timestamp so it does not look fresher than it
+    /// is. Tasks and sections waiting to be created are left out; the queue
+    /// restores them. So are projects the server has not answered for yet.
+    pub fn from_app(app: &App) -> Self {
+        let mut cache_data = Self::with_cursor_info(
+            app.projects
+                .projects
+                .iter()
+                .filter(|project| !project.id.starts_with(TEMP_ID_PREFIX))
+                .cloned()
+                .collect(),
+            app.tasks
+                .tasks
+                .iter()
+                .filter(|task| !task.id.starts_with(TEMP_ID_PREFIX))
+                .cloned()
+                .collect(),
+            app.tasks
+                .sections
+                .sections
+                .iter()
+                .filter(|section| !section.id.starts_with(TEMP_ID_PREFIX))
+                .cloned()
+                .collect(),
+            app.tasks.state.selected(),
+            app.projects.selected_project.clone(),
+        );
+        cache_data.labels = app.labels.labels.clone();
+        cache_data.shared_labels = app.labels.shared.clone();
+        cache_data.selected_label = app.labels.selected_label.clone();
+        cache_data.filters = app.filters.account();
+        cache_data.selected_filter = app.filters.selected_filter.clone();
+        cache_data.start_day = app.tasks.start_day.map(|weekday| weekday.number_from_monday() as u8);
+        if let Some(cached_at) = app.cached_at {
+            cache_data.timestamp = cached_at;
         }
+        cache_data
     }
 
     pub fn with_cursor_info(
@@ -44,17 +88,23 @@ impl CacheData {
             projects,
             tasks,
             sections,
+            labels: Vec::new(),
+            shared_labels: Vec::new(),
             timestamp: SystemTime::now()
                 .duration_since(UNIX_EPOCH)
                 .unwrap()
                 .as_secs(),
             cursor_position,
             selected_project_id,
+            selected_label: None,
+            filters: Vec::new(),
+            selected_filter: None,
+            start_day: None,
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CacheManager {
     cache_file_path: std::path::PathBuf,
 }
@@ -91,19 +141,6 @@ impl CacheManager {
         Ok(Some(cache_data))
     }
 
-    pub fn is_cache_valid(&self, max_age_seconds: u64) -> Result<bool> {
-        match self.load_cache()? {
-            Some(cache_data) => {
-                let current_time = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs();
-                Ok(current_time - cache_data.timestamp < max_age_seconds)
-            }
-            None => Ok(false),
-        }
-    }
-
     pub fn clear_cache(&self) -> Result<()> {
         if self.cache_file_path.exists() {
             fs::remove_file(&self.cache_file_path)?;
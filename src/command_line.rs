@@ -0,0 +1,43 @@
+use tui_textarea::TextArea;
+
+use crate::tasks::UPCOMING_DAYS;
+
+/// The vim-style `:` prompt at the bottom of the screen.
+#[derive(Debug, Default)]
+pub struct CommandLine<'a> {
+    pub input: TextArea<'a>,
+}
+
+/// What a line typed at the prompt asks for.
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    /// `:filter <query>`
+    Filter(String),
+    /// `:upcoming [days]`
+    Upcoming(i64),
+    /// `:upcoming week`, the rest of the current week
+    UpcomingWeek,
+}
+
+impl<'a> CommandLine<'a> {
+    pub fn text(&self) -> String {
+        self.input.lines().join(" ")
+    }
+
+    /// Splits the typed line into a command and its argument.
+    pub fn command(&self) -> Result<Command, String> {
+        let text = self.text();
+        let (name, argument) = text.trim().split_once(' ').unwrap_or((text.trim(), ""));
+        match name {
+            "filter" | "f" if argument.trim().is_empty() => Err("Usage: :filter <query>".to_string()),
+            "filter" | "f" => Ok(Command::Filter(argument.trim().to_string())),
+            "upcoming" | "u" if argument.trim().is_empty() => Ok(Command::Upcoming(UPCOMING_DAYS)),
+            "upcoming" | "u" if matches!(argument.trim(), "week" | "w") => Ok(Command::UpcomingWeek),
+            "upcoming" | "u" => match argument.trim().parse::<i64>() {
+                Ok(days) if days > 0 => Ok(Command::Upcoming(days)),
+                _ => Err("Usage: :upcoming [days|week]".to_string()),
+            },
+            name => Err(format!("Unknown command: {}", name)),
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use ratatui::widgets::ListState;
+use serde::{Deserialize, Serialize};
+use tui_textarea::TextArea;
+
+/// A comment on a task (or project), as returned by `GET /comments`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Comment {
+    pub id: String,
+    pub task_id: Option<String>,
+    pub project_id: Option<String>,
+    pub posted_at: String,
+    pub content: String,
+    /// Who wrote it, where the API says so.
+    #[serde(default)]
+    pub posted_uid: Option<String>,
+}
+
+/// The comments pane opened from the task editor.
+#[derive(Debug, Default)]
+pub struct CommentsView<'a> {
+    pub task_id: String,
+    pub comments: Vec<Comment>,
+    pub state: ListState,
+    pub loading: bool,
+    /// Whether the input box at the bottom has the keyboard.
+    pub composing: bool,
+    /// The comment being edited, or `None` when writing a new one.
+    pub editing: Option<String>,
+    pub input: TextArea<'a>,
+}
+
+impl<'a> CommentsView<'a> {
+    pub fn new(task_id: String) -> Self {
+        CommentsView {
+            task_id,
+            loading: true,
+            ..Default::default()
+        }
+    }
+
+    /// Replaces the list with what the server returned, newest last.
+    pub fn set_comments(&mut self, mut comments: Vec<Comment>) {
+        comments.sort_by(|a, b| a.posted_at.cmp(&b.posted_at));
+        self.comments = comments;
+        self.loading = false;
+        self.state.select(self.comments.len().checked_sub(1));
+    }
+
+    /// Adds a new comment or replaces an edited one. Returns whether it was
+    /// new.
+    pub fn upsert(&mut self, comment: Comment) -> bool {
+        match self.comments.iter_mut().find(|c| c.id == comment.id) {
+            Some(existing) => {
+                *existing = comment;
+                false
+            }
+            None => {
+                self.comments.push(comment);
+                self.state.select(Some(self.comments.len() - 1));
+                true
+            }
+        }
+    }
+
+    pub fn selected(&self) -> Option<&Comment> {
+        self.comments.get(self.state.selected()?)
+    }
+
+    pub fn next(&mut self) {
+        if self.comments.is_empty() {
+            return;
+        }
+        let i = match self.state.selected() {
+            Some(i) if i + 1 < self.comments.len() => i + 1,
+            _ => 0,
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn previous(&mut self) {
+        if self.comments.is_empty() {
+            return;
+        }
+        let i = match self.state.selected() {
+            Some(0) | None => self.comments.len() - 1,
+            Some(i) => i - 1,
+        };
+        self.state.select(Some(i));
+    }
+
+    /// Starts writing a comment, or editing `comment` if given.
+    pub fn compose(&mut self, comment: Option<&Comment>) {
+        self.editing = comment.map(|comment| comment.id.clone());
+        let content = comment.map_or("", |comment| &comment.content);
+        self.input = TextArea::from(content.split('\n'));
+        self.composing = true;
+    }
+
+    pub fn stop_composing(&mut self) {
+        self.composing = false;
+        self.editing = None;
+    }
+}
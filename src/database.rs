@@ -0,0 +1,526 @@
+use std::{fs, path::PathBuf};
+
+use async_trait::async_trait;
+use color_eyre::eyre::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+use crate::{
+    api_error::ApiError,
+    backend::{decode, TaskBackend},
+    comments::Comment,
+    labels::Label,
+    projects::Project,
+    sections::Section,
+    store::{self, Resource, Storage, StoreError},
+    sync::{SyncResponse, FULL_SYNC_TOKEN},
+    tasks::Task,
+};
+
+/// Bump together with a migration in `migrate` when the schema changes.
+const SCHEMA_VERSION: i64 = 2;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS meta (
+        key TEXT PRIMARY KEY,
+        value INTEGER NOT NULL
+    );
+    INSERT OR IGNORE INTO meta (key, value) VALUES ('next_id', 1000);
+
+    CREATE TABLE IF NOT EXISTS projects (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        color TEXT NOT NULL,
+        parent_id TEXT REFERENCES projects(id) ON DELETE CASCADE,
+        child_order INTEGER NOT NULL,
+        is_favorite INTEGER NOT NULL,
+        is_inbox_project INTEGER NOT NULL,
+        view_style TEXT NOT NULL,
+        is_archived INTEGER NOT NULL DEFAULT 0
+    );
+
+    CREATE TABLE IF NOT EXISTS sections (
+        id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+        name TEXT NOT NULL,
+        section_order INTEGER NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS tasks (
+        id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+        section_id TEXT REFERENCES sections(id) ON DELETE CASCADE,
+        parent_id TEXT REFERENCES tasks(id) ON DELETE CASCADE,
+        content TEXT NOT NULL,
+        description TEXT NOT NULL,
+        priority INTEGER NOT NULL,
+        labels TEXT NOT NULL,
+        due TEXT,
+        child_order INTEGER NOT NULL,
+        is_completed INTEGER NOT NULL,
+        created_at TEXT NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS labels (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        color TEXT NOT NULL,
+        item_order INTEGER NOT NULL,
+        is_favorite INTEGER NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS comments (
+        id TEXT PRIMARY KEY,
+        task_id TEXT REFERENCES tasks(id) ON DELETE CASCADE,
+        project_id TEXT REFERENCES projects(id) ON DELETE CASCADE,
+        content TEXT NOT NULL,
+        posted_at TEXT NOT NULL
+    );
+
+    CREATE INDEX IF NOT EXISTS tasks_by_project ON tasks(project_id);
+    CREATE INDEX IF NOT EXISTS tasks_by_parent ON tasks(parent_id);
+    CREATE INDEX IF NOT EXISTS comments_by_task ON comments(task_id);
+";
+
+/// Everything kept in an SQLite database on this machine, for people without
+/// a Todoist account. Rows are converted to and from the REST JSON shapes so
+/// the rest of the app cannot tell the difference.
+#[derive(Debug)]
+pub struct SqliteBackend {
+    connection: std::sync::Mutex<Connection>,
+}
+
+impl SqliteBackend {
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|data| data.join("todoist-vim").join("todoist.db"))
+    }
+
+    /// Opens the database, creating it with an empty inbox on first use.
+    pub fn open(path: PathBuf) -> Result<SqliteBackend> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let connection = Connection::open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        connection.pragma_update(None, "foreign_keys", true)?;
+        migrate(&connection).context("Failed to set up the local database")?;
+
+        let mut store = SqliteStore { connection: &connection };
+        if store.inbox_id().map_err(ApiError::from)?.is_null() {
+            let inbox = json!({ "name": "Inbox", "color": "grey", "is_inbox_project": true });
+            store.insert(Resource::Projects, &inbox).map_err(ApiError::from)?;
+        }
+
+        Ok(SqliteBackend {
+            connection: std::sync::Mutex::new(connection),
+        })
+    }
+
+    /// Runs `query` on the store and decodes what it returns; writes happen
+    /// in a transaction so a failed request changes nothing.
+    fn run<T: DeserializeOwned>(
+        &self,
+        query: impl FnOnce(&mut SqliteStore) -> Result<Value, StoreError>,
+    ) -> Result<T, ApiError> {
+        let mut connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        let transaction = connection.transaction()?;
+        let value = query(&mut SqliteStore { connection: &transaction })?;
+        transaction.commit()?;
+        decode(value)
+    }
+}
+
+fn migrate(connection: &Connection) -> rusqlite::Result<()> {
+    let version: i64 = connection.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if version < 1 {
+        connection.execute_batch(SCHEMA)?;
+    } else if version < 2 {
+        connection.execute_batch(
+            "ALTER TABLE projects ADD COLUMN is_archived INTEGER NOT NULL DEFAULT 0",
+        )?;
+    }
+    if version < SCHEMA_VERSION {
+        connection.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+    }
+    Ok(())
+}
+
+fn table(resource: Resource) -> &'static str {
+    match resource {
+        Resource::Projects => "projects",
+        Resource::Sections => "sections",
+        Resource::Tasks => "tasks",
+        Resource::Labels => "labels",
+        Resource::Comments => "comments",
+    }
+}
+
+/// The SELECT for a resource, with columns in the order `to_json` reads them.
+fn select(resource: Resource) -> &'static str {
+    match resource {
+        Resource::Projects => {
+            "SELECT id, name, color, parent_id, child_order, is_favorite, is_inbox_project, view_style,
+                (SELECT COUNT(*) FROM comments WHERE comments.project_id = projects.id), is_archived
+             FROM projects"
+        }
+        Resource::Sections => "SELECT id, project_id, name, section_order FROM sections",
+        Resource::Tasks => {
+            "SELECT id, project_id, section_id, parent_id, content, description, priority, labels,
+                due, child_order, is_completed, created_at,
+                (SELECT COUNT(*) FROM comments WHERE comments.task_id = tasks.id)
+             FROM tasks"
+        }
+        Resource::Labels => "SELECT id, name, color, item_order, is_favorite FROM labels",
+        Resource::Comments => "SELECT id, task_id, project_id, content, posted_at FROM comments",
+    }
+}
+
+/// Converts a row into the JSON the REST API returns for the resource.
+fn to_json(resource: Resource, row: &Row) -> rusqlite::Result<Value> {
+    let id: String = row.get(0)?;
+    let json_column = |index: usize| -> rusqlite::Result<Value> {
+        let text: Option<String> = row.get(index)?;
+        Ok(text
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or(Value::Null))
+    };
+
+    let mut value = store::template(resource, &id, 0, Value::Null);
+    match resource {
+        Resource::Projects => {
+            value["name"] = json!(row.get::<_, String>(1)?);
+            value["color"] = json!(row.get::<_, String>(2)?);
+            value["parent_id"] = json!(row.get::<_, Option<String>>(3)?);
+            value["order"] = json!(row.get::<_, i64>(4)?);
+            value["is_favorite"] = json!(row.get::<_, bool>(5)?);
+            value["is_inbox_project"] = json!(row.get::<_, bool>(6)?);
+            value["view_style"] = json!(row.get::<_, String>(7)?);
+            value["comment_count"] = json!(row.get::<_, i64>(8)?);
+            value["is_archived"] = json!(row.get::<_, bool>(9)?);
+        }
+        Resource::Sections => {
+            value["project_id"] = json!(row.get::<_, String>(1)?);
+            value["name"] = json!(row.get::<_, String>(2)?);
+            value["order"] = json!(row.get::<_, i64>(3)?);
+        }
+        Resource::Tasks => {
+            value["project_id"] = json!(row.get::<_, String>(1)?);
+            value["section_id"] = json!(row.get::<_, Option<String>>(2)?);
+            value["parent_id"] = json!(row.get::<_, Option<String>>(3)?);
+            value["content"] = json!(row.get::<_, String>(4)?);
+            value["description"] = json!(row.get::<_, String>(5)?);
+            value["priority"] = json!(row.get::<_, i64>(6)?);
+            value["labels"] = json_column(7)?;
+            value["due"] = json_column(8)?;
+            value["order"] = json!(row.get::<_, i64>(9)?);
+            value["is_completed"] = json!(row.get::<_, bool>(10)?);
+            value["created_at"] = json!(row.get::<_, String>(11)?);
+            value["comment_count"] = json!(row.get::<_, i64>(12)?);
+        }
+        Resource::Labels => {
+            value["name"] = json!(row.get::<_, String>(1)?);
+            value["color"] = json!(row.get::<_, String>(2)?);
+            value["order"] = json!(row.get::<_, i64>(3)?);
+            value["is_favorite"] = json!(row.get::<_, bool>(4)?);
+        }
+        Resource::Comments => {
+            value["task_id"] = json!(row.get::<_, Option<String>>(1)?);
+            value["project_id"] = json!(row.get::<_, Option<String>>(2)?);
+            value["content"] = json!(row.get::<_, String>(3)?);
+            value["posted_at"] = json!(row.get::<_, String>(4)?);
+        }
+    }
+    Ok(value)
+}
+
+/// A connection, or the transaction of one request, seen as a `Storage`.
+struct SqliteStore<'a> {
+    connection: &'a Connection,
+}
+
+impl SqliteStore<'_> {
+    fn query(
+        &self,
+        resource: Resource,
+        condition: &str,
+        parameters: impl rusqlite::Params,
+    ) -> Result<Vec<Value>, StoreError> {
+        let sql = format!("{} WHERE {}", select(resource), condition);
+        let mut statement = self.connection.prepare(&sql).map_err(storage_error)?;
+        let rows = statement
+            .query_map(parameters, |row| to_json(resource, row))
+            .map_err(storage_error)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(storage_error)
+    }
+}
+
+impl Storage for SqliteStore<'_> {
+    fn next_id(&mut self) -> Result<String, StoreError> {
+        let id: i64 = self
+            .connection
+            .query_row(
+                "UPDATE meta SET value = value + 1 WHERE key = 'next_id' RETURNING value",
+                [],
+                |row| row.get(0),
+            )
+            .map_err(storage_error)?;
+        Ok(id.to_string())
+    }
+
+    fn all(&self, resource: Resource) -> Result<Vec<Value>, StoreError> {
+        self.query(resource, "1 ORDER BY rowid", [])
+    }
+
+    fn get(&self, resource: Resource, id: &str) -> Result<Option<Value>, StoreError> {
+        Ok(self.query(resource, "id = ?1", [id])?.pop())
+    }
+
+    fn count(&self, resource: Resource) -> Result<usize, StoreError> {
+        let count: i64 = self
+            .connection
+            .query_row(&format!("SELECT COUNT(*) FROM {}", table(resource)), [], |row| row.get(0))
+            .map_err(storage_error)?;
+        Ok(count as usize)
+    }
+
+    fn inbox_id(&self) -> Result<Value, StoreError> {
+        let id: Option<String> = self
+            .connection
+            .query_row("SELECT id FROM projects WHERE is_inbox_project = 1", [], |row| row.get(0))
+            .optional()
+            .map_err(storage_error)?;
+        Ok(id.map(Value::from).unwrap_or(Value::Null))
+    }
+
+    /// Writes the REST JSON of a resource to its row, inserting it if needed.
+    fn put(&mut self, resource: Resource, value: Value) -> Result<(), StoreError> {
+        let text = |field: &str| value[field].as_str().map(str::to_string);
+        let number = |field: &str| value[field].as_i64().unwrap_or_default();
+        let flag = |field: &str| value[field] == true;
+        let json_text = |field: &str| match &value[field] {
+            Value::Null => None,
+            other => Some(other.to_string()),
+        };
+
+        match resource {
+            Resource::Projects => self.connection.execute(
+                "INSERT INTO projects (id, name, color, parent_id, child_order, is_favorite, is_inbox_project,
+                    view_style, is_archived)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                 ON CONFLICT(id) DO UPDATE SET name = excluded.name, color = excluded.color,
+                    parent_id = excluded.parent_id, child_order = excluded.child_order,
+                    is_favorite = excluded.is_favorite, view_style = excluded.view_style,
+                    is_archived = excluded.is_archived",
+                params![
+                    text("id"),
+                    text("name"),
+                    text("color"),
+                    text("parent_id"),
+                    number("order"),
+                    flag("is_favorite"),
+                    flag("is_inbox_project"),
+                    text("view_style"),
+                    flag("is_archived"),
+                ],
+            ),
+            Resource::Sections => self.connection.execute(
+                "INSERT INTO sections (id, project_id, name, section_order) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(id) DO UPDATE SET project_id = excluded.project_id, name = excluded.name,
+                    section_order = excluded.section_order",
+                params![text("id"), text("project_id"), text("name"), number("order")],
+            ),
+            Resource::Tasks => self.connection.execute(
+                "INSERT INTO tasks (id, project_id, section_id, parent_id, content, description, priority,
+                    labels, due, child_order, is_completed, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+                 ON CONFLICT(id) DO UPDATE SET project_id = excluded.project_id,
+                    section_id = excluded.section_id, parent_id = excluded.parent_id,
+                    content = excluded.content, description = excluded.description,
+                    priority = excluded.priority, labels = excluded.labels, due = excluded.due,
+                    child_order = excluded.child_order, is_completed = excluded.is_completed",
+                params![
+                    text("id"),
+                    text("project_id"),
+                    text("section_id"),
+                    text("parent_id"),
+                    text("content"),
+                    text("description"),
+                    number("priority"),
+                    json_text("labels").unwrap_or_else(|| "[]".to_string()),
+                    json_text("due"),
+                    number("order"),
+                    flag("is_completed"),
+                    text("created_at"),
+                ],
+            ),
+            Resource::Labels => self.connection.execute(
+                "INSERT INTO labels (id, name, color, item_order, is_favorite) VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT(id) DO UPDATE SET name = excluded.name, color = excluded.color,
+                    item_order = excluded.item_order, is_favorite = excluded.is_favorite",
+                params![text("id"), text("name"), text("color"), number("order"), flag("is_favorite")],
+            ),
+            Resource::Comments => self.connection.execute(
+                "INSERT INTO comments (id, task_id, project_id, content, posted_at) VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT(id) DO UPDATE SET content = excluded.content",
+                params![text("id"), text("task_id"), text("project_id"), text("content"), text("posted_at")],
+            ),
+        }
+        .map_err(invalid_reference)?;
+        Ok(())
+    }
+
+    fn remove(&mut self, resource: Resource, id: &str) -> Result<(), StoreError> {
+        let sql = format!("DELETE FROM {} WHERE id = ?1", table(resource));
+        self.connection.execute(&sql, [id]).map_err(storage_error)?;
+        Ok(())
+    }
+}
+
+fn storage_error(e: rusqlite::Error) -> StoreError {
+    StoreError::Storage(e.to_string())
+}
+
+/// A reference to a project, section or task that does not exist is the
+/// caller's mistake, not a storage failure.
+fn invalid_reference(e: rusqlite::Error) -> StoreError {
+    match e.sqlite_error_code() {
+        Some(rusqlite::ErrorCode::ConstraintViolation) => {
+            StoreError::Invalid(format!("Invalid reference: {}", e))
+        }
+        _ => storage_error(e),
+    }
+}
+
+#[async_trait]
+impl TaskBackend for SqliteBackend {
+    async fn list_projects(&self) -> Result<Vec<Project>, ApiError> {
+        self.run(|db| db.list(Resource::Projects).map(Value::from))
+    }
+
+    async fn create_project(&self, json: Value) -> Result<Project, ApiError> {
+        self.run(|db| db.create(Resource::Projects, &json))
+    }
+
+    async fn update_project(&self, project_id: String, json: Value) -> Result<Project, ApiError> {
+        self.run(|db| db.update(Resource::Projects, &project_id, &json))
+    }
+
+    async fn delete_project(&self, project_id: String) -> Result<(), ApiError> {
+        self.run(|db| db.delete(Resource::Projects, &project_id).map(|_| Value::Null))
+    }
+
+    async fn list_archived_projects(&self) -> Result<Vec<Project>, ApiError> {
+        self.run(|db| db.archived_projects().map(Value::from))
+    }
+
+    async fn archive_project(&self, project_id: String) -> Result<(), ApiError> {
+        self.run(|db| db.set_archived(&project_id, true).map(|_| Value::Null))
+    }
+
+    async fn unarchive_project(&self, project_id: String) -> Result<(), ApiError> {
+        self.run(|db| db.set_archived(&project_id, false).map(|_| Value::Null))
+    }
+
+    async fn list_sections(&self) -> Result<Vec<Section>, ApiError> {
+        self.run(|db| db.list(Resource::Sections).map(Value::from))
+    }
+
+    async fn create_section(&self, json: Value) -> Result<Section, ApiError> {
+        self.run(|db| db.create(Resource::Sections, &json))
+    }
+
+    async fn update_section(&self, section_id: String, json: Value) -> Result<Section, ApiError> {
+        self.run(|db| db.update(Resource::Sections, &section_id, &json))
+    }
+
+    async fn delete_section(&self, section_id: String) -> Result<(), ApiError> {
+        self.run(|db| db.delete(Resource::Sections, &section_id).map(|_| Value::Null))
+    }
+
+    async fn reorder_sections(&self, json: Value) -> Result<(), ApiError> {
+        self.run(|db| db.reorder(Resource::Sections, &json).map(|_| Value::Null))
+    }
+
+    async fn list_labels(&self) -> Result<Vec<Label>, ApiError> {
+        self.run(|db| db.list(Resource::Labels).map(Value::from))
+    }
+
+    async fn list_shared_labels(&self) -> Result<Vec<String>, ApiError> {
+        self.run(|db| db.shared_labels().map(Value::from))
+    }
+
+    async fn create_label(&self, json: Value) -> Result<Label, ApiError> {
+        self.run(|db| db.create(Resource::Labels, &json))
+    }
+
+    async fn update_label(&self, label_id: String, json: Value) -> Result<Label, ApiError> {
+        self.run(|db| db.update(Resource::Labels, &label_id, &json))
+    }
+
+    async fn delete_label(&self, label_id: String) -> Result<(), ApiError> {
+        self.run(|db| db.delete(Resource::Labels, &label_id).map(|_| Value::Null))
+    }
+
+    async fn list_tasks(&self) -> Result<Vec<Task>, ApiError> {
+        self.run(|db| db.list(Resource::Tasks).map(Value::from))
+    }
+
+    async fn get_task(&self, task_id: String) -> Result<Task, ApiError> {
+        self.run(|db| db.get(Resource::Tasks, &task_id)?.ok_or(StoreError::NotFound))
+    }
+
+    async fn create_task(&self, json: Value, _request_id: String) -> Result<Task, ApiError> {
+        self.run(|db| db.create(Resource::Tasks, &json))
+    }
+
+    async fn update_task(&self, task_id: String, json: Value) -> Result<Task, ApiError> {
+        self.run(|db| db.update(Resource::Tasks, &task_id, &json))
+    }
+
+    async fn close_task(&self, task_id: String, _uuid: String) -> Result<(), ApiError> {
+        self.run(|db| db.set_completed(&task_id, true).map(|_| Value::Null))
+    }
+
+    async fn reopen_task(&self, task_id: String) -> Result<(), ApiError> {
+        self.run(|db| db.set_completed(&task_id, false).map(|_| Value::Null))
+    }
+
+    async fn delete_task(&self, task_id: String) -> Result<(), ApiError> {
+        match self.run(|db| db.delete(Resource::Tasks, &task_id).map(|_| Value::Null)) {
+            Ok(()) | Err(ApiError::NotFound) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn move_task(&self, task_id: String, json: Value) -> Result<(), ApiError> {
+        self.run(|db| db.move_task(&task_id, &json).map(|_| Value::Null))
+    }
+
+    async fn reorder_tasks(&self, json: Value) -> Result<(), ApiError> {
+        self.run(|db| db.reorder(Resource::Tasks, &json).map(|_| Value::Null))
+    }
+
+    async fn list_comments(&self, task_id: String) -> Result<Vec<Comment>, ApiError> {
+        self.run(|db| db.comments_for(&task_id).map(Value::from))
+    }
+
+    async fn create_comment(&self, json: Value) -> Result<Comment, ApiError> {
+        self.run(|db| db.create(Resource::Comments, &json))
+    }
+
+    async fn update_comment(&self, comment_id: String, json: Value) -> Result<Comment, ApiError> {
+        self.run(|db| db.update(Resource::Comments, &comment_id, &json))
+    }
+
+    async fn delete_comment(&self, comment_id: String) -> Result<(), ApiError> {
+        self.run(|db| db.delete(Resource::Comments, &comment_id).map(|_| Value::Null))
+    }
+
+    /// Nothing else writes to the database while the app runs, so every sync
+    /// is simply a full snapshot.
+    async fn sync(&self, _sync_token: String) -> Result<SyncResponse, ApiError> {
+        self.run(|db| db.snapshot(FULL_SYNC_TOKEN))
+    }
+}
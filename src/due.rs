@@ -0,0 +1,487 @@
+use chrono::{
+    DateTime, Datelike, Duration, Local, Months, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday,
+};
+use serde::{Deserialize, Serialize};
+
+/// When a task is due, parsed from the API's `date`/`datetime` pair.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DueAt {
+    /// All day on a calendar date.
+    Date(NaiveDate),
+    /// A wall-clock time that stays the same wherever the user is.
+    Floating(NaiveDateTime),
+    /// A fixed moment, set in a specific timezone.
+    Fixed(DateTime<Utc>),
+}
+
+/// A task's due date. It reads and writes the same JSON as the API, but the
+/// date and time are parsed once so filtering, sorting and display all agree.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "ApiDue", into = "ApiDue")]
+pub struct Due {
+    pub string: String,
+    pub is_recurring: bool,
+    pub timezone: Option<String>,
+    pub at: DueAt,
+}
+
+/// The due object exactly as the REST API sends it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ApiDue {
+    #[serde(default)]
+    string: String,
+    date: String,
+    #[serde(default)]
+    is_recurring: bool,
+    datetime: Option<String>,
+    timezone: Option<String>,
+}
+
+impl Due {
+    /// Builds a due date from the API's fields. A `datetime` ending in `Z` (or
+    /// with an offset) is a fixed moment, one without is floating.
+    pub fn new(
+        string: String,
+        date: &str,
+        datetime: Option<&str>,
+        is_recurring: bool,
+        timezone: Option<String>,
+    ) -> Result<Due, String> {
+        let fixed = datetime.and_then(|datetime| DateTime::parse_from_rfc3339(datetime).ok());
+        let floating = datetime.and_then(|datetime| {
+            NaiveDateTime::parse_from_str(datetime, "%Y-%m-%dT%H:%M:%S%.f").ok()
+        });
+
+        let at = match (fixed, floating) {
+            (Some(fixed), _) => DueAt::Fixed(fixed.with_timezone(&Utc)),
+            (None, Some(floating)) => DueAt::Floating(floating),
+            (None, None) => {
+                let date = date.get(..10).unwrap_or(date);
+                let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                    .map_err(|e| format!("Invalid due date '{}': {}", date, e))?;
+                DueAt::Date(date)
+            }
+        };
+
+        Ok(Due {
+            string,
+            is_recurring,
+            timezone,
+            at,
+        })
+    }
+
+    /// The due time on the user's clock, or `None` for all-day tasks.
+    pub fn local_datetime(&self) -> Option<NaiveDateTime> {
+        match self.at {
+            DueAt::Date(_) => None,
+            DueAt::Floating(datetime) => Some(datetime),
+            DueAt::Fixed(datetime) => Some(datetime.with_timezone(&Local).naive_local()),
+        }
+    }
+
+    /// The day the task is due on the user's calendar.
+    pub fn local_date(&self) -> NaiveDate {
+        match self.at {
+            DueAt::Date(date) => date,
+            _ => self.local_datetime().map(|datetime| datetime.date()).unwrap_or_default(),
+        }
+    }
+
+    /// All-day tasks are overdue from the next day on, timed ones as soon as
+    /// their time has passed.
+    pub fn is_overdue(&self, now: NaiveDateTime) -> bool {
+        match self.local_datetime() {
+            Some(datetime) => datetime < now,
+            None => self.local_date() < now.date(),
+        }
+    }
+
+    pub fn is_due_on(&self, date: NaiveDate) -> bool {
+        self.local_date() == date
+    }
+
+    /// Orders by local day, with timed tasks before all-day ones on the same
+    /// day.
+    pub fn sort_key(&self) -> (NaiveDate, bool, NaiveTime) {
+        let datetime = self.local_datetime();
+        (
+            self.local_date(),
+            datetime.is_none(),
+            datetime.map(|datetime| datetime.time()).unwrap_or_default(),
+        )
+    }
+}
+
+/// How many steps a recurring date may take to get past today before the
+/// rule is given up on.
+const MAX_CATCH_UP_STEPS: u32 = 100_000;
+
+/// How far apart the occurrences of a recurring due date are.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Step {
+    Days(i64),
+    Months(u32),
+    Weekday(Weekday),
+    Workday,
+}
+
+impl Step {
+    /// Reads the rule out of strings like "every day", "every 2 weeks at
+    /// 9am", "every other month" or "every! monday". Returns the step and
+    /// whether it counts from the completion date ("every!").
+    fn parse(string: &str) -> Option<(Step, bool)> {
+        let string = string.trim().to_lowercase();
+        let (rest, from_completion) = match string.strip_prefix("every!") {
+            Some(rest) => (rest, true),
+            None => (string.strip_prefix("every")?, false),
+        };
+        let rest = rest.split(" at ").next().unwrap_or_default();
+        let mut words = rest.split_whitespace().peekable();
+
+        let count = match words.peek().copied() {
+            Some("other") => {
+                words.next();
+                2
+            }
+            Some(word) => match word.parse::<u32>() {
+                // A step of nothing would never get past today
+                Ok(0) => return None,
+                Ok(count) => {
+                    words.next();
+                    count
+                }
+                Err(_) => 1,
+            },
+            None => return None,
+        };
+
+        let unit = words.next()?;
+        let step = match unit.trim_end_matches('s') {
+            "day" => Step::Days(count as i64),
+            "week" => Step::Days(7 * count as i64),
+            "month" => Step::Months(count),
+            "year" => Step::Months(count.checked_mul(12)?),
+            "weekday" | "workday" => Step::Workday,
+            "daily" => Step::Days(1),
+            "weekly" => Step::Days(7),
+            "monthly" => Step::Months(1),
+            "yearly" => Step::Months(12),
+            name => Step::Weekday(name.parse::<Weekday>().ok()?),
+        };
+        Some((step, from_completion))
+    }
+
+    /// The next date the rule falls on, or `None` past the end of the
+    /// calendar.
+    fn advance(self, date: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Step::Days(days) => date.checked_add_signed(Duration::try_days(days)?),
+            Step::Months(months) => date.checked_add_months(Months::new(months)),
+            Step::Weekday(weekday) => {
+                let days_ahead = (7 + weekday.num_days_from_monday() as i64
+                    - date.weekday().num_days_from_monday() as i64
+                    - 1)
+                    % 7
+                    + 1;
+                date.checked_add_signed(Duration::days(days_ahead))
+            }
+            Step::Workday => {
+                let mut next = date.succ_opt()?;
+                while matches!(next.weekday(), Weekday::Sat | Weekday::Sun) {
+                    next = next.succ_opt()?;
+                }
+                Some(next)
+            }
+        }
+    }
+}
+
+/// The first date of a recurring due string such as "every monday", counted
+/// from `today`: today itself, or the next day the rule falls on. `None` if
+/// the rule is not understood.
+pub fn first_occurrence(string: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let (step, _) = Step::parse(string)?;
+    match step {
+        Step::Weekday(_) | Step::Workday => step.advance(today.pred_opt()?),
+        Step::Days(_) | Step::Months(_) => Some(today),
+    }
+}
+
+impl Due {
+    /// Where a recurring task moves when it is completed on `today`: the first
+    /// occurrence after both its current date and today, or one step after
+    /// today for "every!" rules. `None` if the rule is not understood.
+    pub fn next_occurrence(&self, today: NaiveDate) -> Option<Due> {
+        if !self.is_recurring {
+            return None;
+        }
+        let (step, from_completion) = Step::parse(&self.string)?;
+
+        let current = self.local_date();
+        let mut next = step.advance(if from_completion { today } else { current })?;
+        // Even a daily task left for decades catches up well within this
+        let mut steps = 0;
+        while next <= today {
+            steps += 1;
+            if steps > MAX_CATCH_UP_STEPS {
+                return None;
+            }
+            next = step.advance(next)?;
+        }
+
+        let days = next - current;
+        let at = match self.at {
+            DueAt::Date(_) => DueAt::Date(next),
+            DueAt::Floating(datetime) => DueAt::Floating(datetime.checked_add_signed(days)?),
+            DueAt::Fixed(datetime) => DueAt::Fixed(datetime.checked_add_signed(days)?),
+        };
+        Some(Due { at, ..self.clone() })
+    }
+}
+
+impl TryFrom<ApiDue> for Due {
+    type Error = String;
+
+    fn try_from(due: ApiDue) -> Result<Self, Self::Error> {
+        Due::new(
+            due.string,
+            &due.date,
+            due.datetime.as_deref(),
+            due.is_recurring,
+            due.timezone,
+        )
+    }
+}
+
+impl From<Due> for ApiDue {
+    fn from(due: Due) -> Self {
+        let datetime = match due.at {
+            DueAt::Date(_) => None,
+            DueAt::Floating(datetime) => Some(datetime.format("%Y-%m-%dT%H:%M:%S").to_string()),
+            DueAt::Fixed(datetime) => Some(datetime.format("%Y-%m-%dT%H:%M:%SZ").to_string()),
+        };
+        ApiDue {
+            date: due.local_date().format("%Y-%m-%d").to_string(),
+            string: due.string,
+            is_recurring: due.is_recurring,
+            datetime,
+            timezone: due.timezone,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    fn datetime(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn a_date_alone_is_all_day() {
+        let due = Due::new("tomorrow".to_string(), "2024-10-17", None, false, None).unwrap();
+        assert_eq!(due.at, DueAt::Date(date("2024-10-17")));
+        assert_eq!(due.local_datetime(), None);
+        assert_eq!(due.local_date(), date("2024-10-17"));
+
+        assert!(!due.is_overdue(datetime("2024-10-17T23:59:00")));
+        assert!(due.is_overdue(datetime("2024-10-18T00:00:00")));
+    }
+
+    #[test]
+    fn a_datetime_without_offset_is_floating() {
+        let due = Due::new(
+            "tomorrow at 9am".to_string(),
+            "2024-10-17",
+            Some("2024-10-17T09:00:00"),
+            false,
+            None,
+        )
+        .unwrap();
+        assert_eq!(due.at, DueAt::Floating(datetime("2024-10-17T09:00:00")));
+        assert_eq!(due.local_datetime(), Some(datetime("2024-10-17T09:00:00")));
+
+        assert!(!due.is_overdue(datetime("2024-10-17T08:59:00")));
+        assert!(due.is_overdue(datetime("2024-10-17T09:01:00")));
+    }
+
+    #[test]
+    fn a_datetime_with_offset_is_fixed() {
+        let due = Due::new(
+            "tomorrow at 9am".to_string(),
+            "2024-10-17",
+            Some("2024-10-17T09:00:00Z"),
+            false,
+            Some("Europe/London".to_string()),
+        )
+        .unwrap();
+        let moment = DateTime::parse_from_rfc3339("2024-10-17T09:00:00Z").unwrap().with_timezone(&Utc);
+        assert_eq!(due.at, DueAt::Fixed(moment));
+        assert_eq!(due.local_datetime(), Some(moment.with_timezone(&Local).naive_local()));
+
+        let offset = Due::new(String::new(), "2024-10-17", Some("2024-10-17T11:00:00+02:00"), false, None).unwrap();
+        assert_eq!(offset.at, due.at);
+    }
+
+    #[test]
+    fn a_bad_date_is_refused() {
+        let error = Due::new(String::new(), "17/10/2024", None, false, None).unwrap_err();
+        assert!(error.starts_with("Invalid due date '17/10/2024'"), "{}", error);
+    }
+
+    #[test]
+    fn reads_and_writes_the_api_json() {
+        let json = serde_json::json!({
+            "string": "every day at 9am",
+            "date": "2024-10-17",
+            "datetime": "2024-10-17T09:00:00",
+            "is_recurring": true,
+            "timezone": null,
+        });
+        let due: Due = serde_json::from_value(json.clone()).unwrap();
+        assert!(due.is_recurring);
+        assert_eq!(due.at, DueAt::Floating(datetime("2024-10-17T09:00:00")));
+        assert_eq!(serde_json::to_value(&due).unwrap(), json);
+
+        let fixed: Due = serde_json::from_value(serde_json::json!({
+            "date": "2024-10-17",
+            "datetime": "2024-10-17T09:00:00Z",
+        }))
+        .unwrap();
+        assert_eq!(
+            serde_json::to_value(&fixed).unwrap()["datetime"],
+            "2024-10-17T09:00:00Z"
+        );
+
+        let bad = serde_json::from_value::<Due>(serde_json::json!({ "date": "soon" }));
+        assert!(bad.is_err());
+    }
+
+    #[test]
+    fn timed_tasks_sort_before_all_day_ones() {
+        let all_day = Due::new(String::new(), "2024-10-17", None, false, None).unwrap();
+        let timed = Due::new(String::new(), "2024-10-17", Some("2024-10-17T18:00:00"), false, None).unwrap();
+        let earlier = Due::new(String::new(), "2024-10-16", None, false, None).unwrap();
+        assert!(timed.sort_key() < all_day.sort_key());
+        assert!(earlier.sort_key() < timed.sort_key());
+    }
+
+    fn recurring(string: &str, day: &str) -> Due {
+        Due::new(string.to_string(), day, None, true, None).unwrap()
+    }
+
+    #[test]
+    fn parses_recurring_rules() {
+        assert_eq!(Step::parse("every day"), Some((Step::Days(1), false)));
+        assert_eq!(Step::parse("Every 3 days at 9am"), Some((Step::Days(3), false)));
+        assert_eq!(Step::parse("every other week"), Some((Step::Days(14), false)));
+        assert_eq!(Step::parse("every 2 months"), Some((Step::Months(2), false)));
+        assert_eq!(Step::parse("every other year"), Some((Step::Months(24), false)));
+        assert_eq!(Step::parse("every weekday"), Some((Step::Workday, false)));
+        assert_eq!(Step::parse("every monday at 10:00"), Some((Step::Weekday(Weekday::Mon), false)));
+        assert_eq!(Step::parse("every! fri"), Some((Step::Weekday(Weekday::Fri), true)));
+        assert_eq!(Step::parse("every! 2 weeks"), Some((Step::Days(14), true)));
+
+        assert_eq!(Step::parse("every"), None);
+        assert_eq!(Step::parse("every 0 days"), None);
+        assert_eq!(Step::parse("every! 0 months"), None);
+        assert_eq!(Step::parse("every 4000000000 years"), None);
+        assert_eq!(Step::parse("every blue moon"), None);
+        assert_eq!(Step::parse("tomorrow"), None);
+    }
+
+    #[test]
+    fn moves_past_both_the_due_date_and_today() {
+        // Completed on time
+        let due = recurring("every day", "2024-10-16");
+        assert_eq!(due.next_occurrence(date("2024-10-16")).unwrap().local_date(), date("2024-10-17"));
+
+        // Completed late, the rule keeps its rhythm from the due date
+        let due = recurring("every 3 days", "2024-10-10");
+        assert_eq!(due.next_occurrence(date("2024-10-16")).unwrap().local_date(), date("2024-10-19"));
+
+        // "every!" counts from the day it was completed
+        let due = recurring("every! 3 days", "2024-10-10");
+        assert_eq!(due.next_occurrence(date("2024-10-16")).unwrap().local_date(), date("2024-10-19"));
+        let due = recurring("every! 3 days", "2024-10-15");
+        assert_eq!(due.next_occurrence(date("2024-10-16")).unwrap().local_date(), date("2024-10-19"));
+
+        let due = recurring("every other week", "2024-10-16");
+        assert_eq!(due.next_occurrence(date("2024-10-16")).unwrap().local_date(), date("2024-10-30"));
+    }
+
+    #[test]
+    fn weekdays_and_workdays_skip_ahead() {
+        // 2024-10-18 is a Friday
+        let due = recurring("every friday", "2024-10-18");
+        assert_eq!(due.next_occurrence(date("2024-10-18")).unwrap().local_date(), date("2024-10-25"));
+
+        let due = recurring("every weekday", "2024-10-18");
+        assert_eq!(due.next_occurrence(date("2024-10-18")).unwrap().local_date(), date("2024-10-21"));
+
+        assert_eq!(first_occurrence("every friday", date("2024-10-18")), Some(date("2024-10-18")));
+        assert_eq!(first_occurrence("every monday", date("2024-10-18")), Some(date("2024-10-21")));
+        assert_eq!(first_occurrence("every weekday", date("2024-10-19")), Some(date("2024-10-21")));
+        assert_eq!(first_occurrence("every month", date("2024-10-19")), Some(date("2024-10-19")));
+        assert_eq!(first_occurrence("someday", date("2024-10-19")), None);
+    }
+
+    #[test]
+    fn months_stop_at_the_end_of_shorter_months() {
+        let due = recurring("every month", "2024-01-31");
+        assert_eq!(due.next_occurrence(date("2024-01-31")).unwrap().local_date(), date("2024-02-29"));
+
+        let due = recurring("every year", "2024-02-29");
+        assert_eq!(due.next_occurrence(date("2024-02-29")).unwrap().local_date(), date("2025-02-28"));
+
+        let due = recurring("every other month", "2024-08-31");
+        assert_eq!(due.next_occurrence(date("2024-08-31")).unwrap().local_date(), date("2024-10-31"));
+        let due = recurring("every other month", "2024-12-31");
+        assert_eq!(due.next_occurrence(date("2024-12-31")).unwrap().local_date(), date("2025-02-28"));
+    }
+
+    #[test]
+    fn keeps_the_time_of_day() {
+        let due = Due::new(
+            "every day at 9am".to_string(),
+            "2024-10-16",
+            Some("2024-10-16T09:00:00"),
+            true,
+            None,
+        )
+        .unwrap();
+        let next = due.next_occurrence(date("2024-10-16")).unwrap();
+        assert_eq!(next.at, DueAt::Floating(datetime("2024-10-17T09:00:00")));
+        assert_eq!(next.string, "every day at 9am");
+        assert!(next.is_recurring);
+    }
+
+    #[test]
+    fn only_recurring_rules_move() {
+        let once = Due::new("every day".to_string(), "2024-10-16", None, false, None).unwrap();
+        assert!(once.next_occurrence(date("2024-10-16")).is_none());
+        let unknown = recurring("every blue moon", "2024-10-16");
+        assert!(unknown.next_occurrence(date("2024-10-16")).is_none());
+        let never = recurring("every 0 days", "2024-10-16");
+        assert!(never.next_occurrence(date("2024-10-16")).is_none());
+    }
+
+    #[test]
+    fn gives_up_past_the_end_of_the_calendar() {
+        let huge = recurring("every 4000000000 months", "2024-10-16");
+        assert!(huge.next_occurrence(date("2024-10-16")).is_none());
+        let last = Due {
+            string: "every day".to_string(),
+            is_recurring: true,
+            timezone: None,
+            at: DueAt::Date(NaiveDate::MAX),
+        };
+        assert!(last.next_occurrence(NaiveDate::MAX).is_none());
+    }
+}
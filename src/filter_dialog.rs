@@ -0,0 +1,95 @@
+use tui_textarea::{CursorMove, TextArea};
+
+use crate::{filters::SavedFilter, projects::COLORS, query::Query};
+
+/// What the filter dialog is doing.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum FilterDialogKind {
+    #[default]
+    Create,
+    Edit(String),
+    /// Asking before deleting the filter.
+    Delete(String),
+}
+
+/// The part of the filter form that has the keyboard.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum FilterField {
+    #[default]
+    Name,
+    Query,
+    Color,
+}
+
+/// The popup for creating, editing and deleting local saved filters.
+#[derive(Debug, Default)]
+pub struct FilterDialog<'a> {
+    pub kind: FilterDialogKind,
+    pub name: TextArea<'a>,
+    pub query: TextArea<'a>,
+    /// Index into `COLORS`.
+    pub color: usize,
+    pub field: FilterField,
+    /// Why the filter could not be saved.
+    pub error: Option<String>,
+}
+
+impl<'a> FilterDialog<'a> {
+    /// An empty form for a new filter, or one filled from `filter`.
+    pub fn form(kind: FilterDialogKind, filter: Option<&SavedFilter>) -> Self {
+        let text_area = |text: &str| {
+            let mut input = TextArea::from([text]);
+            input.move_cursor(CursorMove::End);
+            input
+        };
+        let color = filter.map_or("charcoal", |filter| filter.color.as_str());
+        FilterDialog {
+            kind,
+            name: text_area(filter.map_or("", |filter| &filter.name)),
+            query: text_area(filter.map_or("", |filter| &filter.query)),
+            color: COLORS.iter().position(|c| *c == color).unwrap_or(0),
+            ..Default::default()
+        }
+    }
+
+    pub fn delete(filter_id: String) -> Self {
+        FilterDialog {
+            kind: FilterDialogKind::Delete(filter_id),
+            ..Default::default()
+        }
+    }
+
+    /// Tab moves on to the next field, Shift+Tab back.
+    pub fn cycle_field(&mut self, forward: bool) {
+        self.field = match (self.field, forward) {
+            (FilterField::Name, true) | (FilterField::Color, false) => FilterField::Query,
+            (FilterField::Query, true) | (FilterField::Name, false) => FilterField::Color,
+            (FilterField::Color, true) | (FilterField::Query, false) => FilterField::Name,
+        };
+    }
+
+    pub fn cycle_color(&mut self, forward: bool) {
+        self.color = match forward {
+            true => (self.color + 1) % COLORS.len(),
+            false => (self.color + COLORS.len() - 1) % COLORS.len(),
+        };
+    }
+
+    /// The filter as filled in, or why it cannot be saved yet.
+    pub fn filter(&self, id: String) -> Result<SavedFilter, String> {
+        let name = self.name.lines().join(" ").trim().to_string();
+        let query = self.query.lines().join(" ").trim().to_string();
+        if name.is_empty() {
+            return Err("The filter needs a name".to_string());
+        }
+        Query::parse(&query)?;
+        Ok(SavedFilter {
+            id,
+            name,
+            query,
+            color: COLORS[self.color].to_string(),
+            order: 0,
+            is_favorite: false,
+        })
+    }
+}
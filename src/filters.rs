@@ -0,0 +1,168 @@
+use ratatui::widgets::ListState;
+use serde::{Deserialize, Serialize};
+
+use crate::query::Query;
+
+/// Prefix of the ids given to filters defined in the config.
+pub const LOCAL_ID_PREFIX: &str = "local-";
+
+/// A named filter query, either one of the account's saved filters or one
+/// defined in the local config. Only the local ones can be changed here.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SavedFilter {
+    /// Filters written into the config by hand may leave it out.
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub query: String,
+    #[serde(default = "default_color")]
+    pub color: String,
+    #[serde(default)]
+    pub order: i32,
+    #[serde(default)]
+    pub is_favorite: bool,
+}
+
+fn default_color() -> String {
+    "charcoal".to_string()
+}
+
+impl SavedFilter {
+    pub fn is_local(&self) -> bool {
+        self.id.starts_with(LOCAL_ID_PREFIX)
+    }
+
+    pub fn parse(&self) -> Result<Query, String> {
+        Query::parse(&self.query)
+    }
+}
+
+/// The saved filters shown in the sidebar: the account's in their order,
+/// followed by the local ones in the order they were added.
+#[derive(Debug, Default)]
+pub struct Filters {
+    pub filters: Vec<SavedFilter>,
+    pub state: ListState,
+    pub selected_filter: Option<String>,
+}
+
+impl Filters {
+    /// Local filters without an id get one from their position.
+    pub fn new(account: Vec<SavedFilter>, mut local: Vec<SavedFilter>) -> Filters {
+        for (position, filter) in local.iter_mut().enumerate() {
+            if !filter.is_local() {
+                filter.id = format!("{}{}", LOCAL_ID_PREFIX, position);
+            }
+        }
+        let mut filters = Filters {
+            filters: account,
+            ..Default::default()
+        };
+        filters.filters.retain(|filter| !filter.is_local());
+        filters.filters.sort_by_key(|filter| filter.order);
+        filters.filters.extend(local);
+        filters
+    }
+
+    /// The filters that live in the config.
+    pub fn local(&self) -> Vec<SavedFilter> {
+        self.filters.iter().filter(|filter| filter.is_local()).cloned().collect()
+    }
+
+    /// The filters that came from the account.
+    pub fn account(&self) -> Vec<SavedFilter> {
+        self.filters.iter().filter(|filter| !filter.is_local()).cloned().collect()
+    }
+
+    /// Adds a local filter, or replaces the one with the same id.
+    pub fn upsert_local(&mut self, filter: SavedFilter) {
+        match self.filters.iter_mut().find(|f| f.id == filter.id) {
+            Some(existing) => *existing = filter,
+            None => self.filters.push(filter),
+        }
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        self.filters.retain(|filter| filter.id != id);
+        if self.selected_filter.as_deref() == Some(id) {
+            self.selected_filter = None;
+        }
+        if let Some(selected) = self.state.selected() {
+            let last = self.filters.len().checked_sub(1);
+            self.state.select(last.map(|last| selected.min(last)));
+        }
+    }
+
+    /// An id for a new local filter that no other filter has.
+    pub fn new_local_id(&self) -> String {
+        (0..)
+            .map(|n| format!("{}{}", LOCAL_ID_PREFIX, n))
+            .find(|id| self.filters.iter().all(|filter| filter.id != *id))
+            .unwrap_or_default()
+    }
+
+    pub fn next(&mut self) {
+        if self.filters.is_empty() {
+            self.state.select(None);
+            return;
+        }
+        let i = match self.state.selected() {
+            Some(i) if i + 1 < self.filters.len() => i + 1,
+            _ => 0,
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn previous(&mut self) {
+        if self.filters.is_empty() {
+            self.state.select(None);
+            return;
+        }
+        let i = match self.state.selected() {
+            Some(0) | None => self.filters.len() - 1,
+            Some(i) => i - 1,
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn unselect(&mut self) {
+        let offset = self.state.offset();
+        self.state.select(None);
+        *self.state.offset_mut() = offset;
+        self.selected_filter = None;
+    }
+
+    /// The filter under the cursor.
+    pub fn selected(&self) -> Option<&SavedFilter> {
+        self.filters.get(self.state.selected()?)
+    }
+
+    /// Applies synced changes to the account's filters like
+    /// `Labels::merge`, keeping the local ones and the cursor.
+    pub fn merge(&mut self, updated: Vec<SavedFilter>, removed: Vec<String>, full_sync: bool) {
+        let selected_id = self.selected().map(|filter| filter.id.clone());
+
+        if full_sync {
+            self.filters
+                .retain(|filter| filter.is_local() || updated.iter().any(|f| f.id == filter.id));
+        }
+        self.filters.retain(|filter| !removed.contains(&filter.id));
+
+        for filter in updated {
+            match self.filters.iter_mut().find(|f| f.id == filter.id) {
+                Some(existing) => *existing = filter,
+                None => self.filters.push(filter),
+            }
+        }
+        // Local filters stay last, in the order they were added
+        self.filters.sort_by_key(|filter| match filter.is_local() {
+            true => (1, 0),
+            false => (0, filter.order),
+        });
+
+        if let Some(id) = selected_id {
+            let index = self.filters.iter().position(|filter| filter.id == id);
+            self.state.select(index);
+        }
+    }
+}
@@ -1,36 +1,53 @@
-use crossterm::event::{KeyCode, KeyEvent};
-use reqwest::Client;
-
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use crate::{
-    api_calls::{self, close_task, create_task, delete_task},
-    new_task, task_edit, App, TaskResult, tasks::Task,
+    api_error::ApiError,
+    backend::Backend,
+    board,
+    command_line::Command,
+    comments::CommentsView,
+    filter_dialog::{FilterDialog, FilterDialogKind, FilterField},
+    label_picker,
+    move_picker::{Destination, MovePicker, Target},
+    new_task,
+    priority::Priority,
+    project_dialog::{DialogKind, ProjectDialog},
+    projects::{ListType, Project, Rollback, COLORS},
+    query::Query,
+    queue::{self, Mutation},
+    sections::SectionsView,
+    task_edit, tui, App, TaskResult,
+    tasks::{Filter, SortCriterion, Task},
 };
 
 pub fn handle_task_editor(
     app: &mut App,
     key: KeyEvent,
-    client: Client,
+    client: Backend,
     tx: std::sync::mpsc::Sender<TaskResult>,
 ) {
+    if key.code == KeyCode::Char('o') && key.modifiers.contains(KeyModifiers::CONTROL) {
+        open_comments(app, client, tx);
+        return;
+    }
+
     if key.code == KeyCode::Esc {
         app.show_task_editor = !app.show_task_editor;
     } else if key.code == KeyCode::Enter {
         app.show_task_editor = !app.show_task_editor;
-        let index = app.task_edit.current_task_index;
+        // A sync may have moved or removed the task while it was open
+        let Some(index) = app.tasks.tree.position(&app.task_edit.task_id) else {
+            app.notify("The task was removed while it was being edited".to_string());
+            return;
+        };
 
         app.tasks.tasks[index].content = app.task_edit.content.lines().join("\n");
         app.tasks.tasks[index].description = app.task_edit.description.lines().join("\n");
         
-        // Parse and update priority
-        let priority_text = app.task_edit.priority_string.lines().join("");
-        let priority_text = priority_text.trim();
-        if !priority_text.is_empty() {
-            if let Ok(priority_value) = priority_text.parse::<u8>() {
-                if priority_value >= 1 && priority_value <= 4 {
-                    app.tasks.tasks[index].priority = priority_value;
-                }
-            }
+        // The editor shows p1..p4 levels; anything else keeps the old priority
+        if let Ok(priority) = app.task_edit.priority_string.lines().join("").parse::<Priority>() {
+            app.tasks.tasks[index].priority = priority;
         }
+        app.tasks.tasks[index].labels = app.task_edit.labels.clone();
 
         let task = app.tasks.tasks[index].clone();
 
@@ -39,9 +56,11 @@ pub fn handle_task_editor(
 
         json["due_string"] = serde_json::Value::String(app.task_edit.due_string.lines().join("\n"));
 
-        tokio::spawn(async move {
-            let _ = api_calls::update_task(&client, json, task.id.to_string(), tx).await;
+        app.queue.push(Mutation::UpdateTask {
+            task_id: task.id,
+            json,
         });
+        app.queue.spawn_flush(client, tx);
     }
     if key.code == KeyCode::Tab {
         if app.task_edit.currently_editing == task_edit::CurrentlyEditing::Content {
@@ -51,6 +70,8 @@ pub fn handle_task_editor(
         } else if app.task_edit.currently_editing == task_edit::CurrentlyEditing::Priority {
             app.task_edit.currently_editing = task_edit::CurrentlyEditing::DueString
         } else if app.task_edit.currently_editing == task_edit::CurrentlyEditing::DueString {
+            app.task_edit.currently_editing = task_edit::CurrentlyEditing::Labels
+        } else if app.task_edit.currently_editing == task_edit::CurrentlyEditing::Labels {
             app.task_edit.currently_editing = task_edit::CurrentlyEditing::ChildTasks
         } else if app.task_edit.currently_editing == task_edit::CurrentlyEditing::ChildTasks {
             app.task_edit.currently_editing = task_edit::CurrentlyEditing::Content
@@ -67,37 +88,45 @@ pub fn handle_task_editor(
         handle_priority_input(&mut app.task_edit.priority_string, key);
     } else if app.task_edit.currently_editing == task_edit::CurrentlyEditing::DueString {
         app.task_edit.due_string.input(key);
+    } else if app.task_edit.currently_editing == task_edit::CurrentlyEditing::Labels {
+        if key.code == KeyCode::Char(' ') {
+            open_label_picker(app, app.task_edit.labels.clone());
+        }
     } else if app.task_edit.currently_editing == task_edit::CurrentlyEditing::ChildTasks {
         if key.code == KeyCode::Char('j') || key.code == KeyCode::Down {
             app.task_edit.next();
         } else if key.code == KeyCode::Char('k') || key.code == KeyCode::Up {
             app.task_edit.previous();
         } else if key.code == KeyCode::Enter {
-            if let Some(selected) = app.task_edit.children_list_state.selected() {
+            // Counted as listed, without subtasks removed since it opened
+            let index = app.task_edit.children_list_state.selected().and_then(|selected| {
+                let mut children = app.task_edit.children.iter().filter_map(|id| app.tasks.tree.position(id));
+                children.nth(selected)
+            });
+            if let Some(index) = index {
                 app.show_task_editor = true;
-                let index = app.task_edit.children[selected];
                 let selected = &app.tasks.tasks[index];
+                let children = app.tasks.child_ids(index);
 
-                let mut children = Vec::new();
-
-                for (index, task) in app.tasks.tasks.iter().enumerate() {
-                    if task.parent_id == Some(selected.id.clone()) {
-                        children.push(index);
-                    }
-                }
-
-                app.task_edit = task_edit::TaskEdit::new(
-                    selected.content.clone(),
-                    selected.description.clone(),
-                    selected.priority.to_string(),
-                    selected.due.as_ref().map_or("", |d| &d.string).to_string(),
-                    children,
-                    index,
-                    task_edit::CurrentlyEditing::Content,
-                );
+                app.task_edit = task_edit::TaskEdit {
+                    labels: selected.labels.clone(),
+                    ..task_edit::TaskEdit::new(
+                        selected.content.clone(),
+                        selected.description.clone(),
+                        selected.priority.level().to_string(),
+                        selected.due.as_ref().map_or("", |d| &d.string).to_string(),
+                        children,
+                        selected.id.clone(),
+                        task_edit::CurrentlyEditing::Content,
+                    )
+                };
             }
         } else if key.code == KeyCode::Char('n') {
-            let task = app.tasks.tasks[app.task_edit.current_task_index].clone();
+            let Some(index) = app.tasks.tree.position(&app.task_edit.task_id) else {
+                app.notify("The task was removed while it was being edited".to_string());
+                return;
+            };
+            let task = app.tasks.tasks[index].clone();
 
             app.show_task_editor = false;
             app.show_new_task = true;
@@ -107,7 +136,12 @@ pub fn handle_task_editor(
     }
 }
 
-pub fn handle_projects(app: &mut App, key: KeyEvent) {
+pub fn handle_projects(
+    app: &mut App,
+    key: KeyEvent,
+    client: Backend,
+    tx: std::sync::mpsc::Sender<TaskResult>,
+) {
     if app.projects.move_mode {
         // In move mode, j/k move projects up/down
         if key.code == KeyCode::Char('j') || key.code == KeyCode::Down {
@@ -118,49 +152,371 @@ pub fn handle_projects(app: &mut App, key: KeyEvent) {
             // Exit move mode and save project order
             app.projects.move_mode = false;
             let project_order: Vec<String> = app.projects.projects.iter().map(|p| p.id.clone()).collect();
-            tokio::spawn(async move {
-                if let Err(e) = crate::save_project_order(&project_order) {
-                    eprintln!("Failed to save project order: {}", e);
-                }
-            });
+            if let Err(e) = crate::save_project_order(&project_order) {
+                app.set_error_message(format!("Failed to save project order: {}", e));
+            }
         }
     } else {
         // Normal mode
         if key.code == KeyCode::Char('j') || key.code == KeyCode::Down {
             app.projects.next();
-            if let Some(selected) = app.projects.state.selected() {
-                let selected_id = app.projects.projects[selected].id.clone();
-                app.tasks.filter = crate::tasks::Filter::ProjectId(selected_id.clone());
-                app.tasks.filter_task_list(false);
-                app.projects.selected_project = Some(selected_id);
+            if let Some(project_id) = app.projects.selected().map(|p| p.id.clone()) {
+                crate::show_project(app, project_id);
             }
         } else if key.code == KeyCode::Char('k') || key.code == KeyCode::Up {
             app.projects.previous();
-            if let Some(selected) = app.projects.state.selected() {
-                let selected_id = app.projects.projects[selected].id.clone();
-                app.tasks.filter = crate::tasks::Filter::ProjectId(selected_id.clone());
-                app.tasks.filter_task_list(false);
-                app.projects.selected_project = Some(selected_id);
+            if let Some(project_id) = app.projects.selected().map(|p| p.id.clone()) {
+                crate::show_project(app, project_id);
+            }
+        } else if key.code == KeyCode::Char('s') {
+            app.projects.include_subprojects = !app.projects.include_subprojects;
+            app.notify(if app.projects.include_subprojects {
+                "Showing tasks of sub-projects".to_string()
+            } else {
+                "Hiding tasks of sub-projects".to_string()
+            });
+            save_project_view(app);
+            if let Some(project_id) = app.projects.selected_project.clone() {
+                crate::show_project(app, project_id);
             }
         } else if key.code == KeyCode::Char('m') {
             // Enter move mode
             app.projects.move_mode = true;
+        } else if key.code == KeyCode::Char('n') {
+            open_project_dialog(app, ProjectDialog::name(DialogKind::Create, ""));
+        } else if key.code == KeyCode::Char('U') {
+            open_project_dialog(app, ProjectDialog::archived());
+            tokio::spawn(async move {
+                let _ = tx.send(match client.list_archived_projects().await {
+                    Ok(archived) => TaskResult::ArchivedProjects(archived),
+                    Err(e) => TaskResult::Error(format!("Failed to load archived projects: {}", e)),
+                });
+            });
+        } else if let Some(project) = app.projects.selected().cloned() {
+            manage_project(app, key, project, client, tx);
+        }
+    }
+}
+
+/// The project management keys that act on the selected project.
+fn manage_project(
+    app: &mut App,
+    key: KeyEvent,
+    project: Project,
+    client: Backend,
+    tx: std::sync::mpsc::Sender<TaskResult>,
+) {
+    let id = project.id.clone();
+    if !is_saved(app, &id) {
+        return;
+    }
+    if key.code == KeyCode::Char('N') {
+        open_project_dialog(app, ProjectDialog::name(DialogKind::CreateChild(id), ""));
+    } else if key.code == KeyCode::Char('R') {
+        open_project_dialog(app, ProjectDialog::name(DialogKind::Rename(id), &project.name));
+    } else if key.code == KeyCode::Char('c') {
+        open_project_dialog(app, ProjectDialog::color(&project));
+    } else if key.code == KeyCode::Char('v') {
+        let view_style = match project.view_style {
+            ListType::List => ListType::Board,
+            ListType::Board => ListType::List,
+        };
+        let json = serde_json::json!({ "view_style": view_style });
+        update_project(app, &id, |p| p.view_style = view_style, json, client, tx);
+    } else if key.code == KeyCode::Char('f') {
+        let is_favorite = !project.is_favorite;
+        let json = serde_json::json!({ "is_favorite": is_favorite });
+        update_project(app, &id, |p| p.is_favorite = is_favorite, json, client, tx);
+    } else if key.code == KeyCode::Char('A') || key.code == KeyCode::Char('x') {
+        if project.is_inbox_project {
+            app.notify("The inbox cannot be archived or deleted".to_string());
         } else if key.code == KeyCode::Char('x') {
-            todo!("DELETE PROJECT");
-        } else if key.code == KeyCode::Char('a') {
-            if let Some(selected) = app.projects.state.selected() {
-                let selected_id = app.projects.projects[selected].id.clone();
-                app.show_new_task = true;
-                app.new_task = new_task::NewTask::new(selected_id, None);
+            open_project_dialog(app, ProjectDialog::delete(id));
+        } else {
+            let rollback = remove_project(app, &id);
+            app.notify(format!("Archived {}", project.name));
+            spawn_project_change(tx, rollback, None, "Failed to archive project", async move {
+                client.archive_project(id).await.map(|_| None)
+            });
+        }
+    } else if key.code == KeyCode::Char('a') {
+        app.new_task = new_task::NewTask::new(id, None);
+        app.show_new_task = true;
+    }
+}
+
+fn open_project_dialog(app: &mut App, dialog: ProjectDialog<'static>) {
+    app.project_dialog = dialog;
+    app.show_project_dialog = true;
+}
+
+/// Snapshot of the given projects before an optimistic change to them.
+fn project_rollback(app: &App, project_ids: &[String]) -> Rollback {
+    Rollback {
+        projects: app.projects.snapshot(project_ids),
+        ..Default::default()
+    }
+}
+
+/// A project created on screen keeps a placeholder id until the server
+/// answers, and nothing can be done with it until then.
+fn is_saved(app: &mut App, project_id: &str) -> bool {
+    if project_id.starts_with(queue::TEMP_ID_PREFIX) {
+        app.notify("The project has not been saved yet".to_string());
+        return false;
+    }
+    true
+}
+
+/// Changes a project on screen right away and sends the change, undoing it if
+/// the server refuses.
+fn update_project(
+    app: &mut App,
+    project_id: &str,
+    change: impl FnOnce(&mut Project),
+    json: serde_json::Value,
+    client: Backend,
+    tx: std::sync::mpsc::Sender<TaskResult>,
+) {
+    let rollback = project_rollback(app, &[project_id.to_string()]);
+    let Some(project) = app.projects.projects.iter_mut().find(|p| p.id == project_id) else {
+        return;
+    };
+    change(project);
+    let project_id = project_id.to_string();
+    spawn_project_change(tx, rollback, None, "Failed to update project", async move {
+        client.update_project(project_id, json).await.map(Some)
+    });
+}
+
+/// Takes a project, its sub-projects and everything in them off the screen,
+/// as deleting or archiving it does on the server.
+fn remove_project(app: &mut App, project_id: &str) -> Rollback {
+    let mut removed: Vec<String> = app
+        .projects
+        .descendants(project_id)
+        .iter()
+        .map(|project| project.id.clone())
+        .collect();
+    removed.push(project_id.to_string());
+    let mut rollback = project_rollback(app, &removed);
+
+    let (gone, kept) = std::mem::take(&mut app.tasks.tasks)
+        .into_iter()
+        .partition(|task| removed.contains(&task.project_id));
+    app.tasks.tasks = kept;
+    app.tasks.reindex();
+    rollback.tasks = gone;
+    let (gone, kept) = std::mem::take(&mut app.tasks.sections.sections)
+        .into_iter()
+        .partition(|section| removed.contains(&section.project_id));
+    app.tasks.sections.sections = kept;
+    rollback.sections = gone;
+
+    app.projects.merge(Vec::new(), removed, false);
+    if let Filter::ProjectId(_) = app.tasks.filter {
+        let selected = app.projects.selected_project.clone();
+        crate::select_project(app, selected);
+    } else {
+        app.tasks.refresh();
+    }
+    rollback
+}
+
+fn spawn_project_change<F>(
+    tx: std::sync::mpsc::Sender<TaskResult>,
+    rollback: Rollback,
+    temp_id: Option<String>,
+    failure: &'static str,
+    request: F,
+) where
+    F: std::future::Future<Output = Result<Option<Project>, ApiError>> + Send + 'static,
+{
+    tokio::spawn(async move {
+        let result = match request.await {
+            Ok(Some(project)) => TaskResult::Project {
+                temp_id,
+                project: Box::new(project),
+            },
+            Ok(None) => return,
+            Err(e) => TaskResult::ProjectFailed {
+                rollback: Box::new(rollback),
+                error: format!("{}: {}", failure, e),
+            },
+        };
+        let _ = tx.send(result);
+    });
+}
+
+/// The project dialog: a name input, the color list, the delete confirmation
+/// or the archived projects.
+pub fn handle_project_dialog(
+    app: &mut App,
+    key: KeyEvent,
+    client: Backend,
+    tx: std::sync::mpsc::Sender<TaskResult>,
+) {
+    if key.code == KeyCode::Esc {
+        app.show_project_dialog = false;
+        return;
+    }
+
+    match app.project_dialog.kind.clone() {
+        kind @ (DialogKind::Create | DialogKind::CreateChild(_) | DialogKind::Rename(_)) => {
+            if key.code != KeyCode::Enter {
+                app.project_dialog.input.input(key);
+                return;
+            }
+            let name = app.project_dialog.input.lines().join(" ").trim().to_string();
+            if name.is_empty() {
+                return;
+            }
+            app.show_project_dialog = false;
+
+            if let DialogKind::Rename(id) = kind {
+                let json = serde_json::json!({ "name": name });
+                update_project(app, &id, |p| p.name = name, json, client, tx);
+                return;
+            }
+
+            let parent_id = match kind {
+                DialogKind::CreateChild(parent_id) => Some(parent_id),
+                _ => None,
+            };
+            let temp_id = queue::new_temp_id();
+            let rollback = project_rollback(app, std::slice::from_ref(&temp_id));
+            app.projects.projects.push(Project {
+                id: temp_id.clone(),
+                name: name.clone(),
+                comment_count: 0,
+                order: app.projects.projects.len() as i32 + 1,
+                color: "charcoal".to_string(),
+                is_shared: false,
+                is_favorite: false,
+                is_inbox_project: false,
+                is_team_inbox: false,
+                view_style: ListType::List,
+                url: String::new(),
+                parent_id: parent_id.clone(),
+            });
+            app.projects.arrange();
+            app.projects.select_id(&temp_id);
+            crate::show_project(app, temp_id.clone());
+
+            let json = match parent_id {
+                Some(parent_id) => serde_json::json!({ "name": name, "parent_id": parent_id }),
+                None => serde_json::json!({ "name": name }),
+            };
+            spawn_project_change(tx, rollback, Some(temp_id), "Failed to create project", async move {
+                client.create_project(json).await.map(Some)
+            });
+        }
+        DialogKind::Color(id) => {
+            if key.code == KeyCode::Char('j') || key.code == KeyCode::Down {
+                app.project_dialog.next();
+            } else if key.code == KeyCode::Char('k') || key.code == KeyCode::Up {
+                app.project_dialog.previous();
+            } else if key.code == KeyCode::Enter {
+                app.show_project_dialog = false;
+                let Some(color) = app.project_dialog.state.selected().and_then(|i| COLORS.get(i)) else {
+                    return;
+                };
+                let json = serde_json::json!({ "color": color });
+                update_project(app, &id, |p| p.color = color.to_string(), json, client, tx);
+            }
+        }
+        DialogKind::Delete(id) => {
+            app.show_project_dialog = false;
+            if key.code != KeyCode::Char('y') {
+                return;
+            }
+            let rollback = remove_project(app, &id);
+            spawn_project_change(tx, rollback, None, "Failed to delete project", async move {
+                client.delete_project(id).await.map(|_| None)
+            });
+        }
+        DialogKind::Archived => {
+            if key.code == KeyCode::Char('j') || key.code == KeyCode::Down {
+                app.project_dialog.next();
+            } else if key.code == KeyCode::Char('k') || key.code == KeyCode::Up {
+                app.project_dialog.previous();
+            } else if key.code == KeyCode::Enter {
+                let Some(index) = app.project_dialog.state.selected() else {
+                    return;
+                };
+                app.show_project_dialog = false;
+                let project = app.project_dialog.archived.remove(index);
+                let rollback = project_rollback(app, std::slice::from_ref(&project.id));
+                let id = project.id.clone();
+                app.notify(format!("Unarchived {}", project.name));
+                app.projects.merge(vec![project], Vec::new(), false);
+
+                tokio::spawn(async move {
+                    let _ = tx.send(match client.unarchive_project(id).await {
+                        Ok(()) => TaskResult::Unarchived,
+                        Err(e) => TaskResult::ProjectFailed {
+                            rollback: Box::new(rollback),
+                            error: format!("Failed to unarchive project: {}", e),
+                        },
+                    });
+                });
             }
         }
     }
 }
 
+/// `za`, `zo` and `zc` in the projects pane: toggle, open or close the fold
+/// of the selected project.
+pub fn fold_projects(app: &mut App, key: KeyEvent) {
+    let fold = match key.code {
+        KeyCode::Char('a') => None,
+        KeyCode::Char('o') => Some(false),
+        KeyCode::Char('c') => Some(true),
+        _ => return,
+    };
+    if app.projects.fold(fold) {
+        save_project_view(app);
+    }
+    // Closing a fold can move the cursor onto the parent
+    if let Some(project_id) = app.projects.selected().map(|p| p.id.clone()) {
+        if app.projects.selected_project.as_ref() != Some(&project_id) {
+            crate::show_project(app, project_id);
+        }
+    }
+}
+
+/// `za`, `zo` and `zc` on the selected task, and `zR`/`zM` to open or close
+/// every fold in the task list.
+pub fn fold_tasks(app: &mut App, key: KeyEvent) {
+    let changed = match key.code {
+        KeyCode::Char('a') => app.tasks.fold(None),
+        KeyCode::Char('o') => app.tasks.fold(Some(false)),
+        KeyCode::Char('c') => app.tasks.fold(Some(true)),
+        KeyCode::Char('R') => app.tasks.fold_all(false),
+        KeyCode::Char('M') => app.tasks.fold_all(true),
+        _ => return,
+    };
+    if changed {
+        save_task_folds(app);
+    }
+}
+
+fn save_task_folds(app: &mut App) {
+    if let Err(e) = crate::save_task_folds(&app.tasks) {
+        app.set_error_message(format!("Failed to save folded tasks: {}", e));
+    }
+}
+
+fn save_project_view(app: &mut App) {
+    if let Err(e) = crate::save_project_view(&app.projects) {
+        app.set_error_message(format!("Failed to save the project view: {}", e));
+    }
+}
+
 pub fn handle_new_tasks(
     app: &mut App,
     key: KeyEvent,
-    client: Client,
+    client: Backend,
     tx: std::sync::mpsc::Sender<TaskResult>,
 ) {
     if key.code == KeyCode::Esc {
@@ -168,25 +524,24 @@ pub fn handle_new_tasks(
     } else if key.code == KeyCode::Enter {
         app.show_new_task = !app.show_new_task;
         
-        // Parse priority from priority_string
-        let priority_text = app.new_task.priority_string.lines().join("");
-        let priority_text = priority_text.trim();
-        if !priority_text.is_empty() {
-            if let Ok(priority_value) = priority_text.parse::<u8>() {
-                if priority_value >= 1 && priority_value <= 4 {
-                    app.new_task.priority = Some(priority_value);
-                }
-            }
+        if let Ok(priority) = app.new_task.priority_string.lines().join("").parse::<Priority>() {
+            app.new_task.priority = priority;
         }
         
         let json = app.new_task.get_json();
 
-        tokio::spawn(async move {
-            let result = create_task(&client, json, tx).await;
-            if let Err(e) = result {
-                eprintln!("Failed to create task: {}", e);
-            }
+        // Show the task right away; it gets its real id once the create
+        // request has gone through
+        let temp_id = queue::new_temp_id();
+        app.tasks.upsert(queue::local_task(temp_id.clone(), &json));
+        app.tasks.refresh();
+
+        app.queue.push(Mutation::CreateTask {
+            temp_id,
+            json,
+            request_id: queue::create_request_id(),
         });
+        app.queue.spawn_flush(client, tx);
     }
     if key.code == KeyCode::Tab {
         if app.new_task.currently_editing == new_task::CurrentlyEditing::Content {
@@ -196,6 +551,8 @@ pub fn handle_new_tasks(
         } else if app.new_task.currently_editing == new_task::CurrentlyEditing::Priority {
             app.new_task.currently_editing = new_task::CurrentlyEditing::DueString
         } else if app.new_task.currently_editing == new_task::CurrentlyEditing::DueString {
+            app.new_task.currently_editing = new_task::CurrentlyEditing::Labels
+        } else if app.new_task.currently_editing == new_task::CurrentlyEditing::Labels {
             app.new_task.currently_editing = new_task::CurrentlyEditing::Content
         }
         return;
@@ -208,59 +565,734 @@ pub fn handle_new_tasks(
         handle_priority_input(&mut app.new_task.priority_string, key);
     } else if app.new_task.currently_editing == new_task::CurrentlyEditing::DueString {
         app.new_task.due_string.input(key);
+    } else if app.new_task.currently_editing == new_task::CurrentlyEditing::Labels
+        && key.code == KeyCode::Char(' ')
+    {
+        open_label_picker(app, app.new_task.labels.clone().unwrap_or_default());
+    }
+}
+
+/// Opens the comments pane for the task in the editor and fetches its
+/// comments.
+fn open_comments(app: &mut App, client: Backend, tx: std::sync::mpsc::Sender<TaskResult>) {
+    let task_id = app.task_edit.task_id.clone();
+    if app.tasks.tree.position(&task_id).is_none() {
+        app.notify("The task was removed while it was being edited".to_string());
+        return;
+    }
+    if task_id.starts_with(queue::TEMP_ID_PREFIX) {
+        app.set_error_message("This task has not been saved yet".to_string());
+        return;
+    }
+    app.comments = CommentsView::new(task_id.clone());
+    app.show_comments = true;
+
+    tokio::spawn(async move {
+        let result = match client.list_comments(task_id.clone()).await {
+            Ok(comments) => TaskResult::Comments { task_id, comments },
+            Err(e) => TaskResult::Error(format!("Failed to load comments: {}", e)),
+        };
+        let _ = tx.send(result);
+    });
+}
+
+/// The comments pane. Comments are sent straight to the backend rather than
+/// through the queue, so they need a connection.
+pub fn handle_comments(
+    app: &mut App,
+    key: KeyEvent,
+    client: Backend,
+    tx: std::sync::mpsc::Sender<TaskResult>,
+) {
+    if app.comments.composing {
+        if key.code == KeyCode::Esc {
+            app.comments.stop_composing();
+        } else if key.code == KeyCode::Enter {
+            let content = app.comments.input.lines().join("\n").trim().to_string();
+            if content.is_empty() {
+                return;
+            }
+            let task_id = app.comments.task_id.clone();
+            let editing = app.comments.editing.clone();
+            app.comments.stop_composing();
+
+            tokio::spawn(async move {
+                let json = serde_json::json!({ "task_id": task_id, "content": content });
+                let result = match editing {
+                    Some(comment_id) => client.update_comment(comment_id, json).await,
+                    None => client.create_comment(json).await,
+                };
+                let _ = tx.send(match result {
+                    Ok(comment) => TaskResult::Comment(Box::new(comment)),
+                    Err(e) => TaskResult::Error(format!("Failed to save comment: {}", e)),
+                });
+            });
+        } else {
+            app.comments.input.input(key);
+        }
+        return;
+    }
+
+    if key.code == KeyCode::Esc {
+        app.show_comments = false;
+    } else if key.code == KeyCode::Char('j') || key.code == KeyCode::Down {
+        app.comments.next();
+    } else if key.code == KeyCode::Char('k') || key.code == KeyCode::Up {
+        app.comments.previous();
+    } else if key.code == KeyCode::Char('n') || key.code == KeyCode::Char('a') {
+        app.comments.compose(None);
+    } else if key.code == KeyCode::Char('e') {
+        if let Some(comment) = app.comments.selected().cloned() {
+            app.comments.compose(Some(&comment));
+        }
+    } else if key.code == KeyCode::Char('d') {
+        let Some(selected) = app.comments.state.selected() else {
+            return;
+        };
+        let comment = app.comments.comments.remove(selected);
+        let count = app.comments.comments.len();
+        app.comments
+            .state
+            .select(if count == 0 { None } else { Some(selected.min(count - 1)) });
+        if let Some(task) = app.tasks.tasks.iter_mut().find(|t| t.id == app.comments.task_id) {
+            task.comment_count = task.comment_count.saturating_sub(1);
+        }
+
+        tokio::spawn(async move {
+            if let Err(e) = client.delete_comment(comment.id).await {
+                let _ = tx.send(TaskResult::Error(format!("Failed to delete comment: {}", e)));
+            }
+        });
+    }
+}
+
+fn open_label_picker(app: &mut App, chosen: Vec<String>) {
+    app.label_picker = label_picker::LabelPicker::new(app.labels.names(), chosen);
+    app.show_label_picker = true;
+}
+
+/// The label checklist opened from either editor. Enter keeps the choice,
+/// Esc throws it away.
+pub fn handle_label_picker(app: &mut App, key: KeyEvent) {
+    if key.code == KeyCode::Char('j') || key.code == KeyCode::Down {
+        app.label_picker.next();
+    } else if key.code == KeyCode::Char('k') || key.code == KeyCode::Up {
+        app.label_picker.previous();
+    } else if key.code == KeyCode::Char(' ') {
+        app.label_picker.toggle();
+    } else if key.code == KeyCode::Esc {
+        app.show_label_picker = false;
+    } else if key.code == KeyCode::Enter {
+        app.show_label_picker = false;
+        let chosen = std::mem::take(&mut app.label_picker.chosen);
+        if app.show_task_editor {
+            app.task_edit.labels = chosen;
+        } else if app.show_new_task {
+            app.new_task.labels = (!chosen.is_empty()).then_some(chosen);
+        }
+    }
+}
+
+/// Opens the sections of the project on screen, on the row of the selected
+/// task's section.
+fn open_sections(app: &mut App) {
+    let Filter::ProjectId(project_id) = &app.tasks.filter else {
+        return;
+    };
+    let project_id = project_id.clone();
+    if !is_saved(app, &project_id) {
+        return;
+    }
+    let selected_section = app.tasks.selected_section_id();
+    let row = app
+        .tasks
+        .sections
+        .for_project(&project_id)
+        .iter()
+        .position(|section| Some(&section.id) == selected_section.as_ref())
+        .map_or(0, |position| position + 1);
+    app.sections_view = SectionsView::new(project_id, row);
+    app.show_sections = true;
+}
+
+/// The sections popup: create, rename, reorder and delete the project's
+/// sections, or move the selected task into one with Enter.
+pub fn handle_sections(
+    app: &mut App,
+    key: KeyEvent,
+    client: Backend,
+    tx: std::sync::mpsc::Sender<TaskResult>,
+) {
+    let section_ids: Vec<String> = app
+        .tasks
+        .sections
+        .for_project(&app.sections_view.project_id)
+        .iter()
+        .map(|section| section.id.clone())
+        .collect();
+    let rows = section_ids.len() + 1;
+    let row = app.sections_view.state.selected().unwrap_or(0);
+    // Row 0 is "No section"
+    let selected_id = row.checked_sub(1).and_then(|i| section_ids.get(i)).cloned();
+
+    if app.sections_view.composing {
+        if key.code == KeyCode::Esc {
+            app.sections_view.stop_composing();
+        } else if key.code == KeyCode::Enter {
+            let name = app.sections_view.input.lines().join(" ").trim().to_string();
+            if name.is_empty() {
+                return;
+            }
+            let renaming = app.sections_view.renaming.clone();
+            app.sections_view.stop_composing();
+
+            match renaming {
+                Some(section_id) => {
+                    if let Some(section) = app.tasks.sections.sections.iter_mut().find(|s| s.id == section_id) {
+                        section.name = name.clone();
+                    }
+                    app.queue.push(Mutation::UpdateSection {
+                        section_id,
+                        json: serde_json::json!({ "name": name }),
+                    });
+                }
+                None => {
+                    let project_id = app.sections_view.project_id.clone();
+                    let order = app
+                        .tasks
+                        .sections
+                        .for_project(&project_id)
+                        .last()
+                        .map_or(1, |section| section.order + 1);
+                    let temp_id = queue::new_temp_id();
+                    let json = serde_json::json!({ "project_id": project_id, "name": name, "order": order });
+                    app.tasks.sections.sections.push(queue::local_section(temp_id.clone(), &json));
+                    app.queue.push(Mutation::CreateSection { temp_id, json });
+                }
+            }
+            app.tasks.refresh();
+            app.queue.spawn_flush(client, tx);
+        } else {
+            app.sections_view.input.input(key);
+        }
+        return;
+    }
+
+    if app.sections_view.confirm_delete {
+        app.sections_view.confirm_delete = false;
+        if key.code != KeyCode::Char('y') {
+            return;
+        }
+        let Some(section_id) = selected_id else {
+            return;
+        };
+        // The server deletes the tasks of the section along with it
+        let mut removed = Vec::new();
+        for task in app.tasks.tasks.iter().filter(|t| t.section_id.as_ref() == Some(&section_id)) {
+            removed.push(task.id.clone());
+            removed.extend(app.tasks.descendant_ids(&task.id));
+        }
+        app.tasks.remove_tasks(&removed);
+        app.tasks.sections.merge(Vec::new(), vec![section_id.clone()], false);
+        app.tasks.refresh();
+        app.sections_view.state.select(Some(row - 1));
+
+        app.queue.push(Mutation::DeleteSection { section_id });
+        app.queue.spawn_flush(client, tx);
+        return;
+    }
+
+    if key.code == KeyCode::Esc {
+        app.show_sections = false;
+    } else if key.code == KeyCode::Char('j') || key.code == KeyCode::Down {
+        app.sections_view.next(rows);
+    } else if key.code == KeyCode::Char('k') || key.code == KeyCode::Up {
+        app.sections_view.previous(rows);
+    } else if key.code == KeyCode::Char('n') || key.code == KeyCode::Char('a') {
+        app.sections_view.compose(None);
+    } else if key.code == KeyCode::Char('r') || key.code == KeyCode::Char('e') {
+        let section = app.tasks.sections.sections.iter().find(|s| Some(&s.id) == selected_id.as_ref());
+        if let Some(section) = section.cloned() {
+            app.sections_view.compose(Some(&section));
+        }
+    } else if key.code == KeyCode::Char('d') {
+        app.sections_view.confirm_delete = selected_id.is_some();
+    } else if key.code == KeyCode::Char('J') || key.code == KeyCode::Char('K') {
+        // "No section" always comes first, so only sections swap places
+        let Some(from) = row.checked_sub(1) else {
+            return;
+        };
+        let to = if key.code == KeyCode::Char('J') { from + 1 } else { from.wrapping_sub(1) };
+        if to >= section_ids.len() {
+            return;
+        }
+        let mut ordered = section_ids;
+        ordered.swap(from, to);
+        app.sections_view.state.select(Some(to + 1));
+
+        // Renumber the whole project so sections with equal orders sort too
+        let mut orders = Vec::new();
+        for (position, section_id) in ordered.iter().enumerate() {
+            let order = position as i32 + 1;
+            if let Some(section) = app.tasks.sections.sections.iter_mut().find(|s| s.id == *section_id) {
+                section.order = order;
+            }
+            orders.push(serde_json::json!({ "id": section_id, "section_order": order }));
+        }
+        app.tasks.refresh();
+
+        app.queue.push(Mutation::ReorderSections {
+            json: serde_json::json!({ "sections": orders }),
+        });
+        app.queue.spawn_flush(client, tx);
+    } else if key.code == KeyCode::Enter {
+        app.show_sections = false;
+        let Some(&index) = app.tasks.state.selected().and_then(|selected| app.tasks.display_tasks.get(selected)) else {
+            return;
+        };
+        move_to_section(app, index, selected_id, client, tx);
+    }
+}
+
+/// Moves a task and its subtasks into a section of its project, or out of
+/// its section with `None`.
+fn move_to_section(
+    app: &mut App,
+    index: usize,
+    section_id: Option<String>,
+    client: Backend,
+    tx: std::sync::mpsc::Sender<TaskResult>,
+) {
+    if app.tasks.tasks[index].parent_id.is_some() {
+        app.notify("Subtasks move with their parent".to_string());
+        return;
+    }
+    let destination = match section_id {
+        Some(section_id) => Destination::Section(section_id),
+        None => Destination::Project(app.tasks.tasks[index].project_id.clone()),
+    };
+    move_task(app, index, destination, client, tx);
+}
+
+/// Moves a task and its subtasks locally and queues the move for the server.
+/// Returns whether anything changed.
+fn move_task(
+    app: &mut App,
+    index: usize,
+    destination: Destination,
+    client: Backend,
+    tx: std::sync::mpsc::Sender<TaskResult>,
+) -> bool {
+    let task = &app.tasks.tasks[index];
+    let (project_id, section_id, parent_id) = match &destination {
+        Destination::Project(project_id) => (project_id.clone(), None, None),
+        Destination::Section(section_id) => {
+            let Some(section) = app.tasks.sections.sections.iter().find(|s| &s.id == section_id) else {
+                return false;
+            };
+            (section.project_id.clone(), Some(section_id.clone()), None)
+        }
+        Destination::Parent(parent_id) => {
+            let Some(parent) = app.tasks.tree.position(parent_id).map(|i| &app.tasks.tasks[i]) else {
+                return false;
+            };
+            // A task cannot go under itself or one of its own subtasks
+            if parent.id == task.id || app.tasks.descendant_ids(&task.id).contains(parent_id) {
+                return false;
+            }
+            (parent.project_id.clone(), parent.section_id.clone(), Some(parent_id.clone()))
+        }
+    };
+    if task.project_id == project_id && task.section_id == section_id && task.parent_id == parent_id {
+        return false;
+    }
+
+    let task_id = task.id.clone();
+    // Keep the moved task in sight under its new parent
+    if let Some(parent_id) = &parent_id {
+        if app.tasks.folded.remove(parent_id) {
+            save_task_folds(app);
+        }
+    }
+    app.tasks.move_task(index, project_id, section_id, parent_id);
+    app.tasks.select_task_id(&task_id);
+
+    app.queue.push(Mutation::MoveTask {
+        task_id,
+        json: destination.json(),
+    });
+    app.queue.spawn_flush(client, tx);
+    true
+}
+
+/// Opens the move popup for the selected task, listing every project, every
+/// section and every task it could go under.
+fn open_move_picker(app: &mut App) {
+    let Some(&index) = app.tasks.state.selected().and_then(|selected| app.tasks.display_tasks.get(selected)) else {
+        return;
+    };
+    let task_id = &app.tasks.tasks[index].id;
+    let excluded = app.tasks.descendant_ids(task_id);
+    let project_name = |project_id: &str| {
+        app.projects
+            .projects
+            .iter()
+            .find(|project| project.id == project_id)
+            .map_or("", |project| project.name.as_str())
+            .to_string()
+    };
+
+    // Projects still being created cannot take tasks yet
+    let saved_projects = app
+        .projects
+        .projects
+        .iter()
+        .filter(|project| !project.id.starts_with(queue::TEMP_ID_PREFIX));
+    let mut targets: Vec<Target> = saved_projects
+        .clone()
+        .map(|project| Target {
+            label: format!("#{}", project.name),
+            destination: Destination::Project(project.id.clone()),
+        })
+        .collect();
+    for project in saved_projects {
+        targets.extend(app.tasks.sections.for_project(&project.id).into_iter().map(|section| Target {
+            label: format!("#{} / {}", project.name, section.name),
+            destination: Destination::Section(section.id.clone()),
+        }));
+    }
+    targets.extend(
+        app.tasks
+            .tasks
+            .iter()
+            .filter(|task| &task.id != task_id && !excluded.contains(&task.id))
+            .map(|task| Target {
+                label: format!("{} (#{})", task.content, project_name(&task.project_id)),
+                destination: Destination::Parent(task.id.clone()),
+            }),
+    );
+
+    app.move_picker = MovePicker::new(task_id.clone(), targets);
+    app.show_move_picker = true;
+}
+
+/// The move popup: typing narrows the list, Enter moves the task and Esc
+/// leaves it where it is.
+pub fn handle_move_picker(
+    app: &mut App,
+    key: KeyEvent,
+    client: Backend,
+    tx: std::sync::mpsc::Sender<TaskResult>,
+) {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    match key.code {
+        KeyCode::Esc => app.show_move_picker = false,
+        KeyCode::Down => app.move_picker.next(),
+        KeyCode::Up => app.move_picker.previous(),
+        KeyCode::Char('n') if ctrl => app.move_picker.next(),
+        KeyCode::Char('p') if ctrl => app.move_picker.previous(),
+        KeyCode::Enter => {
+            let Some(target) = app.move_picker.selected().cloned() else {
+                return;
+            };
+            app.show_move_picker = false;
+            let Some(index) = app.tasks.tree.position(&app.move_picker.task_id) else {
+                app.notify("The task was removed before it could be moved".to_string());
+                return;
+            };
+            if move_task(app, index, target.destination, client, tx) {
+                app.notify(format!("Moved to {}", target.label));
+            }
+        }
+        _ => {
+            app.move_picker.input.input(key);
+            app.move_picker.update_matches();
+        }
+    }
+}
+
+/// `>>` puts the selected task under the sibling above it, `<<` lifts it
+/// out of its parent to sit next to it.
+fn indent_task(
+    app: &mut App,
+    indent: bool,
+    client: Backend,
+    tx: std::sync::mpsc::Sender<TaskResult>,
+) {
+    let Some(selected) = app.tasks.state.selected() else {
+        return;
+    };
+    let Some(&index) = app.tasks.display_tasks.get(selected) else {
+        return;
+    };
+    let task = &app.tasks.tasks[index];
+
+    let destination = if indent {
+        let depth = app.tasks.tree.depth(index);
+        let sibling = app.tasks.display_tasks[..selected]
+            .iter()
+            .rev()
+            .take_while(|&&i| app.tasks.tree.depth(i) >= depth)
+            .map(|&i| &app.tasks.tasks[i])
+            .find(|other| {
+                other.parent_id == task.parent_id
+                    && other.project_id == task.project_id
+                    && other.section_id == task.section_id
+            });
+        let Some(sibling) = sibling else {
+            app.notify("No task above to indent under".to_string());
+            return;
+        };
+        Destination::Parent(sibling.id.clone())
+    } else {
+        let Some(parent) = task
+            .parent_id
+            .as_ref()
+            .and_then(|parent_id| app.tasks.tree.position(parent_id))
+            .map(|i| &app.tasks.tasks[i])
+        else {
+            return;
+        };
+        match (&parent.parent_id, &parent.section_id) {
+            (Some(grandparent_id), _) => Destination::Parent(grandparent_id.clone()),
+            (None, Some(section_id)) => Destination::Section(section_id.clone()),
+            (None, None) => Destination::Project(parent.project_id.clone()),
+        }
+    };
+    move_task(app, index, destination, client, tx);
+}
+
+pub fn handle_labels(app: &mut App, key: KeyEvent) {
+    if key.code == KeyCode::Char('j') || key.code == KeyCode::Down {
+        app.labels.next();
+    } else if key.code == KeyCode::Char('k') || key.code == KeyCode::Up {
+        app.labels.previous();
+    } else {
+        return;
+    }
+    if let Some(name) = app.labels.selected_name() {
+        app.projects.unselect();
+        app.projects.selected_project = None;
+        app.filters.unselect();
+        app.tasks.filter = crate::tasks::Filter::Label(name.clone());
+        app.tasks.filter_task_list(false);
+        app.labels.selected_label = Some(name);
+    }
+}
+
+/// The saved filters in the sidebar: moving onto one applies it, and the
+/// local ones can be added, edited and deleted.
+pub fn handle_filters(app: &mut App, key: KeyEvent) {
+    if key.code == KeyCode::Char('j') || key.code == KeyCode::Down {
+        app.filters.next();
+    } else if key.code == KeyCode::Char('k') || key.code == KeyCode::Up {
+        app.filters.previous();
+    } else if key.code == KeyCode::Char('n') {
+        open_filter_dialog(app, FilterDialog::form(FilterDialogKind::Create, None));
+        return;
+    } else if key.code == KeyCode::Char('e') || key.code == KeyCode::Char('x') {
+        let Some(filter) = app.filters.selected().cloned() else {
+            return;
+        };
+        if !filter.is_local() {
+            app.notify("Filters from the account are changed in Todoist".to_string());
+        } else if key.code == KeyCode::Char('e') {
+            open_filter_dialog(app, FilterDialog::form(FilterDialogKind::Edit(filter.id.clone()), Some(&filter)));
+        } else {
+            open_filter_dialog(app, FilterDialog::delete(filter.id));
+        }
+        return;
+    } else if key.code != KeyCode::Enter {
+        return;
+    }
+    if let Some(filter_id) = app.filters.selected().map(|filter| filter.id.clone()) {
+        crate::select_filter(app, &filter_id);
+    }
+}
+
+fn open_filter_dialog(app: &mut App, dialog: FilterDialog<'static>) {
+    app.filter_dialog = dialog;
+    app.show_filter_dialog = true;
+}
+
+/// The popup for local filters: Tab moves between name, query and color,
+/// Enter saves once the query parses.
+pub fn handle_filter_dialog(app: &mut App, key: KeyEvent) {
+    if key.code == KeyCode::Esc {
+        app.show_filter_dialog = false;
+        return;
+    }
+
+    let id = match app.filter_dialog.kind.clone() {
+        FilterDialogKind::Delete(id) => {
+            app.show_filter_dialog = false;
+            if key.code == KeyCode::Char('y') {
+                let showing = app.filters.selected_filter.as_ref() == Some(&id);
+                app.filters.remove(&id);
+                save_local_filters(app);
+                // Its tasks are no longer what the list is about
+                if showing {
+                    let selected = app.projects.selected_project.clone();
+                    crate::select_project(app, selected);
+                }
+            }
+            return;
+        }
+        FilterDialogKind::Edit(id) => id,
+        FilterDialogKind::Create => app.filters.new_local_id(),
+    };
+
+    let dialog = &mut app.filter_dialog;
+    match key.code {
+        KeyCode::Tab => dialog.cycle_field(true),
+        KeyCode::BackTab => dialog.cycle_field(false),
+        KeyCode::Enter => match dialog.filter(id) {
+            Ok(filter) => {
+                app.show_filter_dialog = false;
+                let id = filter.id.clone();
+                app.filters.upsert_local(filter);
+                save_local_filters(app);
+                crate::select_filter(app, &id);
+            }
+            Err(e) => dialog.error = Some(e),
+        },
+        _ => match dialog.field {
+            FilterField::Color => match key.code {
+                KeyCode::Char('j' | 'l') | KeyCode::Down | KeyCode::Right => dialog.cycle_color(true),
+                KeyCode::Char('k' | 'h') | KeyCode::Up | KeyCode::Left => dialog.cycle_color(false),
+                _ => {}
+            },
+            FilterField::Name => {
+                dialog.name.input(key);
+                dialog.error = None;
+            }
+            FilterField::Query => {
+                dialog.query.input(key);
+                dialog.error = None;
+            }
+        },
     }
 }
 
-pub fn handle_tasks(app: &mut App, key: KeyEvent, client: Client) {
+fn save_local_filters(app: &mut App) {
+    if let Err(e) = crate::save_local_filters(&app.filters) {
+        app.set_error_message(format!("Failed to save filters: {}", e));
+    }
+}
+
+pub fn handle_tasks(
+    app: &mut App,
+    key: KeyEvent,
+    client: Backend,
+    tx: std::sync::mpsc::Sender<TaskResult>,
+) {
+    if app.tasks.move_mode {
+        // In move mode, j/k move the task among its siblings
+        if key.code == KeyCode::Char('j') || key.code == KeyCode::Down {
+            app.tasks.reordered |= app.tasks.move_down();
+        } else if key.code == KeyCode::Char('k') || key.code == KeyCode::Up {
+            app.tasks.reordered |= app.tasks.move_up();
+        } else if key.code == KeyCode::Esc || key.code == KeyCode::Char('m') {
+            finish_task_move(app, client, tx);
+        }
+        return;
+    }
+
+    // `>>` and `<<` indent and outdent, like shifting lines in vim
+    if let KeyCode::Char(c @ ('>' | '<')) = key.code {
+        if app.pending_indent.take() == Some(c) {
+            indent_task(app, c == '>', client, tx);
+        } else {
+            app.pending_indent = Some(c);
+        }
+        return;
+    }
+    app.pending_indent = None;
+
+    if board::showing(app) && handle_board(app, key, client.clone(), tx.clone()) {
+        return;
+    }
+
     if key.code == KeyCode::Char('j') || key.code == KeyCode::Down {
         app.tasks.next();
     } else if key.code == KeyCode::Char('k') || key.code == KeyCode::Up {
         app.tasks.previous();
+    } else if key.code == KeyCode::Char(']') {
+        app.tasks.next_group();
+    } else if key.code == KeyCode::Char('[') {
+        app.tasks.previous_group();
     } else if key.code == KeyCode::Enter {
         if let Some(selected) = app.tasks.state.selected() {
             app.show_task_editor = true;
             let index = app.tasks.display_tasks[selected];
             let selected = &app.tasks.tasks[index];
 
-            let mut children = Vec::new();
-
-            for (index, task) in app.tasks.tasks.iter().enumerate() {
-                if task.parent_id == Some(selected.id.clone()) {
-                    children.push(index);
-                }
-            }
+            let children = app.tasks.child_ids(index);
 
-            app.task_edit = task_edit::TaskEdit::new(
-                selected.content.clone(),
-                selected.description.clone(),
-                selected.priority.to_string(),
-                selected.due.as_ref().map_or("", |d| &d.string).to_string(),
-                children,
-                index,
-                task_edit::CurrentlyEditing::Content,
-            );
+            app.task_edit = task_edit::TaskEdit {
+                labels: selected.labels.clone(),
+                ..task_edit::TaskEdit::new(
+                    selected.content.clone(),
+                    selected.description.clone(),
+                    selected.priority.level().to_string(),
+                    selected.due.as_ref().map_or("", |d| &d.string).to_string(),
+                    children,
+                    selected.id.clone(),
+                    task_edit::CurrentlyEditing::Content,
+                )
+            };
         }
     } else if key.code == KeyCode::Char('x') {
         if let Some(selected) = app.tasks.state.selected() {
             let index = app.tasks.display_tasks[selected];
             let task_id = app.tasks.tasks[index].id.clone();
+
+            // Recurring tasks move to their next date and stay where they are,
+            // subtasks included. The server's date replaces this guess.
+            if let Some(due) = app.tasks.tasks[index].due.as_ref().filter(|due| due.is_recurring) {
+                if let Some(next) = due.next_occurrence(chrono::Local::now().date_naive()) {
+                    app.notify(format!("Rescheduled to {}", tui::utils::format_due(&next)));
+                    let mut task = app.tasks.tasks[index].clone();
+                    task.due = Some(next);
+                    app.upsert_task(task);
+                }
+                app.queue.push(Mutation::CloseTask {
+                    task_id,
+                    recurring: true,
+                    uuid: queue::close_uuid(),
+                });
+                app.queue.spawn_flush(client, tx);
+                return;
+            }
             
-            // Find all children (and their children) to complete
-            let mut tasks_to_complete = Vec::new();
-            tasks_to_complete.push(task_id.clone());
-            
-            // Recursively find all children
-            find_all_children(&app.tasks.tasks, &task_id, &mut tasks_to_complete);
-            
-            // Remove all tasks (parent + children) from the tasks list
-            // Sort indices in descending order to avoid index shifting issues
-            let mut indices_to_remove: Vec<usize> = tasks_to_complete.iter()
-                .filter_map(|task_id| app.tasks.tasks.iter().position(|t| t.id == *task_id))
-                .collect();
-            indices_to_remove.sort_by(|a, b| b.cmp(a)); // Sort descending
-            
-            for &task_index in &indices_to_remove {
-                app.tasks.tasks.remove(task_index);
+            // The task goes along with all its subtasks. Recurring subtasks
+            // are closed as well, but move to their next date rather than
+            // going away
+            let today = chrono::Local::now().date_naive();
+            let mut closes = vec![(task_id.clone(), false)];
+            let mut tasks_to_complete = vec![task_id.clone()];
+            let mut rescheduled = Vec::new();
+            for index in app.tasks.tree.descendants(&app.tasks.tasks, &task_id) {
+                let subtask = &app.tasks.tasks[index];
+                match subtask.due.as_ref().filter(|due| due.is_recurring) {
+                    Some(due) => {
+                        closes.push((subtask.id.clone(), true));
+                        if let Some(next) = due.next_occurrence(today) {
+                            rescheduled.push(Task {
+                                due: Some(next),
+                                ..subtask.clone()
+                            });
+                        }
+                    }
+                    None => {
+                        closes.push((subtask.id.clone(), false));
+                        tasks_to_complete.push(subtask.id.clone());
+                    }
+                }
+            }
+            app.tasks.remove_tasks(&tasks_to_complete);
+            for task in rescheduled {
+                app.tasks.upsert(task);
             }
             
             // Rebuild display_tasks list to ensure valid indices
@@ -281,24 +1313,36 @@ pub fn handle_tasks(app: &mut App, key: KeyEvent, client: Client) {
             app.tasks.state.select(new_selection);
             
             // Complete all tasks via API
-            for task_id_to_complete in tasks_to_complete {
-                let client_clone = client.clone();
-                tokio::spawn(async move {
-                    close_task(&client_clone, task_id_to_complete).await.unwrap();
+            for (task_id, recurring) in closes {
+                app.queue.push(Mutation::CloseTask {
+                    task_id,
+                    recurring,
+                    uuid: queue::close_uuid(),
                 });
             }
+            app.queue.spawn_flush(client, tx);
         }
-    } else if key.code == KeyCode::Char('n') {
-        if let Some(selected) = app.projects.state.selected() {
-            let selected_id = app.projects.projects[selected].id.clone();
-            app.show_new_task = true;
-            app.new_task = new_task::NewTask::new(selected_id, None);
+    } else if key.code == KeyCode::Char('S') {
+        open_sections(app);
+    } else if key.code == KeyCode::Char('M') {
+        open_move_picker(app);
+    } else if key.code == KeyCode::Char('m') {
+        if app.tasks.state.selected().is_none() {
+            return;
         }
-    } else if key.code == KeyCode::Char('a') {
-        if let Some(selected) = app.projects.state.selected() {
-            let selected_id = app.projects.projects[selected].id.clone();
-            app.show_new_task = true;
-            app.new_task = new_task::NewTask::new(selected_id, None);
+        // Moving by hand only shows in the manual order
+        if app.tasks.sort != SortCriterion::Manual {
+            app.tasks.sort_tasks(SortCriterion::Manual);
+            app.notify("Sorted in manual order".to_string());
+        }
+        app.tasks.move_mode = true;
+        app.tasks.reordered = false;
+    } else if key.code == KeyCode::Char('n') || key.code == KeyCode::Char('a') {
+        if let Some(project_id) = app.projects.selected().map(|project| project.id.clone()) {
+            if is_saved(app, &project_id) {
+                app.new_task = new_task::NewTask::new(project_id, None);
+                app.show_new_task = true;
+            }
         }
     } else if key.code == KeyCode::Char('d') {
         if let Some(selected) = app.tasks.state.selected() {
@@ -318,23 +1362,10 @@ pub fn handle_tasks(app: &mut App, key: KeyEvent, client: Client) {
                 None
             };
             
-            // Find all children (and their children) to delete
-            let mut tasks_to_delete = Vec::new();
-            tasks_to_delete.push(task_id.clone());
-            
-            // Recursively find all children
-            find_all_children(&app.tasks.tasks, &task_id, &mut tasks_to_delete);
-            
-            // Remove all tasks (parent + children) from the tasks list
-            // Sort indices in descending order to avoid index shifting issues
-            let mut indices_to_remove: Vec<usize> = tasks_to_delete.iter()
-                .filter_map(|task_id| app.tasks.tasks.iter().position(|t| t.id == *task_id))
-                .collect();
-            indices_to_remove.sort_by(|a, b| b.cmp(a)); // Sort descending
-            
-            for &task_index in &indices_to_remove {
-                app.tasks.tasks.remove(task_index);
-            }
+            // The task goes along with all its subtasks
+            let mut tasks_to_delete = vec![task_id.clone()];
+            tasks_to_delete.extend(app.tasks.descendant_ids(&task_id));
+            app.tasks.remove_tasks(&tasks_to_delete);
             
             // Rebuild display_tasks list to ensure valid indices
             app.tasks.filter_task_list(false);
@@ -359,12 +1390,10 @@ pub fn handle_tasks(app: &mut App, key: KeyEvent, client: Client) {
             }
             
             // Delete all tasks from the API
-            for task_id_to_delete in tasks_to_delete {
-                let client_clone = client.clone();
-                tokio::spawn(async move {
-                    delete_task(&client_clone, task_id_to_delete).await.unwrap();
-                });
+            for task_id in tasks_to_delete {
+                app.queue.push(Mutation::DeleteTask { task_id });
             }
+            app.queue.spawn_flush(client, tx);
         }
     } else if key.code == KeyCode::Char('o') {
         // Create subtask for selected task
@@ -374,48 +1403,162 @@ pub fn handle_tasks(app: &mut App, key: KeyEvent, client: Client) {
             app.show_new_task = true;
             app.new_task = new_task::NewTask::new(selected_task.project_id.clone(), Some(selected_task.id.clone()));
         }
-    } else if key.code == KeyCode::Char('1') {
-        // Set priority to 1 (highest)
-        if let Some(selected) = app.tasks.state.selected() {
-            let index = app.tasks.display_tasks[selected];
-            app.tasks.tasks[index].priority = 1;
-        }
-    } else if key.code == KeyCode::Char('2') {
-        // Set priority to 2
-        if let Some(selected) = app.tasks.state.selected() {
-            let index = app.tasks.display_tasks[selected];
-            app.tasks.tasks[index].priority = 2;
+    } else if let KeyCode::Char(c @ '1'..='4') = key.code {
+        // 1-4 are the p1..p4 levels of the official apps
+        let priority = Priority::from_level(c as u8 - b'0').unwrap_or_default();
+        if let Some(&index) = app.tasks.state.selected().and_then(|selected| app.tasks.display_tasks.get(selected)) {
+            let task = &mut app.tasks.tasks[index];
+            task.priority = priority;
+            let task_id = task.id.clone();
+            // A priority sort or filter may move or hide the task
+            app.tasks.refresh();
+            app.queue.push(Mutation::UpdateTask {
+                task_id,
+                json: serde_json::json!({ "priority": priority }),
+            });
+            app.queue.spawn_flush(client, tx);
         }
-    } else if key.code == KeyCode::Char('3') {
-        // Set priority to 3
-        if let Some(selected) = app.tasks.state.selected() {
-            let index = app.tasks.display_tasks[selected];
-            app.tasks.tasks[index].priority = 3;
+    }
+}
+
+/// The `:` prompt: Enter runs the typed command, Esc or a Backspace on an
+/// empty line leaves it.
+pub fn handle_command_line(app: &mut App, key: KeyEvent) {
+    match key.code {
+        KeyCode::Esc => app.show_command_line = false,
+        KeyCode::Backspace if app.command_line.text().is_empty() => app.show_command_line = false,
+        KeyCode::Enter => {
+            app.show_command_line = false;
+            match app.command_line.command() {
+                Ok(Command::Filter(text)) => match Query::parse(&text) {
+                    Ok(query) => {
+                        crate::apply_query(app, text, query);
+                        app.current_focus = crate::CurrentFocus::Tasks;
+                    }
+                    Err(e) => app.set_error_message(format!("Invalid filter: {}", e)),
+                },
+                Ok(Command::Upcoming(days)) => {
+                    crate::show_upcoming(app, days);
+                    app.current_focus = crate::CurrentFocus::Tasks;
+                }
+                Ok(Command::UpcomingWeek) => {
+                    let days = app.tasks.days_left_in_week(chrono::Local::now().date_naive());
+                    crate::show_upcoming(app, days);
+                    app.current_focus = crate::CurrentFocus::Tasks;
+                }
+                Err(e) => app.set_error_message(e),
+            }
         }
-    } else if key.code == KeyCode::Char('4') {
-        // Set priority to 4 (lowest)
-        if let Some(selected) = app.tasks.state.selected() {
-            let index = app.tasks.display_tasks[selected];
-            app.tasks.tasks[index].priority = 4;
+        _ => {
+            app.command_line.input.input(key);
         }
     }
 }
 
-fn find_all_children(tasks: &Vec<Task>, parent_id: &String, tasks_to_delete: &mut Vec<String>) {
-    for task in tasks {
-        if let Some(task_parent_id) = &task.parent_id {
-            if task_parent_id == parent_id {
-                tasks_to_delete.push(task.id.clone());
-                // Recursively find children of this child
-                find_all_children(tasks, &task.id, tasks_to_delete);
+/// Leaves task move mode and saves the new order of the moved task and its
+/// siblings with a single reorder request.
+fn finish_task_move(app: &mut App, client: Backend, tx: std::sync::mpsc::Sender<TaskResult>) {
+    app.tasks.move_mode = false;
+    if !std::mem::take(&mut app.tasks.reordered) {
+        return;
+    }
+    let Some(&index) = app.tasks.state.selected().and_then(|selected| app.tasks.display_tasks.get(selected)) else {
+        return;
+    };
+    let items: Vec<serde_json::Value> = app
+        .tasks
+        .siblings(index)
+        .into_iter()
+        .map(|i| {
+            let task = &app.tasks.tasks[i];
+            serde_json::json!({ "id": task.id, "child_order": task.order })
+        })
+        .collect();
+    app.queue.push(Mutation::ReorderTasks {
+        json: serde_json::json!({ "items": items }),
+    });
+    app.queue.spawn_flush(client, tx);
+}
+
+/// Moves around a board: `h`/`l` between columns, `j`/`k` within one and
+/// `H`/`L` to carry the selected card into the neighbouring column. Returns
+/// whether the key was used.
+fn handle_board(
+    app: &mut App,
+    key: KeyEvent,
+    client: Backend,
+    tx: std::sync::mpsc::Sender<TaskResult>,
+) -> bool {
+    let columns = board::columns(app);
+    let current = board::selected_column(app, &columns);
+    let selected = app.tasks.state.selected();
+
+    match key.code {
+        KeyCode::Char('h') | KeyCode::Left | KeyCode::Char('l') | KeyCode::Right => {
+            let left = matches!(key.code, KeyCode::Char('h') | KeyCode::Left);
+            // Empty columns have no card to put the cursor on
+            let target = if left {
+                columns[..current].iter().rposition(|column| !column.tasks.is_empty())
+            } else {
+                columns[current + 1..]
+                    .iter()
+                    .position(|column| !column.tasks.is_empty())
+                    .map(|position| position + current + 1)
+            };
+            match target {
+                Some(target) => {
+                    // Keep the cursor at the same height where possible
+                    let row = selected.map_or(0, |selected| selected - columns[current].tasks.start);
+                    let tasks = &columns[target].tasks;
+                    app.tasks.state.select(Some(tasks.start + row.min(tasks.len() - 1)));
+                }
+                None if left => {
+                    app.current_focus = crate::CurrentFocus::Projects;
+                    app.tasks.unselect();
+                }
+                None => {}
+            }
+        }
+        KeyCode::Char('j') | KeyCode::Down | KeyCode::Char('k') | KeyCode::Up => {
+            let tasks = &columns[current].tasks;
+            let Some(selected) = selected.filter(|_| !tasks.is_empty()) else {
+                return true;
+            };
+            let down = matches!(key.code, KeyCode::Char('j') | KeyCode::Down);
+            let next = match (down, selected) {
+                (true, selected) if selected + 1 >= tasks.end => tasks.start,
+                (true, selected) => selected + 1,
+                (false, selected) if selected <= tasks.start => tasks.end - 1,
+                (false, selected) => selected - 1,
+            };
+            app.tasks.state.select(Some(next));
+        }
+        KeyCode::Char('H') | KeyCode::Char('L') => {
+            let Some(&index) = selected.and_then(|selected| app.tasks.display_tasks.get(selected)) else {
+                return true;
+            };
+            let target = if key.code == KeyCode::Char('H') {
+                current.checked_sub(1)
+            } else {
+                Some(current + 1)
+            };
+            let Some(target) = target.and_then(|target| columns.get(target)) else {
+                return true;
+            };
+            if !columns[current].movable || !target.movable {
+                app.notify("Cards only move between the project's sections".to_string());
+                return true;
             }
+            move_to_section(app, index, target.section_id.clone(), client, tx);
         }
+        _ => return false,
     }
+    true
 }
 
 fn handle_priority_input(priority_string: &mut tui_textarea::TextArea, key: KeyEvent) {
     match key.code {
-        KeyCode::Char(c) if c.is_ascii_digit() && c >= '1' && c <= '4' => {
+        KeyCode::Char(c) if ('1'..='4').contains(&c) => {
             // Clear existing content and set the new digit
             *priority_string = tui_textarea::TextArea::from(vec![c.to_string()]);
         }
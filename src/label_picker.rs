@@ -0,0 +1,66 @@
+use ratatui::widgets::ListState;
+
+/// A checklist of label names opened from the task editors. The chosen names
+/// are copied back into the editor when it is closed.
+#[derive(Debug, Default)]
+pub struct LabelPicker {
+    pub names: Vec<String>,
+    pub chosen: Vec<String>,
+    pub state: ListState,
+}
+
+impl LabelPicker {
+    /// Lists every known label, plus any the task already has that are not
+    /// known (for example labels on tasks someone shared).
+    pub fn new(mut names: Vec<String>, chosen: Vec<String>) -> LabelPicker {
+        for name in &chosen {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+        let mut state = ListState::default();
+        if !names.is_empty() {
+            state.select(Some(0));
+        }
+        LabelPicker {
+            names,
+            chosen,
+            state,
+        }
+    }
+
+    pub fn next(&mut self) {
+        if self.names.is_empty() {
+            return;
+        }
+        let i = match self.state.selected() {
+            Some(i) if i + 1 < self.names.len() => i + 1,
+            _ => 0,
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn previous(&mut self) {
+        if self.names.is_empty() {
+            return;
+        }
+        let i = match self.state.selected() {
+            Some(0) | None => self.names.len() - 1,
+            Some(i) => i - 1,
+        };
+        self.state.select(Some(i));
+    }
+
+    /// Adds or removes the label under the cursor.
+    pub fn toggle(&mut self) {
+        let Some(name) = self.state.selected().and_then(|i| self.names.get(i)) else {
+            return;
+        };
+        match self.chosen.iter().position(|chosen| chosen == name) {
+            Some(position) => {
+                self.chosen.remove(position);
+            }
+            None => self.chosen.push(name.clone()),
+        }
+    }
+}
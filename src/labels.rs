@@ -0,0 +1,109 @@
+use ratatui::widgets::ListState;
+use serde::{Deserialize, Serialize};
+
+/// A personal label as returned by `GET /labels`. Tasks refer to labels by name.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Label {
+    pub id: String,
+    pub name: String,
+    pub color: String,
+    pub order: i32,
+    pub is_favorite: bool,
+}
+
+/// The labels shown in the sidebar: the user's own labels in their order,
+/// followed by shared labels (names used on shared tasks) that are not also
+/// personal ones.
+#[derive(Debug, Default)]
+pub struct Labels {
+    pub labels: Vec<Label>,
+    pub shared: Vec<String>,
+    pub state: ListState,
+    pub selected_label: Option<String>,
+}
+
+impl Labels {
+    pub fn new(mut labels: Vec<Label>, shared: Vec<String>) -> Labels {
+        labels.sort_by_key(|label| label.order);
+        Labels {
+            labels,
+            shared,
+            state: ListState::default(),
+            selected_label: None,
+        }
+    }
+
+    /// Every label name, in the order they are listed.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.labels.iter().map(|label| label.name.clone()).collect();
+        for name in &self.shared {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+        names
+    }
+
+    pub fn next(&mut self) {
+        let count = self.names().len();
+        if count == 0 {
+            self.state.select(None);
+            return;
+        }
+        let i = match self.state.selected() {
+            Some(i) if i + 1 < count => i + 1,
+            _ => 0,
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn previous(&mut self) {
+        let count = self.names().len();
+        if count == 0 {
+            self.state.select(None);
+            return;
+        }
+        let i = match self.state.selected() {
+            Some(0) | None => count - 1,
+            Some(i) => i - 1,
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn unselect(&mut self) {
+        let offset = self.state.offset();
+        self.state.select(None);
+        *self.state.offset_mut() = offset;
+        self.selected_label = None;
+    }
+
+    /// The name under the cursor.
+    pub fn selected_name(&self) -> Option<String> {
+        self.names().get(self.state.selected()?).cloned()
+    }
+
+    /// Applies synced changes like `Projects::merge`, keeping the cursor on the
+    /// same label.
+    pub fn merge(&mut self, updated: Vec<Label>, removed: Vec<String>, full_sync: bool) {
+        let selected_name = self.selected_name();
+
+        if full_sync {
+            self.labels
+                .retain(|label| updated.iter().any(|l| l.id == label.id));
+        }
+        self.labels.retain(|label| !removed.contains(&label.id));
+
+        for label in updated {
+            match self.labels.iter_mut().find(|l| l.id == label.id) {
+                Some(existing) => *existing = label,
+                None => self.labels.push(label),
+            }
+        }
+        self.labels.sort_by_key(|label| label.order);
+
+        if let Some(name) = selected_name {
+            let index = self.names().iter().position(|n| *n == name);
+            self.state.select(index);
+        }
+    }
+}
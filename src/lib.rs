@@ -0,0 +1,1162 @@
+use color_eyre::Result;
+use crossterm::event::{self, KeyCode, KeyEventKind};
+use dirs::config_dir;
+use key_handler::{
+    fold_projects, fold_tasks, handle_command_line, handle_comments, handle_filter_dialog, handle_filters,
+    handle_label_picker, handle_labels, handle_move_picker,
+    handle_new_tasks, handle_projects, handle_project_dialog, handle_sections, handle_task_editor, handle_tasks,
+};
+use labels::Labels;
+use new_task::NewTask;
+use projects::Projects;
+use sections::Sections;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{stdin, BufReader},
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, Sender, TryRecvError},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tasks::{Filter, Task, Tasks};
+use tokio::sync::Mutex;
+
+pub mod api_calls;
+pub mod api_error;
+pub mod backend;
+pub mod banner;
+pub mod board;
+pub mod cache;
+pub mod command_line;
+pub mod comments;
+pub mod database;
+pub mod due;
+pub mod error;
+pub mod filter_dialog;
+pub mod filters;
+pub mod key_handler;
+pub mod label_picker;
+pub mod labels;
+pub mod loading;
+pub mod mock_server;
+pub mod move_picker;
+pub mod new_task;
+pub mod priority;
+pub mod project_dialog;
+pub mod projects;
+pub mod query;
+pub mod queue;
+pub mod sections;
+pub mod store;
+pub mod sync;
+pub mod task_edit;
+pub mod task_tree;
+pub mod tasks;
+pub mod transfer;
+pub mod tui;
+
+#[derive(Debug, Default)]
+pub enum CurrentScreen {
+    #[default]
+    Main,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub enum CurrentFocus {
+    #[default]
+    Projects,
+    Tasks,
+    Filters,
+    Labels,
+    Help,
+    Error,
+}
+
+#[derive(Debug, Default)]
+pub struct App<'a> {
+    pub current_screen: CurrentScreen,
+    pub exit: bool,
+    pub projects: Projects,
+    pub current_focus: CurrentFocus,
+    pub tasks: Tasks,
+    pub show_help: bool,
+    pub labels: Labels,
+    pub filters: filters::Filters,
+    pub show_task_editor: bool,
+    pub task_edit: task_edit::TaskEdit<'a>,
+    pub show_new_task: bool,
+    pub new_task: NewTask<'a>,
+    pub show_comments: bool,
+    pub comments: comments::CommentsView<'a>,
+    pub show_label_picker: bool,
+    pub label_picker: label_picker::LabelPicker,
+    pub show_move_picker: bool,
+    pub move_picker: move_picker::MovePicker<'a>,
+    pub show_filter_dialog: bool,
+    pub filter_dialog: filter_dialog::FilterDialog<'a>,
+    pub show_command_line: bool,
+    pub command_line: command_line::CommandLine<'a>,
+    pub show_sections: bool,
+    pub sections_view: sections::SectionsView<'a>,
+    pub show_project_dialog: bool,
+    pub project_dialog: project_dialog::ProjectDialog<'a>,
+    pub error_message: Option<String>,
+    pub show_error: bool,
+    pub queue: queue::MutationQueue,
+    /// When the data on screen came from the cache, the time it was cached.
+    /// Cleared once it has been reconciled with the server.
+    pub cached_at: Option<u64>,
+    pub loading: loading::Loading,
+    /// A short message for the title bar and when it was set.
+    pub notice: Option<(String, Instant)>,
+    /// When `z` was pressed, while waiting to see if it starts a fold command.
+    pub pending_z: Option<Instant>,
+    /// The first `>` or `<` of an indent or outdent, until the second one.
+    pub pending_indent: Option<char>,
+}
+
+/// How long a notice stays in the title bar.
+const NOTICE_DURATION: Duration = Duration::from_secs(4);
+/// How long `z` waits for the rest of a fold command before opening the help.
+const FOLD_KEY_TIMEOUT: Duration = Duration::from_millis(500);
+
+impl<'a> App<'a> {
+    pub fn new() -> App<'a> {
+        App::default()
+    }
+
+    pub fn set_error_message(&mut self, message: String) {
+        self.error_message = Some(message);
+        self.show_error = true;
+    }
+
+    pub fn clear_error(&mut self) {
+        self.error_message = None;
+        self.show_error = false;
+    }
+
+    pub fn notify(&mut self, message: String) {
+        self.notice = Some((message, Instant::now()));
+    }
+
+    /// The current notice, until it has been shown for long enough.
+    pub fn notice(&self) -> Option<&str> {
+        self.notice
+            .as_ref()
+            .filter(|(_, since)| since.elapsed() < NOTICE_DURATION)
+            .map(|(message, _)| message.as_str())
+    }
+
+    /// Replaces a task with a newer copy, or adds it, keeping the cursor
+    /// where it was.
+    pub fn upsert_task(&mut self, task: Task) {
+        self.tasks.upsert(task);
+
+        // Preserve current cursor position when refreshing the task list
+        let current_selection = self.tasks.state.selected();
+        self.tasks.filter_task_list(false);
+        
+        // Restore cursor position if it was valid
+        if let Some(selection) = current_selection {
+            let display_tasks_len = self.tasks.display_tasks.len();
+            if selection < display_tasks_len {
+                self.tasks.state.select(Some(selection));
+            } else if display_tasks_len > 0 {
+                // If the old selection is out of bounds, select the last item
+                self.tasks.state.select(Some(display_tasks_len - 1));
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum TaskResult {
+    Task(Box<Task>),
+    /// A recurring task was completed and moved to its next date.
+    Rescheduled(Box<Task>),
+    /// A task created offline reached the server and got its real id.
+    Created { temp_id: String, task: Box<Task> },
+    Synced(Box<sync::SyncResponse>),
+    /// All comments of a task, for the comments pane.
+    Comments {
+        task_id: String,
+        comments: Vec<comments::Comment>,
+    },
+    /// A comment that was posted or edited.
+    Comment(Box<comments::Comment>),
+    /// A section that was created or renamed. `temp_id` is the placeholder
+    /// shown while it was being created.
+    Section {
+        temp_id: Option<String>,
+        section: Box<sections::Section>,
+    },
+    /// A project the server created or changed. `temp_id` is the placeholder
+    /// shown while it was being created.
+    Project {
+        temp_id: Option<String>,
+        project: Box<projects::Project>,
+    },
+    /// The server refused a project change that is already on screen.
+    ProjectFailed {
+        rollback: Box<projects::Rollback>,
+        error: String,
+    },
+    ArchivedProjects(Vec<projects::Project>),
+    /// A project was unarchived; its sections and tasks come back with a sync.
+    Unarchived,
+    Error(String),
+}
+
+/// Runs the app with the command line arguments it was started with.
+pub async fn run() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    // `--mock-server [ADDR]` only runs the bundled mock API, so other
+    // instances (or tests) can be pointed at it with TODOIST_API_URL.
+    if let Some(position) = args.iter().position(|arg| arg == "--mock-server") {
+        let addr = args
+            .get(position + 1)
+            .map(String::as_str)
+            .unwrap_or("127.0.0.1:8080")
+            .parse()?;
+        return mock_server::serve(addr, store::JsonStore::demo()).await;
+    }
+
+    // `--import` and `--export` copy everything between Todoist and the local
+    // database used by `--local`
+    if let Some(import) = args.iter().find_map(|arg| match arg.as_str() {
+        "--import" => Some(true),
+        "--export" => Some(false),
+        _ => None,
+    }) {
+        return transfer_local(import).await;
+    }
+
+    let (tx, rx): (Sender<TaskResult>, Receiver<TaskResult>) = mpsc::channel();
+    let use_local = args.iter().any(|arg| arg == "--local");
+    let use_mock = args.iter().any(|arg| arg == "--mock");
+    // `--store FILE` keeps everything in a local JSON file instead of Todoist
+    let store_path = args
+        .iter()
+        .position(|arg| arg == "--store")
+        .and_then(|position| args.get(position + 1))
+        .map(PathBuf::from);
+    // Only a Todoist account changes behind our back and needs syncing, and
+    // only a real one has state worth keeping between sessions
+    let remote = !use_local && store_path.is_none();
+    let persistent = remote && !use_mock;
+
+    let client: backend::Backend = if use_local {
+        Arc::new(database::SqliteBackend::open(local_database_path()?)?)
+    } else if let Some(path) = store_path {
+        Arc::new(backend::LocalBackend::open(path)?)
+    } else if use_mock {
+        // Run against an in-process mock server seeded with demo data
+        let addr = mock_server::spawn(([127, 0, 0, 1], 0).into(), store::JsonStore::demo())?;
+        let base_url = format!("http://{}", addr);
+        let sync_url = format!("{}/sync", base_url);
+        Arc::new(backend::RestBackend(api_calls::ApiClient::new("mock", base_url, sync_url)?))
+    } else {
+        let base_url = api_base_url();
+        let sync_url = sync_url(&base_url);
+        Arc::new(backend::RestBackend(api_calls::ApiClient::new(&get_token(), base_url, sync_url)?))
+    };
+
+    error::install_hooks()?;
+    let mut terminal = tui::init()?;
+    // Changes made while offline are kept on disk, except for mock and local sessions
+    let mutation_queue = if persistent {
+        queue::MutationQueue::load(queue::MutationQueue::default_path())
+    } else {
+        queue::MutationQueue::load(None)
+    };
+    mutation_queue.spawn_periodic_flush(
+        std::time::Duration::from_secs(QUEUE_RETRY_INTERVAL_SECS),
+        client.clone(),
+        tx.clone(),
+    );
+
+    let mut app = App::new();
+    if let Some(e) = mutation_queue.load_error() {
+        app.set_error_message(e.to_string());
+    }
+    app.queue = mutation_queue.clone();
+    app.filters = filters::Filters::new(Vec::new(), load_local_filters());
+
+    // Show the last session right away while the server is asked for changes
+    let cache_manager = if persistent {
+        cache::CacheManager::new().ok()
+    } else {
+        None
+    };
+    let restored = match cache_manager.as_ref().and_then(load_cache) {
+        Some(cache_data) => {
+            restore_from_cache(&mut app, cache_data);
+            true
+        }
+        None => false,
+    };
+    let app = Arc::new(Mutex::new(app));
+
+    // The saved token only describes the data in the cache; without it the
+    // next sync has to be a full one
+    let sync_engine = if persistent {
+        let sync_token = load_config().and_then(|c| c.sync_token).filter(|_| restored);
+        sync::SyncEngine::new(client.clone(), sync_token, true)
+    } else {
+        sync::SyncEngine::new(client.clone(), None, false)
+    };
+    let sync_interval = load_config()
+        .and_then(|config| config.sync_interval_secs)
+        .unwrap_or(DEFAULT_SYNC_INTERVAL_SECS);
+    if remote && sync_interval > 0 {
+        sync_engine.spawn_periodic(std::time::Duration::from_secs(sync_interval), tx.clone());
+    }
+
+    let mut initialise_task = tokio::spawn(initial_load(
+        Arc::clone(&app),
+        client.clone(),
+        cache_manager.clone(),
+        tx.clone(),
+    ));
+
+    let app_handle = Arc::clone(&app);
+    loop {
+        let mut app = app.lock().await;
+        terminal.draw(|frame| tui::ui(frame, &mut app))?;
+
+        if event::poll(std::time::Duration::from_millis(150))? {
+            if let event::Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    // Opened from an editor, so it takes keys first
+                    if app.show_label_picker {
+                        handle_label_picker(&mut app, key);
+                        continue;
+                    }
+
+                    if app.show_move_picker {
+                        handle_move_picker(&mut app, key, client.clone(), tx.clone());
+                        continue;
+                    }
+
+                    if app.show_command_line {
+                        handle_command_line(&mut app, key);
+                        continue;
+                    }
+
+                    if app.show_comments {
+                        handle_comments(&mut app, key, client.clone(), tx.clone());
+                        continue;
+                    }
+
+                    if app.show_project_dialog {
+                        handle_project_dialog(&mut app, key, client.clone(), tx.clone());
+                        continue;
+                    }
+
+                    if app.show_filter_dialog {
+                        handle_filter_dialog(&mut app, key);
+                        continue;
+                    }
+
+                    if app.show_sections {
+                        handle_sections(&mut app, key, client.clone(), tx.clone());
+                        continue;
+                    }
+
+                    if app.show_task_editor {
+                        handle_task_editor(&mut app, key, client.clone(), tx.clone());
+                        continue;
+                    }
+
+                    if app.show_new_task {
+                        handle_new_tasks(&mut app, key, client.clone(), tx.clone());
+                        continue;
+                    }
+
+                    if app.show_error {
+                        if key.code == KeyCode::Esc {
+                            app.clear_error();
+                        }
+                        continue;
+                    }
+
+                    // `z` alone toggles the help; followed by a, o or c it
+                    // folds the selected project or task, and R or M opens
+                    // or closes every task
+                    if app.pending_z.take().is_some() {
+                        if app.current_focus == CurrentFocus::Projects
+                            && matches!(key.code, KeyCode::Char('a' | 'o' | 'c'))
+                        {
+                            fold_projects(&mut app, key);
+                            continue;
+                        }
+                        if app.current_focus == CurrentFocus::Tasks
+                            && matches!(key.code, KeyCode::Char('a' | 'o' | 'c' | 'R' | 'M'))
+                        {
+                            fold_tasks(&mut app, key);
+                            continue;
+                        }
+                        app.show_help = !app.show_help;
+                    }
+
+                    // On a board, h and l move between its columns
+                    let on_board = app.current_focus == CurrentFocus::Tasks && board::showing(&app);
+                    if key.code == KeyCode::Char('z') {
+                        if app.show_help {
+                            app.show_help = false;
+                        } else {
+                            app.pending_z = Some(Instant::now());
+                        }
+                    } else if key.code == KeyCode::Char('h') && !on_board {
+                        app.current_focus = CurrentFocus::Projects;
+                        app.tasks.unselect();
+                    } else if key.code == KeyCode::Char('l') && !on_board {
+                        app.current_focus = CurrentFocus::Tasks;
+                        // Ensure first task is selected when switching to task view
+                        if !app.tasks.display_tasks.is_empty() {
+                            app.tasks.state.select(Some(0));
+                        }
+                        // Or a board would take the same key as a move right
+                        if !app.show_help {
+                            continue;
+                        }
+                    } else if key.code == KeyCode::Char('q') {
+                        break;
+                    } else if key.code == KeyCode::Char(':') {
+                        app.command_line = command_line::CommandLine::default();
+                        app.show_command_line = true;
+                        continue;
+                    } else if key.code == KeyCode::Char('t') {
+                        app.tasks.filter = Filter::Today;
+                        app.tasks.filter_task_list(true);
+                        app.projects.unselect();
+                        app.labels.unselect();
+                        app.filters.unselect();
+                    } else if key.code == KeyCode::Char('o') {
+                        // Only apply overdue filter if not in task focus
+                        if app.current_focus != CurrentFocus::Tasks {
+                            app.tasks.filter = Filter::Overdue;
+                            app.tasks.filter_task_list(true);
+                            app.projects.unselect();
+                            app.labels.unselect();
+                            app.filters.unselect();
+                        }
+                    } else if key.code == KeyCode::Char('u') {
+                        show_upcoming(&mut app, tasks::UPCOMING_DAYS);
+                    } else if key.code == KeyCode::Char('e') {
+                        // In the filters pane `e` edits the selected filter
+                        if app.current_focus != CurrentFocus::Filters {
+                            app.tasks.filter = Filter::All;
+                            app.tasks.filter_task_list(true);
+                            app.projects.unselect();
+                            app.labels.unselect();
+                            app.filters.unselect();
+                        }
+                    } else if key.code == KeyCode::Char('p') {
+                        app.tasks.sort_tasks(tasks::SortCriterion::Priority);
+                    } else if key.code == KeyCode::Char('d') {
+                        app.tasks.sort_tasks(tasks::SortCriterion::Date);
+                    } else if key.code == KeyCode::Char('O') {
+                        app.tasks.sort_tasks(tasks::SortCriterion::Manual);
+                    } else if key.code == KeyCode::Char('r') {
+                        if app.loading.has_failed() && !app.loading.in_progress() {
+                            // Retry the initial load rather than syncing into an empty app
+                            initialise_task = tokio::spawn(initial_load(
+                                Arc::clone(&app_handle),
+                                client.clone(),
+                                cache_manager.clone(),
+                                tx.clone(),
+                            ));
+                        } else {
+                            sync_engine.spawn_sync(tx.clone(), true);
+                        }
+                    }
+
+                    if app.show_help {
+                        continue;
+                    }
+
+                    if key.code == KeyCode::Tab {
+                        match app.current_focus {
+                            // Tab goes down the sidebar, through the labels if there are any
+                            CurrentFocus::Projects => {
+                                app.current_focus = CurrentFocus::Filters;
+                            }
+                            CurrentFocus::Filters if !app.labels.names().is_empty() => {
+                                app.current_focus = CurrentFocus::Labels;
+                            }
+                            CurrentFocus::Filters | CurrentFocus::Labels => {
+                                app.current_focus = CurrentFocus::Tasks;
+                                // Ensure first task is selected when switching to task view
+                                if !app.tasks.display_tasks.is_empty() {
+                                    app.tasks.state.select(Some(0));
+                                }
+                            },
+                            CurrentFocus::Tasks => {
+                                app.current_focus = CurrentFocus::Projects;
+                                app.tasks.unselect();
+                            },
+                            _ => {}
+                        }
+                    }
+
+                    if app.current_focus == CurrentFocus::Projects {
+                        handle_projects(&mut app, key, client.clone(), tx.clone())
+                    } else if app.current_focus == CurrentFocus::Filters {
+                        handle_filters(&mut app, key)
+                    } else if app.current_focus == CurrentFocus::Labels {
+                        handle_labels(&mut app, key)
+                    } else if app.current_focus == CurrentFocus::Tasks {
+                        handle_tasks(&mut app, key, client.clone(), tx.clone())
+                    }
+                }
+            }
+        }
+
+        if app.pending_z.is_some_and(|since| since.elapsed() >= FOLD_KEY_TIMEOUT) {
+            app.pending_z = None;
+            app.show_help = !app.show_help;
+        }
+
+        match rx.try_recv() {
+            Ok(received) => handle_result(&mut app, received, &sync_engine, cache_manager.as_ref(), &tx),
+            Err(TryRecvError::Empty) => continue,
+            Err(TryRecvError::Disconnected) => break,
+        }
+    }
+    tui::restore()?;
+    initialise_task.abort();
+    let _ = initialise_task.await;
+
+    if let Some(cache_manager) = &cache_manager {
+        let app = app.lock().await;
+        // Nothing was ever loaded, keep whatever was cached before
+        if !app.projects.projects.is_empty() {
+            if let Err(e) = cache_manager.save_cache(&cache::CacheData::from_app(&app)) {
+                eprintln!("Failed to save cache: {}", e);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn local_database_path() -> Result<PathBuf> {
+    database::SqliteBackend::default_path()
+        .ok_or_else(|| color_eyre::eyre::eyre!("Could not find a data directory for the local database"))
+}
+
+/// Copies everything from Todoist into the local database (`import`) or back.
+async fn transfer_local(import: bool) -> Result<()> {
+    let path = local_database_path()?;
+    let local = database::SqliteBackend::open(path.clone())?;
+    let base_url = api_base_url();
+    let sync_url = sync_url(&base_url);
+    let todoist = backend::RestBackend(api_calls::ApiClient::new(&get_token(), base_url, sync_url)?);
+
+    let copied = if import {
+        println!("Importing from Todoist into {}...", path.display());
+        transfer::copy(&todoist, &local).await?
+    } else {
+        println!("Exporting {} to Todoist...", path.display());
+        transfer::copy(&local, &todoist).await?
+    };
+    println!("Copied {}.", copied);
+    Ok(())
+}
+
+/// Applies what a background request sent back over the task channel.
+pub fn handle_result(
+    app: &mut App,
+    received: TaskResult,
+    sync_engine: &sync::SyncEngine,
+    cache_manager: Option<&cache::CacheManager>,
+    tx: &Sender<TaskResult>,
+) {
+    match received {
+        TaskResult::Task(task) => app.upsert_task(*task),
+        TaskResult::Rescheduled(task) => {
+            if let Some(due) = &task.due {
+                app.notify(format!("Rescheduled to {}", tui::utils::format_due(due)));
+            }
+            app.upsert_task(*task);
+        }
+        TaskResult::Created { temp_id, task } => {
+            if app.task_edit.task_id == temp_id {
+                app.task_edit.task_id = task.id.clone();
+            }
+            if app.move_picker.task_id == temp_id {
+                app.move_picker.task_id = task.id.clone();
+            }
+            app.tasks.replace_temp(&temp_id, *task);
+        }
+        TaskResult::Synced(response) => {
+            let sync_token = response.sync_token.clone();
+            sync::apply(app, *response);
+            app.cached_at = None;
+            // The token is kept only along with the data it describes
+            if let Some(cache_manager) = cache_manager {
+                if cache_manager.save_cache(&cache::CacheData::from_app(app)).is_ok() {
+                    if let Err(e) = sync_engine.save_token(&sync_token) {
+                        app.set_error_message(format!("Failed to save sync token: {}", e));
+                    }
+                }
+            }
+        }
+        TaskResult::Comments { task_id, comments } => {
+            if let Some(task) = app.tasks.tasks.iter_mut().find(|t| t.id == task_id) {
+                task.comment_count = comments.len() as u16;
+            }
+            if app.comments.task_id == task_id {
+                app.comments.set_comments(comments);
+            }
+        }
+        TaskResult::Comment(comment) => {
+            let task_id = comment.task_id.clone().unwrap_or_default();
+            if app.comments.task_id == task_id && app.comments.upsert(*comment) {
+                if let Some(task) = app.tasks.tasks.iter_mut().find(|t| t.id == task_id) {
+                    task.comment_count += 1;
+                }
+            }
+        }
+        TaskResult::Section { temp_id, section } => {
+            match temp_id {
+                Some(temp_id) => {
+                    for task in &mut app.tasks.tasks {
+                        if task.section_id.as_deref() == Some(temp_id.as_str()) {
+                            task.section_id = Some(section.id.clone());
+                        }
+                    }
+                    app.tasks.reindex();
+                    app.tasks.sections.replace_temp(&temp_id, *section);
+                }
+                None => app.tasks.sections.merge(vec![*section], Vec::new(), false),
+            }
+            app.tasks.refresh();
+        }
+        TaskResult::Project { temp_id, project } => match temp_id {
+            Some(temp_id) => {
+                let id = project.id.clone();
+                app.projects.replace_temp(&temp_id, *project);
+                if app.projects.selected_project.as_deref() == Some(temp_id.as_str()) {
+                    show_project(app, id);
+                }
+            }
+            None => app.projects.merge(vec![*project], Vec::new(), false),
+        },
+        TaskResult::ProjectFailed { rollback, error } => {
+            let rollback = *rollback;
+            app.projects.roll_back(rollback.projects);
+            app.tasks.sections.merge(rollback.sections, Vec::new(), false);
+            for task in rollback.tasks {
+                if app.tasks.tree.position(&task.id).is_none() {
+                    app.tasks.upsert(task);
+                }
+            }
+            app.tasks.refresh();
+            if let Filter::ProjectId(_) = app.tasks.filter {
+                let selected = app.projects.selected_project.clone();
+                select_project(app, selected);
+            }
+            app.set_error_message(error);
+        }
+        TaskResult::ArchivedProjects(archived) => {
+            app.project_dialog.set_archived(archived);
+        }
+        TaskResult::Unarchived => sync_engine.spawn_sync(tx.clone(), true),
+        TaskResult::Error(error_msg) => {
+            app.set_error_message(error_msg);
+        }
+    }
+}
+
+/// Resources fetched on startup. They are requested concurrently; projects and
+/// tasks are required, the rest only add detail.
+const INITIAL_RESOURCES: [&str; 4] = ["projects", "tasks", "sections", "labels"];
+
+/// Fetches everything from the backend and replaces what is shown, keeping the
+/// user's place if they were already looking at cached data.
+pub async fn initial_load(
+    app: Arc<Mutex<App<'static>>>,
+    api: backend::Backend,
+    cache_manager: Option<cache::CacheManager>,
+    tx: Sender<TaskResult>,
+) {
+    let mutation_queue = {
+        let mut app = app.lock().await;
+        app.loading = loading::Loading::start(&INITIAL_RESOURCES);
+        app.queue.clone()
+    };
+
+    // Filters and the user's settings only come with a sync, which is limited
+    // to them; the rest is fetched above
+    let (project_resp, task_resp, sections_resp, labels_resp, settings_resp) = tokio::join!(
+        async {
+            let result = api.list_projects().await;
+            app.lock().await.loading.finish("projects", &result);
+            result
+        },
+        async {
+            let result = api.list_tasks().await;
+            app.lock().await.loading.finish("tasks", &result);
+            result
+        },
+        async {
+            let result = api.list_sections().await;
+            app.lock().await.loading.finish("sections", &result);
+            result
+        },
+        async {
+            let (result, shared) = tokio::join!(api.list_labels(), api.list_shared_labels());
+            app.lock().await.loading.finish("labels", &result);
+            // Shared labels only add names to the sidebar
+            result.map(|labels| (labels, shared.unwrap_or_default()))
+        },
+        api.sync_settings(),
+    );
+
+    let mut app = app.lock().await;
+    let from_cache = app.cached_at.is_some();
+
+    let (project_resp, mut task_resp) = match (project_resp, task_resp) {
+        (Ok(projects), Ok(tasks)) => (projects, tasks),
+        (Err(e), _) | (_, Err(e)) => {
+            // Without cached data the loading screen already shows the failure
+            if from_cache {
+                let _ = tx.send(TaskResult::Error(format!("Failed to refresh: {}", e)));
+            }
+            return;
+        }
+    };
+    if let Err(e) = &sections_resp {
+        if from_cache {
+            let _ = tx.send(TaskResult::Error(format!("Failed to load sections: {}", e)));
+        }
+    }
+    if let Err(e) = &labels_resp {
+        if from_cache {
+            let _ = tx.send(TaskResult::Error(format!("Failed to load labels: {}", e)));
+        }
+    }
+    if let Err(e) = &settings_resp {
+        let _ = tx.send(TaskResult::Error(format!("Failed to load filters: {}", e)));
+    }
+
+    let mut projects = Projects::new(project_resp);
+
+    // Apply saved project order if available
+    if let Some(saved_order) = load_project_order() {
+        let mut reordered_projects = Vec::new();
+        let mut remaining_projects = projects.projects.clone();
+
+        // First, add projects in the saved order
+        for project_id in &saved_order {
+            if let Some(pos) = remaining_projects.iter().position(|p| p.id == *project_id) {
+                reordered_projects.push(remaining_projects.remove(pos));
+            }
+        }
+
+        // Then add any remaining projects that weren't in the saved order
+        reordered_projects.extend(remaining_projects);
+
+        projects.projects = reordered_projects;
+    }
+    load_project_view(&mut projects);
+
+    mutation_queue.apply_pending(&mut task_resp);
+    let mut tasks = Tasks::new(task_resp);
+    load_task_folds(&mut tasks);
+    let mut sections = Sections::new(sections_resp.unwrap_or_default());
+    mutation_queue.apply_pending_sections(&mut sections.sections);
+    let (labels, shared_labels) = labels_resp.unwrap_or_default();
+
+    // Whatever the user opened while the cached data was shown stays open
+    let selected_project = app.projects.selected_project.clone();
+    let selected_label = app.labels.selected_label.clone();
+    let selected_task = app.tasks.selected_task_id();
+    let filter = std::mem::take(&mut app.tasks.filter);
+    let sort = app.tasks.sort;
+    let start_day = app.tasks.start_day;
+
+    app.projects = projects;
+    app.tasks = tasks;
+    app.tasks.sort = sort;
+    app.tasks.start_day = start_day;
+    app.tasks.sections = sections;
+    app.tasks.projects = query_projects(&app.projects);
+    app.labels = Labels::new(labels, shared_labels);
+    if let Ok(response) = settings_resp {
+        sync::apply_settings(&mut app, response.filters, response.user, true);
+    }
+
+    if !from_cache {
+        // Select the first project on startup and filter tasks by it
+        select_project(&mut app, None);
+    } else if let Filter::ProjectId(_) = filter {
+        select_project(&mut app, selected_project);
+    } else if let (Filter::Label(_), Some(name)) = (&filter, selected_label) {
+        select_label(&mut app, &name);
+    } else {
+        app.tasks.filter = filter;
+        app.tasks.filter_task_list(false);
+        app.projects.selected_project = selected_project;
+    }
+    if let Some(task_id) = selected_task {
+        app.tasks.select_task_id(&task_id);
+    }
+
+    app.cached_at = None;
+    if let Some(cache_manager) = cache_manager {
+        let _ = cache_manager.save_cache(&cache::CacheData::from_app(&app));
+    }
+}
+
+/// Loads the cache, discarding it if it cannot be read (for example after an
+/// upgrade changed its format).
+fn load_cache(cache_manager: &cache::CacheManager) -> Option<cache::CacheData> {
+    match cache_manager.load_cache() {
+        Ok(cache_data) => cache_data,
+        Err(_) => {
+            let _ = cache_manager.clear_cache();
+            None
+        }
+    }
+}
+
+fn restore_from_cache(app: &mut App, cache_data: cache::CacheData) {
+    let mut tasks = cache_data.tasks;
+    app.queue.apply_pending(&mut tasks);
+
+    app.cached_at = Some(cache_data.timestamp);
+    app.projects = Projects::new(cache_data.projects);
+    load_project_view(&mut app.projects);
+    app.tasks = Tasks::new(tasks);
+    load_task_folds(&mut app.tasks);
+    app.tasks.sections = Sections::new(cache_data.sections);
+    app.queue.apply_pending_sections(&mut app.tasks.sections.sections);
+    app.tasks.start_day = cache_data.start_day.and_then(tasks::start_day);
+    app.labels = Labels::new(cache_data.labels, cache_data.shared_labels);
+    app.filters.merge(cache_data.filters, Vec::new(), true);
+    let selected_filter = cache_data
+        .selected_filter
+        .filter(|filter_id| app.filters.filters.iter().any(|filter| filter.id == *filter_id));
+    match (cache_data.selected_label, selected_filter) {
+        (_, Some(filter_id)) if select_filter(app, &filter_id) => {}
+        (Some(name), _) => select_label(app, &name),
+        _ => select_project(app, cache_data.selected_project_id),
+    }
+
+    if let Some(cursor) = cache_data.cursor_position {
+        if cursor < app.tasks.display_tasks.len() {
+            app.tasks.state.select(Some(cursor));
+            app.current_focus = CurrentFocus::Tasks;
+        }
+    }
+}
+
+/// Selects a project and shows its tasks, falling back to the first project if
+/// it is not given or no longer exists.
+pub fn select_project(app: &mut App, project_id: Option<String>) {
+    let found = project_id.is_some_and(|id| app.projects.select_id(&id));
+    if !found {
+        let first = if app.projects.rows.is_empty() { None } else { Some(0) };
+        app.projects.state.select(first);
+    }
+
+    match app.projects.selected().map(|project| project.id.clone()) {
+        Some(selected_id) => show_project(app, selected_id),
+        None => app.tasks.filter_task_list(false),
+    }
+}
+
+/// Shows the tasks of a project, and of its sub-projects if they are
+/// included.
+pub fn show_project(app: &mut App, project_id: String) {
+    app.tasks.subprojects = if app.projects.include_subprojects {
+        app.projects
+            .descendants(&project_id)
+            .into_iter()
+            .map(|project| (project.id.clone(), project.name.clone()))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    app.tasks.filter = Filter::ProjectId(project_id.clone());
+    app.tasks.filter_task_list(false);
+    app.projects.selected_project = Some(project_id);
+    app.labels.unselect();
+    app.filters.unselect();
+}
+
+/// Shows the tasks matching a filter query in place of a project or label.
+pub fn apply_query(app: &mut App, text: String, query: query::Query) {
+    app.tasks.projects = query_projects(&app.projects);
+    app.tasks.filter = Filter::Query { text, query };
+    app.tasks.filter_task_list(true);
+    app.projects.unselect();
+    app.projects.selected_project = None;
+    app.labels.unselect();
+    app.filters.unselect();
+}
+
+/// Shows overdue tasks and those due in the next `days` days by day.
+pub fn show_upcoming(app: &mut App, days: i64) {
+    app.tasks.filter = Filter::Upcoming { days };
+    app.tasks.filter_task_list(true);
+    app.projects.unselect();
+    app.labels.unselect();
+    app.filters.unselect();
+}
+
+/// Shows the tasks of a saved filter, moving the sidebar cursor onto it. An
+/// invalid query is reported and leaves the view as it was.
+pub fn select_filter(app: &mut App, filter_id: &str) -> bool {
+    let Some(index) = app.filters.filters.iter().position(|filter| filter.id == filter_id) else {
+        return false;
+    };
+    let filter = app.filters.filters[index].clone();
+    match filter.parse() {
+        Ok(query) => {
+            apply_query(app, filter.name, query);
+            app.filters.state.select(Some(index));
+            app.filters.selected_filter = Some(filter.id);
+            true
+        }
+        Err(e) => {
+            app.set_error_message(format!("Invalid filter {}: {}", filter.name, e));
+            false
+        }
+    }
+}
+
+/// The projects as queries see them: id, name and parent id.
+pub fn query_projects(projects: &Projects) -> Vec<(String, String, Option<String>)> {
+    projects
+        .projects
+        .iter()
+        .map(|project| (project.id.clone(), project.name.clone(), project.parent_id.clone()))
+        .collect()
+}
+
+/// Shows the tasks with a label, moving the sidebar cursor onto it.
+fn select_label(app: &mut App, name: &str) {
+    let index = app.labels.names().iter().position(|n| n == name);
+    app.labels.state.select(index);
+    app.labels.selected_label = Some(name.to_string());
+    app.projects.unselect();
+    app.projects.selected_project = None;
+    app.filters.unselect();
+    app.tasks.filter = Filter::Label(name.to_string());
+    app.tasks.filter_task_list(false);
+}
+
+const DEFAULT_SYNC_INTERVAL_SECS: u64 = 60;
+/// How often queued changes are retried while offline.
+const QUEUE_RETRY_INTERVAL_SECS: u64 = 15;
+
+#[derive(Deserialize, Serialize)]
+struct Config {
+    bearer_token: String,
+    project_order: Option<Vec<String>>,
+    /// Projects folded in the sidebar.
+    folded_projects: Option<Vec<String>>,
+    /// Tasks whose subtasks are folded away in the task list.
+    folded_tasks: Option<Vec<String>>,
+    /// Saved filters kept on this machine rather than in the account.
+    filters: Option<Vec<filters::SavedFilter>>,
+    include_subprojects: Option<bool>,
+    api_url: Option<String>,
+    sync_url: Option<String>,
+    sync_token: Option<String>,
+    sync_interval_secs: Option<u64>,
+}
+
+/// Where the config and the queue of pending changes are kept:
+/// `TODOIST_CONFIG_DIR` if set, else `<config dir>/.todoist`.
+pub fn todoist_config_dir() -> Option<std::path::PathBuf> {
+    std::env::var_os("TODOIST_CONFIG_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(std::path::PathBuf::from)
+        .or_else(|| config_dir().map(|home| home.join(".todoist")))
+}
+
+fn config_file_path() -> Option<std::path::PathBuf> {
+    todoist_config_dir().map(|dir| dir.join("config.json"))
+}
+
+fn load_config() -> Option<Config> {
+    let file = File::open(config_file_path()?).ok()?;
+    serde_json::from_reader(BufReader::new(file)).ok()
+}
+
+/// Held while the config is read, changed and written back, so settings
+/// saved from different tasks at the same time do not undo each other.
+static CONFIG_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+/// Changes the saved config, if there is one. It is written to a temporary
+/// file that then replaces the config, so a crash never leaves half of it.
+fn update_config(change: impl FnOnce(&mut Config)) -> Result<(), Box<dyn std::error::Error>> {
+    let config_file_path = config_file_path().ok_or("No config directory found")?;
+    let _guard = CONFIG_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(mut config) = load_config() {
+        change(&mut config);
+        write_config(&config_file_path, &config)?;
+    }
+    Ok(())
+}
+
+fn write_config(path: &Path, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let temp_path = path.with_extension("json.tmp");
+    fs::write(&temp_path, serde_json::to_string(config)?)?;
+    fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+/// The REST base URL, taken from `TODOIST_API_URL`, then the `api_url` config
+/// key, falling back to the real Todoist API.
+fn api_base_url() -> String {
+    std::env::var("TODOIST_API_URL")
+        .ok()
+        .filter(|url| !url.is_empty())
+        .or_else(|| load_config().and_then(|config| config.api_url))
+        .unwrap_or_else(|| api_calls::DEFAULT_BASE_URL.to_string())
+}
+
+/// The Sync API endpoint, taken from `TODOIST_SYNC_URL`, then the `sync_url`
+/// config key. A custom REST base URL gets `<base>/sync` so a proxy or mock
+/// server never has its syncs sent to the real API.
+fn sync_url(base_url: &str) -> String {
+    std::env::var("TODOIST_SYNC_URL")
+        .ok()
+        .filter(|url| !url.is_empty())
+        .or_else(|| load_config().and_then(|config| config.sync_url))
+        .unwrap_or_else(|| {
+            if base_url == api_calls::DEFAULT_BASE_URL {
+                api_calls::DEFAULT_SYNC_URL.to_string()
+            } else {
+                format!("{}/sync", base_url.trim_end_matches('/'))
+            }
+        })
+}
+
+pub fn save_project_order(project_order: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    update_config(|config| config.project_order = Some(project_order.to_vec()))
+}
+
+pub fn save_sync_token(sync_token: &str) -> Result<(), Box<dyn std::error::Error>> {
+    update_config(|config| config.sync_token = Some(sync_token.to_string()))
+}
+
+fn load_project_order() -> Option<Vec<String>> {
+    load_config()?.project_order
+}
+
+/// Saves which projects are folded and whether sub-project tasks are shown.
+pub fn save_project_view(projects: &Projects) -> Result<(), Box<dyn std::error::Error>> {
+    let mut folded: Vec<String> = projects.folded.iter().cloned().collect();
+    folded.sort();
+    update_config(|config| {
+        config.folded_projects = Some(folded);
+        config.include_subprojects = Some(projects.include_subprojects);
+    })
+}
+
+fn load_project_view(projects: &mut Projects) {
+    if let Some(config) = load_config() {
+        projects.folded = config.folded_projects.unwrap_or_default().into_iter().collect();
+        projects.include_subprojects = config.include_subprojects.unwrap_or_default();
+    }
+    projects.arrange();
+}
+
+/// Saves which tasks are folded. Tasks that are gone are forgotten.
+pub fn save_task_folds(tasks: &Tasks) -> Result<(), Box<dyn std::error::Error>> {
+    let mut folded: Vec<String> = tasks
+        .folded
+        .iter()
+        .filter(|id| tasks.tree.position(id).is_some())
+        .cloned()
+        .collect();
+    folded.sort();
+    update_config(|config| config.folded_tasks = Some(folded))
+}
+
+pub fn save_local_filters(filters: &filters::Filters) -> Result<(), Box<dyn std::error::Error>> {
+    let filters = filters.local();
+    update_config(|config| config.filters = Some(filters))
+}
+
+fn load_local_filters() -> Vec<filters::SavedFilter> {
+    load_config().and_then(|config| config.filters).unwrap_or_default()
+}
+
+fn load_task_folds(tasks: &mut Tasks) {
+    if let Some(config) = load_config() {
+        tasks.folded = config.folded_tasks.unwrap_or_default().into_iter().collect();
+    }
+}
+
+fn get_token() -> String {
+    let mut client_key = String::new();
+    match todoist_config_dir() {
+        Some(config_dir) => {
+            if !config_dir.exists() {
+                fs::create_dir_all(&config_dir).unwrap();
+            }
+            let config_file_path = &config_dir.join("config.json");
+
+            if config_file_path.exists() {
+                let file = File::open(config_file_path).unwrap();
+                let reader = BufReader::new(file);
+
+                let config: Config = serde_json::from_reader(reader).unwrap();
+                client_key = config.bearer_token;
+
+                println!("Your config is saved at {}", config_file_path.display());
+            } else {
+                println!("{}", banner::BANNER);
+
+                println!();
+
+                println!("\nHow to get setup:");
+                println!("-----------------\n");
+
+                let instructions = [
+                    "Go to the todoist integrations - https://app.todoist.com/app/settings/integrations/developer",
+                    "Under the developer tab, you will be able to see the API Token",
+                    "Copy the token and paste it below",
+                    "You are now ready to authenticate with Todoist!",
+                    &format!("Config will be saved to {}\n\n", config_file_path.display()),
+                  ];
+
+                for (number, item) in instructions.iter().enumerate() {
+                    println!("  {}. {}", number + 1, item);
+                }
+
+                println!("\nEnter your API token:");
+                stdin().read_line(&mut client_key).unwrap();
+                client_key = client_key.trim().to_string(); // Trim the newline character
+
+                let config = Config {
+                    bearer_token: client_key.clone(),
+                    project_order: None,
+                    folded_projects: None,
+                    folded_tasks: None,
+                    filters: None,
+                    include_subprojects: None,
+                    api_url: None,
+                    sync_url: None,
+                    sync_token: None,
+                    sync_interval_secs: None,
+                };
+
+                write_config(config_file_path, &config).unwrap();
+            }
+        }
+        None => panic!("No directory found"),
+    }
+    client_key
+}
@@ -0,0 +1,55 @@
+use crate::api_error::ApiError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadStatus {
+    Pending,
+    /// Finished, with the number of items received.
+    Loaded(usize),
+    Failed(String),
+}
+
+/// Progress of the initial fetch, one entry per resource.
+#[derive(Debug, Default)]
+pub struct Loading {
+    pub resources: Vec<(&'static str, LoadStatus)>,
+}
+
+impl Loading {
+    pub fn start(names: &[&'static str]) -> Loading {
+        Loading {
+            resources: names
+                .iter()
+                .map(|name| (*name, LoadStatus::Pending))
+                .collect(),
+        }
+    }
+
+    pub fn finish<T>(&mut self, name: &str, result: &Result<Vec<T>, ApiError>) {
+        let status = match result {
+            Ok(items) => LoadStatus::Loaded(items.len()),
+            Err(e) => LoadStatus::Failed(e.to_string()),
+        };
+        if let Some((_, current)) = self.resources.iter_mut().find(|(n, _)| *n == name) {
+            *current = status;
+        }
+    }
+
+    pub fn in_progress(&self) -> bool {
+        self.resources
+            .iter()
+            .any(|(_, status)| *status == LoadStatus::Pending)
+    }
+
+    pub fn has_failed(&self) -> bool {
+        self.resources
+            .iter()
+            .any(|(_, status)| matches!(status, LoadStatus::Failed(_)))
+    }
+
+    pub fn loaded_count(&self) -> usize {
+        self.resources
+            .iter()
+            .filter(|(_, status)| matches!(status, LoadStatus::Loaded(_)))
+            .count()
+    }
+}
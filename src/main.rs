@@ -1,389 +1,4 @@
-use color_eyre::Result;
-use crossterm::event::{self, KeyCode, KeyEventKind};
-use dirs::config_dir;
-use key_handler::{handle_new_tasks, handle_projects, handle_task_editor, handle_tasks};
-use new_task::NewTask;
-use projects::Projects;
-use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
-use sections::Sections;
-use serde::{Deserialize, Serialize};
-use std::{
-    fs::{self, File},
-    io::{stdin, BufReader},
-    path::Path,
-    sync::{
-        mpsc::{self, Receiver, Sender, TryRecvError},
-        Arc,
-    },
-};
-use tasks::{Filter, Task, Tasks};
-use tokio::sync::Mutex;
-
-mod api_calls;
-mod banner;
-mod error;
-mod key_handler;
-mod new_task;
-mod projects;
-mod sections;
-mod task_edit;
-mod tasks;
-mod tui;
-
-#[derive(Debug, Default)]
-pub enum CurrentScreen {
-    #[default]
-    Main,
-}
-
-#[derive(Debug, Default, PartialEq)]
-pub enum CurrentFocus {
-    #[default]
-    Projects,
-    Tasks,
-    Help,
-    Error,
-}
-
-#[derive(Debug, Default)]
-pub struct App<'a> {
-    pub current_screen: CurrentScreen,
-    pub exit: bool,
-    pub projects: Projects,
-    pub current_focus: CurrentFocus,
-    pub tasks: Tasks,
-    pub show_help: bool,
-    pub sections: Sections,
-    pub show_task_editor: bool,
-    pub task_edit: task_edit::TaskEdit<'a>,
-    pub show_new_task: bool,
-    pub new_task: NewTask<'a>,
-    pub error_message: Option<String>,
-    pub show_error: bool,
-}
-
-impl<'a> App<'a> {
-    pub fn new() -> App<'a> {
-        App::default()
-    }
-
-    pub fn set_error_message(&mut self, message: String) {
-        self.error_message = Some(message);
-        self.show_error = true;
-    }
-
-    pub fn clear_error(&mut self) {
-        self.error_message = None;
-        self.show_error = false;
-    }
-}
-
-#[derive(Debug, Clone)]
-pub enum TaskResult {
-    Task(Task),
-    Error(String),
-}
-
 #[tokio::main]
-async fn main() -> Result<()> {
-    let (tx, rx): (Sender<TaskResult>, Receiver<TaskResult>) = mpsc::channel();
-    let bearer_token = get_token();
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        AUTHORIZATION,
-        HeaderValue::from_str(&format!("Bearer {}", bearer_token)).unwrap(),
-    );
-
-    let client = reqwest::Client::builder()
-        .default_headers(headers)
-        .build()
-        .unwrap();
-
-    error::install_hooks()?;
-    let mut terminal = tui::init()?;
-    let app = Arc::new(Mutex::new(App::new()));
-
-    let app_clone = Arc::clone(&app);
-    let client_clone = client.clone();
-    let initialise_task = tokio::spawn(async move {
-        // todo: make network calls parallel
-        let project_resp = api_calls::fetch_projects(&client_clone).await.unwrap();
-        let mut projects = Projects::new(project_resp);
-        
-        // Apply saved project order if available
-        if let Some(saved_order) = load_project_order() {
-            let mut reordered_projects = Vec::new();
-            let mut remaining_projects = projects.projects.clone();
-            
-            // First, add projects in the saved order
-            for project_id in &saved_order {
-                if let Some(pos) = remaining_projects.iter().position(|p| p.id == *project_id) {
-                    reordered_projects.push(remaining_projects.remove(pos));
-                }
-            }
-            
-            // Then add any remaining projects that weren't in the saved order
-            reordered_projects.extend(remaining_projects);
-            
-            projects.projects = reordered_projects;
-        }
-        let task_resp = api_calls::fetch_tasks(&client_clone).await.unwrap();
-        let tasks = Tasks::new(task_resp);
-        let mut app = app_clone.lock().await;
-        let sections_resp = api_calls::fetch_sections(&client_clone).await.unwrap();
-        let sections = Sections::new(sections_resp);
-        app.projects = projects;
-        app.tasks = tasks;
-        app.sections = sections;
-        app.tasks.find_tasks_with_children();
-        
-        // Select the first project on startup and filter tasks by it
-        if !app.projects.projects.is_empty() {
-            app.projects.state.select(Some(0));
-            let selected_id = app.projects.projects[0].id.clone();
-            app.tasks.filter = crate::tasks::Filter::ProjectId(selected_id.clone());
-            app.tasks.filter_task_list(false);
-            app.projects.selected_project = Some(selected_id);
-        } else {
-            app.tasks.filter_task_list(false);
-        }
-    });
-
-    loop {
-        let mut app = app.lock().await;
-        terminal.draw(|frame| tui::ui(frame, &mut app))?;
-
-        if event::poll(std::time::Duration::from_millis(150))? {
-            if let event::Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    if app.show_task_editor {
-                        handle_task_editor(&mut app, key, client.clone(), tx.clone());
-                        continue;
-                    }
-
-                    if app.show_new_task {
-                        handle_new_tasks(&mut app, key, client.clone(), tx.clone());
-                        continue;
-                    }
-
-                    if app.show_error {
-                        if key.code == KeyCode::Esc {
-                            app.clear_error();
-                        }
-                        continue;
-                    }
-
-                    if key.code == KeyCode::Char('z') {
-                        app.show_help = !app.show_help;
-                    } else if key.code == KeyCode::Char('h') {
-                        app.current_focus = CurrentFocus::Projects;
-                        app.tasks.unselect();
-                    } else if key.code == KeyCode::Char('l') {
-                        app.current_focus = CurrentFocus::Tasks;
-                        // Ensure first task is selected when switching to task view
-                        if !app.tasks.display_tasks.is_empty() {
-                            app.tasks.state.select(Some(0));
-                        }
-                    } else if key.code == KeyCode::Char('q') {
-                        break;
-                    } else if key.code == KeyCode::Char('t') {
-                        app.tasks.filter = Filter::Today;
-                        app.tasks.filter_task_list(true);
-                        app.projects.unselect();
-                    } else if key.code == KeyCode::Char('o') {
-                        // Only apply overdue filter if not in task focus
-                        if app.current_focus != CurrentFocus::Tasks {
-                            app.tasks.filter = Filter::Overdue;
-                            app.tasks.filter_task_list(true);
-                            app.projects.unselect();
-                        }
-                    } else if key.code == KeyCode::Char('e') {
-                        app.tasks.filter = Filter::All;
-                        app.tasks.filter_task_list(true);
-                        app.projects.unselect();
-                    } else if key.code == KeyCode::Char('p') {
-                        app.tasks.sort_tasks(tasks::SortCriterion::Priority);
-                    } else if key.code == KeyCode::Char('d') {
-                        app.tasks.sort_tasks(tasks::SortCriterion::Date);
-                    }
-
-                    if app.show_help {
-                        continue;
-                    }
-
-                    if key.code == KeyCode::Tab {
-                        match app.current_focus {
-                            CurrentFocus::Projects => {
-                                app.current_focus = CurrentFocus::Tasks;
-                                // Ensure first task is selected when switching to task view
-                                if !app.tasks.display_tasks.is_empty() {
-                                    app.tasks.state.select(Some(0));
-                                }
-                            },
-                            CurrentFocus::Tasks => {
-                                app.current_focus = CurrentFocus::Projects;
-                                app.tasks.unselect();
-                            },
-                            _ => {}
-                        }
-                    }
-
-                    if app.current_focus == CurrentFocus::Projects {
-                        handle_projects(&mut app, key)
-                    } else if app.current_focus == CurrentFocus::Tasks {
-                        handle_tasks(&mut app, key, client.clone())
-                    }
-                }
-            }
-        }
-
-        match rx.try_recv() {
-            Ok(received) => match received {
-                TaskResult::Task(task) => {
-                    let mut task_exists = false;
-                    for existing_task in &mut app.tasks.tasks {
-                        if existing_task.id == task.id {
-                            *existing_task = task.clone();
-                            task_exists = true;
-                            break;
-                        }
-                    }
-
-                    if !task_exists {
-                        app.tasks.tasks.push(task);
-                    }
-                    
-                    // Preserve current cursor position when refreshing the task list
-                    let current_selection = app.tasks.state.selected();
-                    app.tasks.filter_task_list(false);
-                    
-                    // Restore cursor position if it was valid
-                    if let Some(selection) = current_selection {
-                        let display_tasks_len = app.tasks.display_tasks.len();
-                        if selection < display_tasks_len {
-                            app.tasks.state.select(Some(selection));
-                        } else if display_tasks_len > 0 {
-                            // If the old selection is out of bounds, select the last item
-                            app.tasks.state.select(Some(display_tasks_len - 1));
-                        }
-                    }
-                }
-                TaskResult::Error(error_msg) => {
-                    app.set_error_message(error_msg);
-                }
-            },
-            Err(TryRecvError::Empty) => continue,
-            Err(TryRecvError::Disconnected) => break,
-        }
-    }
-    tui::restore()?;
-    let _ = initialise_task.await;
-    Ok(())
-}
-
-#[derive(Deserialize, Serialize)]
-struct Config {
-    bearer_token: String,
-    project_order: Option<Vec<String>>,
-}
-
-pub fn save_project_order(project_order: &[String]) -> Result<(), Box<dyn std::error::Error>> {
-    match config_dir() {
-        Some(home) => {
-            let path = Path::new(&home);
-            let config_dir = path.join(".todoist");
-            let config_file_path = &config_dir.join("config.json");
-
-            if config_file_path.exists() {
-                let file = File::open(config_file_path)?;
-                let reader = BufReader::new(file);
-                let mut config: Config = serde_json::from_reader(reader)?;
-                config.project_order = Some(project_order.to_vec());
-                
-                let config_json = serde_json::to_string(&config)?;
-                fs::write(config_file_path, config_json)?;
-            }
-        }
-        None => return Err("No config directory found".into()),
-    }
-    Ok(())
-}
-
-fn load_project_order() -> Option<Vec<String>> {
-    match config_dir() {
-        Some(home) => {
-            let path = Path::new(&home);
-            let config_dir = path.join(".todoist");
-            let config_file_path = &config_dir.join("config.json");
-
-            if config_file_path.exists() {
-                let file = File::open(config_file_path).ok()?;
-                let reader = BufReader::new(file);
-                let config: Config = serde_json::from_reader(reader).ok()?;
-                config.project_order
-            } else {
-                None
-            }
-        }
-        None => None,
-    }
-}
-
-fn get_token() -> String {
-    let mut client_key = String::new();
-    match config_dir() {
-        Some(home) => {
-            let path = Path::new(&home);
-            let config_dir = path.join(".todoist");
-            if !config_dir.exists() {
-                fs::create_dir(&config_dir).unwrap();
-            }
-            let config_file_path = &config_dir.join("config.json");
-
-            if config_file_path.exists() {
-                let file = File::open(config_file_path).unwrap();
-                let reader = BufReader::new(file);
-
-                let config: Config = serde_json::from_reader(reader).unwrap();
-                client_key = config.bearer_token;
-
-                println!("Your config is saved at {}", config_file_path.display());
-            } else {
-                println!("{}", banner::BANNER);
-
-                println!();
-
-                println!("\nHow to get setup:");
-                println!("-----------------\n");
-
-                let instructions = [
-                    "Go to the todoist integrations - https://app.todoist.com/app/settings/integrations/developer",
-                    "Under the developer tab, you will be able to see the API Token",
-                    "Copy the token and paste it below",
-                    "You are now ready to authenticate with Todoist!",
-                    &format!("Config will be saved to {}\n\n", config_file_path.display()),
-                  ];
-
-                let mut number = 1;
-                for item in instructions.iter() {
-                    println!("  {}. {}", number, item);
-                    number += 1;
-                }
-
-                println!("\nEnter your API token:");
-                stdin().read_line(&mut client_key).unwrap();
-                client_key = client_key.trim().to_string(); // Trim the newline character
-
-                let config = Config {
-                    bearer_token: client_key.clone(),
-                    project_order: None,
-                };
-
-                let config_json = serde_json::to_string(&config).unwrap();
-                fs::write(config_file_path, config_json).unwrap();
-            }
-        }
-        None => panic!("No directory found"),
-    }
-    client_key
+async fn main() -> color_eyre::Result<()> {
+    todoist::run().await
 }
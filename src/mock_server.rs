@@ -0,0 +1,169 @@
+use std::{convert::Infallible, net::SocketAddr, sync::Arc};
+
+use color_eyre::eyre::{Context, Result};
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+
+use crate::api_calls;
+use crate::store::{JsonStore, Resource, Storage, StoreError};
+
+/// In-memory stand-in for the Todoist REST and Sync APIs, serving a
+/// `JsonStore` over HTTP.
+type SharedState = Arc<Mutex<JsonStore>>;
+
+/// Starts the mock server in the background and returns the address it is
+/// listening on. Bind to port 0 to get a free port.
+pub fn spawn(addr: SocketAddr, state: JsonStore) -> Result<SocketAddr> {
+    let state: SharedState = Arc::new(Mutex::new(state));
+    let make_service = make_service_fn(move |_| {
+        let state = Arc::clone(&state);
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                handle(request, Arc::clone(&state))
+            }))
+        }
+    });
+
+    let server = Server::try_bind(&addr)
+        .with_context(|| format!("Failed to bind mock server to {}", addr))?
+        .serve(make_service);
+    let local_addr = server.local_addr();
+
+    tokio::spawn(async move {
+        if let Err(e) = server.await {
+            eprintln!("Mock server error: {}", e);
+        }
+    });
+
+    Ok(local_addr)
+}
+
+/// Runs the mock server in the foreground until the process is interrupted.
+pub async fn serve(addr: SocketAddr, state: JsonStore) -> Result<()> {
+    let local_addr = spawn(addr, state)?;
+    println!("Mock Todoist API listening on http://{}", local_addr);
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
+
+async fn handle(request: Request<Body>, state: SharedState) -> Result<Response<Body>, Infallible> {
+    // Any token is accepted, but there has to be one
+    let authorized = request
+        .headers()
+        .get(hyper::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| !token.trim().is_empty());
+    if !authorized {
+        return Ok(json_response(StatusCode::UNAUTHORIZED, json!("Unauthorized")));
+    }
+
+    let request_id = request
+        .headers()
+        .get(api_calls::REQUEST_ID)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let task_id = request
+        .uri()
+        .query()
+        .and_then(|query| query.split('&').find_map(|pair| pair.strip_prefix("task_id=")))
+        .map(str::to_string);
+    let body = match hyper::body::to_bytes(request.into_body()).await {
+        Ok(bytes) if bytes.is_empty() => Value::Null,
+        Ok(bytes) => match serde_json::from_slice(&bytes) {
+            Ok(value) => value,
+            Err(e) => return Ok(json_response(StatusCode::BAD_REQUEST, json!(e.to_string()))),
+        },
+        Err(e) => return Ok(json_response(StatusCode::BAD_REQUEST, json!(e.to_string()))),
+    };
+
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let mut state = state.lock().await;
+
+    let response = match (&method, segments.as_slice()) {
+        (&Method::POST, ["sync"]) if body["commands"].is_array() => {
+            let commands = body["commands"].as_array().cloned().unwrap_or_default();
+            json_response(StatusCode::OK, state.run_commands(&commands))
+        }
+        (&Method::POST, ["sync"]) => {
+            let since = body["sync_token"]
+                .as_str()
+                .and_then(|token| token.parse::<u64>().ok());
+            let mut response = state.sync(since);
+            // Like the real API, only the resource types asked for come back
+            if let (Some(wanted), Some(response)) = (body["resource_types"].as_array(), response.as_object_mut()) {
+                response.retain(|key, _| {
+                    matches!(key.as_str(), "sync_token" | "full_sync")
+                        || wanted.iter().any(|resource_type| resource_type == key.as_str())
+                });
+            }
+            json_response(StatusCode::OK, response)
+        }
+        (&Method::GET, ["comments"]) => match task_id {
+            Some(task_id) => store_response(state.comments_for(&task_id).map(Value::from)),
+            None => json_response(StatusCode::BAD_REQUEST, json!("task_id is required")),
+        },
+        (&Method::GET, ["labels", "shared"]) => store_response(state.shared_labels().map(Value::from)),
+        (&Method::GET, ["projects", "archived"]) => store_response(state.archived_projects().map(Value::from)),
+        (&Method::POST, ["projects", id, action @ ("archive" | "unarchive")]) => {
+            store_response(state.set_archived(id, *action == "archive").map(|_| Value::Null))
+        }
+        (&Method::POST, ["tasks", id, action @ ("close" | "reopen")]) => {
+            store_response(state.set_completed(id, *action == "close").map(|_| Value::Null))
+        }
+        (method, [resource, rest @ ..]) => match (method, Resource::from_path(resource), rest) {
+            (&Method::GET, Some(resource), []) => store_response(state.list(resource).map(Value::from)),
+            (&Method::GET, Some(resource), [id]) => {
+                store_response(state.get(resource, id).and_then(|value| value.ok_or(StoreError::NotFound)))
+            }
+            (&Method::POST, Some(resource), []) => {
+                store_response(state.create_once(resource, &body, request_id.as_deref()))
+            }
+            (&Method::POST, Some(resource), [id]) => store_response(state.update(resource, id, &body)),
+            (&Method::DELETE, Some(resource), [id]) => {
+                store_response(state.delete(resource, id).map(|_| Value::Null))
+            }
+            _ => not_found(),
+        },
+        _ => not_found(),
+    };
+
+    Ok(response)
+}
+
+fn json_response(status: StatusCode, value: Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .body(Body::from(value.to_string()))
+        .unwrap_or_default()
+}
+
+/// Turns the outcome of a store operation into the response the real API
+/// would give: the resource, 204 for actions without a body, 404 or 400.
+fn store_response(result: Result<Value, StoreError>) -> Response<Body> {
+    match result {
+        Ok(Value::Null) => no_content(),
+        Ok(value) => json_response(StatusCode::OK, value),
+        Err(StoreError::NotFound) => not_found(),
+        Err(StoreError::Invalid(message)) => json_response(StatusCode::BAD_REQUEST, json!(message)),
+        Err(StoreError::Storage(message)) => json_response(StatusCode::INTERNAL_SERVER_ERROR, json!(message)),
+    }
+}
+
+fn no_content() -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .body(Body::empty())
+        .unwrap_or_default()
+}
+
+fn not_found() -> Response<Body> {
+    json_response(StatusCode::NOT_FOUND, json!("Not found"))
+}
@@ -0,0 +1,114 @@
+use ratatui::widgets::ListState;
+use serde_json::{json, Value};
+use tui_textarea::TextArea;
+
+/// Where a task can be moved: to the top of a project, into a section or
+/// under another task.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Destination {
+    Project(String),
+    Section(String),
+    Parent(String),
+}
+
+impl Destination {
+    /// The arguments of the move request.
+    pub fn json(&self) -> Value {
+        match self {
+            Destination::Project(id) => json!({ "project_id": id }),
+            Destination::Section(id) => json!({ "section_id": id }),
+            Destination::Parent(id) => json!({ "parent_id": id }),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Target {
+    pub label: String,
+    pub destination: Destination,
+}
+
+/// The "move to" popup: every project, section and possible parent task,
+/// narrowed down by fuzzy matching what is typed.
+#[derive(Debug, Default)]
+pub struct MovePicker<'a> {
+    /// The task being moved. Looked up again on Enter, as a sync may have
+    /// moved it in `Tasks::tasks` while the picker was open.
+    pub task_id: String,
+    pub targets: Vec<Target>,
+    /// The targets matching the query, best first, as indexes into `targets`.
+    pub matches: Vec<usize>,
+    pub input: TextArea<'a>,
+    pub state: ListState,
+}
+
+impl<'a> MovePicker<'a> {
+    pub fn new(task_id: String, targets: Vec<Target>) -> Self {
+        let mut picker = MovePicker {
+            task_id,
+            targets,
+            ..Default::default()
+        };
+        picker.update_matches();
+        picker
+    }
+
+    pub fn query(&self) -> String {
+        self.input.lines().join(" ")
+    }
+
+    /// Filters the targets again after the query changed.
+    pub fn update_matches(&mut self) {
+        let query = self.query();
+        let mut scored: Vec<(usize, usize)> = self
+            .targets
+            .iter()
+            .enumerate()
+            .filter_map(|(index, target)| fuzzy_score(&query, &target.label).map(|score| (score, index)))
+            .collect();
+        // Stable, so equally good matches keep their order
+        scored.sort_by_key(|&(score, _)| score);
+        self.matches = scored.into_iter().map(|(_, index)| index).collect();
+        self.state.select(if self.matches.is_empty() { None } else { Some(0) });
+    }
+
+    pub fn next(&mut self) {
+        if self.matches.is_empty() {
+            return;
+        }
+        let i = match self.state.selected() {
+            Some(i) if i + 1 < self.matches.len() => i + 1,
+            _ => 0,
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn previous(&mut self) {
+        if self.matches.is_empty() {
+            return;
+        }
+        let i = match self.state.selected() {
+            Some(0) | None => self.matches.len() - 1,
+            Some(i) => i - 1,
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn selected(&self) -> Option<&Target> {
+        let index = *self.matches.get(self.state.selected()?)?;
+        self.targets.get(index)
+    }
+}
+
+/// How well `text` matches `query` when the query's characters appear in it
+/// in order, ignoring case: lower is better, counting the characters skipped
+/// before and between them. `None` if they do not all appear.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<usize> {
+    let mut text = text.chars().flat_map(char::to_lowercase);
+    let mut score = 0;
+    for wanted in query.chars().filter(|c| !c.is_whitespace()).flat_map(char::to_lowercase) {
+        let skipped = text.by_ref().position(|c| c == wanted)?;
+        score += skipped;
+    }
+    Some(score)
+}
@@ -1,6 +1,8 @@
 use serde::{Serialize, Serializer};
 use tui_textarea::TextArea;
 
+use crate::priority::Priority;
+
 #[derive(Debug, Default, Serialize)]
 pub struct NewTask<'a> {
     #[serde(serialize_with = "serialize_text_area")]
@@ -12,7 +14,7 @@ pub struct NewTask<'a> {
     pub parent_id: Option<String>,
     pub order: Option<usize>,
     pub labels: Option<Vec<String>>,
-    pub priority: Option<u8>,
+    pub priority: Priority,
     #[serde(serialize_with = "serialize_text_area")]
     pub priority_string: TextArea<'a>,
     #[serde(serialize_with = "serialize_text_area")]
@@ -34,13 +36,14 @@ pub enum CurrentlyEditing {
     Description,
     DueString,
     Priority,
+    Labels,
 }
 
 fn serialize_text_area<S>(text: &TextArea, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    let s = format!("{}", text.lines().join("\n")); // Convert the TextArea to a String
+    let s = text.lines().join("\n"); // Convert the TextArea to a String
     serializer.serialize_str(&s)
 }
 
@@ -57,7 +60,7 @@ impl<'a> NewTask<'a> {
         NewTask {
             project_id: Some(project_id),
             parent_id,
-            priority: Some(4), // Default to P4 (lowest priority)
+            priority: Priority::default(),
             ..Default::default()
         }
     }
@@ -0,0 +1,123 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// A task priority. The API counts up towards urgent (4 is "p1"), while the
+/// official apps and this UI count down (p1 is urgent, p4 is none). Keep the
+/// API value inside and convert only at the edges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(from = "u8", into = "u8")]
+pub struct Priority(u8);
+
+impl Priority {
+    /// p1
+    pub const URGENT: Priority = Priority(4);
+    /// p4, what new tasks get unless told otherwise.
+    pub const NONE: Priority = Priority(1);
+
+    /// From the p1..p4 level shown to the user.
+    pub fn from_level(level: u8) -> Option<Priority> {
+        (1..=4).contains(&level).then(|| Priority(5 - level))
+    }
+
+    /// The p1..p4 level shown to the user.
+    pub fn level(self) -> u8 {
+        5 - self.0
+    }
+
+    pub fn api_value(self) -> u8 {
+        self.0
+    }
+}
+
+impl Default for Priority {
+    fn default() -> Self {
+        Priority::NONE
+    }
+}
+
+/// API values outside 1..=4 are clamped rather than rejected, so one odd task
+/// cannot fail a whole fetch.
+impl From<u8> for Priority {
+    fn from(value: u8) -> Self {
+        Priority(value.clamp(1, 4))
+    }
+}
+
+impl From<Priority> for u8 {
+    fn from(priority: Priority) -> Self {
+        priority.0
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "p{}", self.level())
+    }
+}
+
+/// Parses what the user typed: "1".."4" or "p1".."p4".
+impl FromStr for Priority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let digits = s.strip_prefix(['p', 'P']).unwrap_or(s);
+        digits
+            .parse()
+            .ok()
+            .and_then(Priority::from_level)
+            .ok_or_else(|| format!("Invalid priority '{}', expected 1-4", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn p1_is_api_value_4() {
+        assert_eq!(Priority::from_level(1), Some(Priority::URGENT));
+        assert_eq!(Priority::URGENT.api_value(), 4);
+        assert_eq!(Priority::URGENT.level(), 1);
+        assert_eq!(Priority::NONE.api_value(), 1);
+        assert_eq!(Priority::NONE.level(), 4);
+        assert_eq!(Priority::default(), Priority::NONE);
+        for level in 1..=4 {
+            let priority = Priority::from_level(level).unwrap();
+            assert_eq!(priority.level(), level);
+            assert_eq!(priority.api_value(), 5 - level);
+        }
+        assert_eq!(Priority::from_level(0), None);
+        assert_eq!(Priority::from_level(5), None);
+    }
+
+    #[test]
+    fn urgent_sorts_highest() {
+        assert!(Priority::URGENT > Priority::from_level(2).unwrap());
+        assert!(Priority::from_level(3).unwrap() > Priority::NONE);
+    }
+
+    #[test]
+    fn shows_and_parses_levels() {
+        assert_eq!(Priority::URGENT.to_string(), "p1");
+        assert_eq!(Priority::NONE.to_string(), "p4");
+        assert_eq!("p1".parse(), Ok(Priority::URGENT));
+        assert_eq!(" P2 ".parse(), Ok(Priority::from_level(2).unwrap()));
+        assert_eq!("4".parse(), Ok(Priority::NONE));
+        assert_eq!(
+            "p5".parse::<Priority>(),
+            Err("Invalid priority 'p5', expected 1-4".to_string())
+        );
+        assert!("urgent".parse::<Priority>().is_err());
+    }
+
+    #[test]
+    fn uses_the_api_value_in_json() {
+        assert_eq!(serde_json::to_value(Priority::URGENT).unwrap(), 4);
+        assert_eq!(serde_json::from_value::<Priority>(serde_json::json!(4)).unwrap(), Priority::URGENT);
+        // Out of range values are clamped
+        assert_eq!(serde_json::from_value::<Priority>(serde_json::json!(9)).unwrap(), Priority::URGENT);
+        assert_eq!(serde_json::from_value::<Priority>(serde_json::json!(0)).unwrap(), Priority::NONE);
+    }
+}
@@ -0,0 +1,110 @@
+use ratatui::widgets::ListState;
+use tui_textarea::{CursorMove, TextArea};
+
+use crate::projects::{Project, COLORS};
+
+/// What the project dialog is doing.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum DialogKind {
+    /// Naming a new top-level project.
+    #[default]
+    Create,
+    /// Naming a new sub-project of this project.
+    CreateChild(String),
+    Rename(String),
+    Color(String),
+    /// Asking before deleting the project.
+    Delete(String),
+    /// The list of archived projects, to unarchive one.
+    Archived,
+}
+
+/// The popup the project management keys in the sidebar open.
+#[derive(Debug, Default)]
+pub struct ProjectDialog<'a> {
+    pub kind: DialogKind,
+    pub input: TextArea<'a>,
+    /// The cursor in the color or archived project list.
+    pub state: ListState,
+    pub archived: Vec<Project>,
+    pub loading: bool,
+}
+
+impl<'a> ProjectDialog<'a> {
+    /// A name input, filled with `name` when renaming.
+    pub fn name(kind: DialogKind, name: &str) -> Self {
+        let mut input = TextArea::from([name]);
+        input.move_cursor(CursorMove::End);
+        ProjectDialog {
+            kind,
+            input,
+            ..Default::default()
+        }
+    }
+
+    /// The palette, with the cursor on the project's current color.
+    pub fn color(project: &Project) -> Self {
+        let mut state = ListState::default();
+        state.select(Some(COLORS.iter().position(|c| *c == project.color).unwrap_or(0)));
+        ProjectDialog {
+            kind: DialogKind::Color(project.id.clone()),
+            state,
+            ..Default::default()
+        }
+    }
+
+    pub fn delete(project_id: String) -> Self {
+        ProjectDialog {
+            kind: DialogKind::Delete(project_id),
+            ..Default::default()
+        }
+    }
+
+    /// The archived projects, while they are being fetched.
+    pub fn archived() -> Self {
+        ProjectDialog {
+            kind: DialogKind::Archived,
+            loading: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn set_archived(&mut self, archived: Vec<Project>) {
+        self.archived = archived;
+        self.loading = false;
+        self.state.select((!self.archived.is_empty()).then_some(0));
+    }
+
+    /// How many rows the current list has.
+    pub fn rows(&self) -> usize {
+        match self.kind {
+            DialogKind::Color(_) => COLORS.len(),
+            DialogKind::Archived => self.archived.len(),
+            _ => 0,
+        }
+    }
+
+    pub fn next(&mut self) {
+        let rows = self.rows();
+        if rows == 0 {
+            return;
+        }
+        let i = match self.state.selected() {
+            Some(i) if i + 1 < rows => i + 1,
+            _ => 0,
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn previous(&mut self) {
+        let rows = self.rows();
+        if rows == 0 {
+            return;
+        }
+        let i = match self.state.selected() {
+            Some(0) | None => rows - 1,
+            Some(i) => i - 1,
+        };
+        self.state.select(Some(i));
+    }
+}
@@ -1,12 +1,52 @@
+use std::collections::HashSet;
+
 use ratatui::widgets::ListState;
 use serde::{Deserialize, Serialize};
 
+use crate::{queue::TEMP_ID_PREFIX, sections::Section, tasks::Task};
+
+/// The colors Todoist offers for projects and labels, in the order its apps
+/// list them.
+pub const COLORS: [&str; 20] = [
+    "berry_red",
+    "red",
+    "orange",
+    "yellow",
+    "olive_green",
+    "lime_green",
+    "green",
+    "mint_green",
+    "teal",
+    "sky_blue",
+    "light_blue",
+    "blue",
+    "grape",
+    "violet",
+    "lavender",
+    "magenta",
+    "salmon",
+    "charcoal",
+    "grey",
+    "taupe",
+];
+
+/// The projects in the sidebar. `projects` keeps the user's order; `rows` is
+/// the tree built from it, minus the children of folded projects, and is what
+/// `state` points into.
 #[derive(Debug, Default)]
 pub struct Projects {
     pub projects: Vec<Project>,
     pub state: ListState,
     pub selected_project: Option<String>,
     pub move_mode: bool,
+    /// Visible rows as an index into `projects` and a nesting depth. The
+    /// first `favorites` rows are the favorite projects.
+    pub rows: Vec<(usize, u8)>,
+    pub favorites: usize,
+    /// Ids of the projects whose children are hidden.
+    pub folded: HashSet<String>,
+    /// Whether a parent project also shows the tasks of its sub-projects.
+    pub include_subprojects: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,7 +66,19 @@ pub struct Project {
     pub parent_id: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// What a project change made optimistically replaced: the projects it
+/// touched, and the sections and tasks it removed. Put back if the server
+/// refuses the change.
+#[derive(Debug, Clone, Default)]
+pub struct Rollback {
+    /// Each touched project by id, as it was and where it was in the list;
+    /// `None` for one the change added.
+    pub projects: Vec<(String, Option<(usize, Project)>)>,
+    pub sections: Vec<Section>,
+    pub tasks: Vec<Task>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ListType {
     Board,
@@ -35,26 +87,157 @@ pub enum ListType {
 
 impl Projects {
     pub fn new(items: Vec<Project>) -> Projects {
-        Projects {
+        let mut projects = Projects {
             projects: items,
-            state: ListState::default(),
-            selected_project: None,
-            move_mode: false,
+            ..Default::default()
+        };
+        projects.arrange();
+        projects
+    }
+
+    /// Rebuilds `rows`: the favorites, then the tree with the inbox pinned
+    /// first. Sub-projects follow their parent in the order of `projects`; a
+    /// project whose parent is gone is shown at the top level.
+    pub fn arrange(&mut self) {
+        let selected_id = self.selected().map(|project| project.id.clone());
+        let in_favorites = self.state.selected().is_some_and(|row| row < self.favorites);
+
+        let mut roots: Vec<usize> = (0..self.projects.len())
+            .filter(|&index| {
+                let parent_id = &self.projects[index].parent_id;
+                parent_id.as_ref().is_none_or(|id| !self.projects.iter().any(|p| p.id == *id))
+            })
+            .collect();
+        roots.sort_by_key(|&index| !self.projects[index].is_inbox_project);
+
+        let mut tree = Vec::new();
+        for index in roots {
+            self.add_rows(index, 0, false, &mut tree);
+        }
+        let favorites: Vec<(usize, u8)> = tree
+            .iter()
+            .filter(|(index, _, _)| self.projects[*index].is_favorite)
+            .map(|&(index, _, _)| (index, 0))
+            .collect();
+        self.favorites = favorites.len();
+        self.rows = favorites;
+        self.rows.extend(
+            tree.into_iter()
+                .filter(|(_, _, hidden)| !hidden)
+                .map(|(index, depth, _)| (index, depth)),
+        );
+
+        if let Some(selected_id) = selected_id {
+            let favorite_row = self.rows[..self.favorites]
+                .iter()
+                .position(|(index, _)| self.projects[*index].id == selected_id);
+            match favorite_row.filter(|_| in_favorites) {
+                Some(row) => self.state.select(Some(row)),
+                None => {
+                    self.select_id(&selected_id);
+                }
+            }
         }
     }
 
+    /// Adds a project and its sub-projects in tree order, marking the ones
+    /// inside a folded project as hidden.
+    fn add_rows(&self, index: usize, depth: u8, hidden: bool, rows: &mut Vec<(usize, u8, bool)>) {
+        rows.push((index, depth, hidden));
+        let id = &self.projects[index].id;
+        let hidden = hidden || self.folded.contains(id);
+        for child in self.children(id) {
+            self.add_rows(child, depth + 1, hidden, rows);
+        }
+    }
+
+    /// Indexes of the direct sub-projects, in order.
+    fn children<'a>(&'a self, id: &'a str) -> impl Iterator<Item = usize> + 'a {
+        self.projects
+            .iter()
+            .enumerate()
+            .filter(move |(_, project)| project.parent_id.as_deref() == Some(id))
+            .map(|(index, _)| index)
+    }
+
+    pub fn has_children(&self, id: &str) -> bool {
+        self.children(id).next().is_some()
+    }
+
+    /// Every sub-project below the given one, depth first.
+    pub fn descendants(&self, id: &str) -> Vec<&Project> {
+        let mut descendants = Vec::new();
+        for child in self.children(id) {
+            let project = &self.projects[child];
+            descendants.push(project);
+            descendants.extend(self.descendants(&project.id));
+        }
+        descendants
+    }
+
+    /// The project under the cursor.
+    pub fn selected(&self) -> Option<&Project> {
+        let (index, _) = self.rows.get(self.state.selected()?)?;
+        self.projects.get(*index)
+    }
+
+    /// Moves the cursor onto a project, unfolding its parents if needed.
+    pub fn select_id(&mut self, id: &str) -> bool {
+        let mut parent_id = self.projects.iter().find(|p| p.id == id).and_then(|p| p.parent_id.clone());
+        let mut unfolded = false;
+        while let Some(id) = parent_id {
+            unfolded |= self.folded.remove(&id);
+            parent_id = self.projects.iter().find(|p| p.id == id).and_then(|p| p.parent_id.clone());
+        }
+        if unfolded {
+            self.arrange();
+        }
+        // The project's place in the tree, rather than among the favorites
+        let row = self.rows[self.favorites..]
+            .iter()
+            .position(|(index, _)| self.projects[*index].id == id)
+            .map(|row| row + self.favorites);
+        self.state.select(row);
+        row.is_some()
+    }
+
+    /// Folds or unfolds the selected project, `None` toggling it. Closing a
+    /// project without sub-projects closes its parent instead, like `zc` in
+    /// Vim closes the fold around the cursor.
+    pub fn fold(&mut self, fold: Option<bool>) -> bool {
+        let Some(project) = self.selected() else {
+            return false;
+        };
+        let mut id = project.id.clone();
+        if !self.has_children(&id) {
+            match (&project.parent_id, fold) {
+                (Some(parent_id), Some(true) | None) => id = parent_id.clone(),
+                _ => return false,
+            }
+        }
+        let fold = fold.unwrap_or(!self.folded.contains(&id));
+        let changed = if fold {
+            self.folded.insert(id.clone())
+        } else {
+            self.folded.remove(&id)
+        };
+        self.arrange();
+        self.select_id(&id);
+        changed
+    }
+
     // pub async fn initialise(&mut self) {
     //     self.projects = api_calls::fetch_projects().await.unwrap();
     // }
 
     pub fn next(&mut self) {
-        if self.projects.len() == 0 {
+        if self.rows.is_empty() {
             self.state.select(None);
             return;
         }
         let i = match self.state.selected() {
             Some(i) => {
-                if i >= self.projects.len() - 1 {
+                if i >= self.rows.len() - 1 {
                     0
                 } else {
                     i + 1
@@ -66,14 +249,14 @@ impl Projects {
     }
 
     pub fn previous(&mut self) {
-        if self.projects.len() == 0 {
+        if self.rows.is_empty() {
             self.state.select(None);
             return;
         }
         let i = match self.state.selected() {
             Some(i) => {
                 if i == 0 {
-                    self.projects.len() - 1
+                    self.rows.len() - 1
                 } else {
                     i - 1
                 }
@@ -89,25 +272,131 @@ impl Projects {
         *self.state.offset_mut() = offset;
     }
 
-    pub fn toggle_move_mode(&mut self) {
-        self.move_mode = !self.move_mode;
+    /// The projects with the given ids as they are now, to undo a change to
+    /// them with `roll_back`.
+    pub fn snapshot(&self, ids: &[String]) -> Vec<(String, Option<(usize, Project)>)> {
+        ids.iter()
+            .map(|id| {
+                let position = self.projects.iter().position(|p| p.id == *id);
+                (id.clone(), position.map(|index| (index, self.projects[index].clone())))
+            })
+            .collect()
     }
 
-    pub fn move_up(&mut self) {
-        if let Some(selected) = self.state.selected() {
-            if selected > 0 {
-                self.projects.swap(selected, selected - 1);
-                self.state.select(Some(selected - 1));
+    /// Puts the given projects back the way `snapshot` found them, leaving
+    /// every other project alone.
+    pub fn roll_back(&mut self, snapshot: Vec<(String, Option<(usize, Project)>)>) {
+        let selected_id = self.selected().map(|project| project.id.clone());
+        let mut restored: Vec<(usize, Project)> = Vec::new();
+        for (id, previous) in snapshot {
+            self.projects.retain(|project| project.id != id);
+            restored.extend(previous);
+        }
+        restored.sort_by_key(|(index, _)| *index);
+        for (index, project) in restored {
+            self.projects.insert(index.min(self.projects.len()), project);
+        }
+
+        self.arrange();
+        match selected_id {
+            Some(selected_id) if !self.select_id(&selected_id) => self.state.select(None),
+            _ => {}
+        }
+    }
+
+    /// Applies synced changes, keeping the local order of known projects and
+    /// appending new ones. A full sync also drops projects missing from it,
+    /// except those still being created.
+    pub fn merge(&mut self, updated: Vec<Project>, removed: Vec<String>, full_sync: bool) {
+        let selected_id = self.selected().map(|project| project.id.clone());
+
+        if full_sync {
+            self.projects.retain(|project| {
+                project.id.starts_with(TEMP_ID_PREFIX) || updated.iter().any(|p| p.id == project.id)
+            });
+        }
+        self.projects.retain(|project| !removed.contains(&project.id));
+
+        for project in updated {
+            match self.projects.iter_mut().find(|p| p.id == project.id) {
+                Some(existing) => *existing = project,
+                None => self.projects.push(project),
             }
         }
+
+        self.arrange();
+        match selected_id {
+            Some(selected_id) if !self.select_id(&selected_id) => self.state.select(None),
+            _ => {}
+        }
     }
 
-    pub fn move_down(&mut self) {
-        if let Some(selected) = self.state.selected() {
-            if selected < self.projects.len() - 1 {
-                self.projects.swap(selected, selected + 1);
-                self.state.select(Some(selected + 1));
+    /// Swaps a project created offline for the one the server created,
+    /// keeping its place.
+    pub fn replace_temp(&mut self, temp_id: &str, project: Project) {
+        let selected_id = self.selected().map(|p| p.id.clone());
+        for existing in &mut self.projects {
+            if existing.parent_id.as_deref() == Some(temp_id) {
+                existing.parent_id = Some(project.id.clone());
             }
         }
+        let id = project.id.clone();
+        match self.projects.iter_mut().find(|p| p.id == temp_id) {
+            Some(existing) => *existing = project,
+            None => self.projects.push(project),
+        }
+        if self.folded.remove(temp_id) {
+            self.folded.insert(id.clone());
+        }
+        self.arrange();
+        match selected_id {
+            Some(selected_id) if selected_id == temp_id => self.select_id(&id),
+            Some(selected_id) => self.select_id(&selected_id),
+            None => false,
+        };
+    }
+
+    pub fn toggle_move_mode(&mut self) {
+        self.move_mode = !self.move_mode;
+    }
+
+    /// Moves the selected project, with its sub-projects, above the previous
+    /// project with the same parent.
+    pub fn move_up(&mut self) {
+        self.move_among_siblings(false);
+    }
+
+    /// Moves the selected project, with its sub-projects, below the next
+    /// project with the same parent.
+    pub fn move_down(&mut self) {
+        self.move_among_siblings(true);
+    }
+
+    fn move_among_siblings(&mut self, down: bool) {
+        let Some(project) = self.selected() else {
+            return;
+        };
+        let id = project.id.clone();
+        let parent_id = project.parent_id.clone();
+        // The inbox stays pinned at the top
+        let siblings: Vec<usize> = self
+            .projects
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.parent_id == parent_id && !p.is_inbox_project)
+            .map(|(index, _)| index)
+            .collect();
+        let Some(position) = siblings.iter().position(|&index| self.projects[index].id == id) else {
+            return;
+        };
+        let target = if down { position + 1 } else { position.wrapping_sub(1) };
+        let Some(&other) = siblings.get(target) else {
+            return;
+        };
+        // Sub-projects are placed by their parent, so swapping the two
+        // siblings moves both subtrees
+        self.projects.swap(siblings[position], other);
+        self.arrange();
+        self.select_id(&id);
     }
 }
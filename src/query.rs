@@ -0,0 +1,628 @@
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Weekday};
+
+use crate::{priority::Priority, sections::Sections, tasks::Task};
+
+/// A parsed filter in Todoist's query syntax, such as `p1 & @work` or
+/// `(today | overdue) & !#Someday`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+    Not(Box<Query>),
+    /// `all`
+    All,
+    /// `p1` to `p4`
+    Priority(Priority),
+    /// `@name`, where `*` matches any text
+    Label(String),
+    /// `no labels`
+    NoLabels,
+    /// `#name`, or `##name` to take in its sub-projects
+    Project { name: String, with_subprojects: bool },
+    /// `/name`
+    Section(String),
+    /// `no date`
+    NoDate,
+    /// `overdue` or `od`
+    Overdue,
+    /// `today`, `tomorrow`, `due: +3 days` and other single days
+    DueOn(DateExpr),
+    /// `due before: <date>`, not including the day itself
+    DueBefore(DateExpr),
+    /// `due after: <date>`, not including the day itself
+    DueAfter(DateExpr),
+    /// `3 days` or `next 3 days`: due from today up to that many days ahead.
+    /// `-3 days` looks back instead.
+    DueWithin(i64),
+    /// `recurring`
+    Recurring,
+    /// `subtask`
+    Subtask,
+    /// `assigned`
+    Assigned,
+    /// `search: text`
+    Search(String),
+}
+
+/// How far from today a relative day in a query may be, either way: a
+/// hundred years.
+const MAX_DAYS: i64 = 36_500;
+
+/// A day named in a query, worked out against today when it is matched.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateExpr {
+    /// Days from today: `today`, `tomorrow`, `yesterday`, `+3 days`, `-1 week`
+    Relative(i64),
+    /// The next such day, today included: `monday`, `fri`
+    Weekday(Weekday),
+    /// `2024-10-21`
+    Date(NaiveDate),
+}
+
+impl DateExpr {
+    /// `None` if the day falls off the calendar.
+    fn resolve(self, today: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            DateExpr::Relative(days) => today.checked_add_signed(Duration::try_days(days)?),
+            DateExpr::Weekday(weekday) => {
+                let days_ahead = (7 + weekday.num_days_from_monday() as i64
+                    - today.weekday().num_days_from_monday() as i64)
+                    % 7;
+                today.checked_add_signed(Duration::try_days(days_ahead)?)
+            }
+            DateExpr::Date(date) => Some(date),
+        }
+    }
+}
+
+/// What matching needs to know beyond the task itself.
+pub struct Context<'a> {
+    pub today: NaiveDate,
+    pub now: NaiveDateTime,
+    /// Every project as id, name and parent id.
+    pub projects: &'a [(String, String, Option<String>)],
+    pub sections: &'a Sections,
+}
+
+impl Query {
+    /// Parses a query. `|` binds looser than `&`, `!` negates the term after
+    /// it and parentheses group. Names are matched ignoring case.
+    pub fn parse(text: &str) -> Result<Query, String> {
+        let mut parser = Parser {
+            chars: text.chars().collect(),
+            position: 0,
+        };
+        let query = parser.or()?;
+        parser.skip_whitespace();
+        match parser.peek() {
+            None => Ok(query),
+            Some(c) => Err(format!("Unexpected '{}'", c)),
+        }
+    }
+
+    pub fn matches(&self, task: &Task, context: &Context) -> bool {
+        let due_date = task.due.as_ref().map(|due| due.local_date());
+        match self {
+            Query::And(a, b) => a.matches(task, context) && b.matches(task, context),
+            Query::Or(a, b) => a.matches(task, context) || b.matches(task, context),
+            Query::Not(query) => !query.matches(task, context),
+            Query::All => true,
+            Query::Priority(priority) => task.priority == *priority,
+            Query::Label(pattern) => task.labels.iter().any(|label| wildcard_match(pattern, label)),
+            Query::NoLabels => task.labels.is_empty(),
+            Query::Project {
+                name,
+                with_subprojects,
+            } => {
+                let mut project_id = Some(&task.project_id);
+                while let Some(id) = project_id {
+                    let Some((_, project_name, parent_id)) =
+                        context.projects.iter().find(|(project_id, _, _)| project_id == id)
+                    else {
+                        return false;
+                    };
+                    if wildcard_match(name, project_name) {
+                        return true;
+                    }
+                    project_id = parent_id.as_ref().filter(|_| *with_subprojects);
+                }
+                false
+            }
+            Query::Section(name) => task.section_id.as_ref().is_some_and(|section_id| {
+                context
+                    .sections
+                    .sections
+                    .iter()
+                    .any(|section| &section.id == section_id && wildcard_match(name, &section.name))
+            }),
+            Query::NoDate => task.due.is_none(),
+            Query::Overdue => task.due.as_ref().is_some_and(|due| due.is_overdue(context.now)),
+            Query::DueOn(date) => due_date.is_some_and(|due| date.resolve(context.today) == Some(due)),
+            Query::DueBefore(date) => due_date
+                .zip(date.resolve(context.today))
+                .is_some_and(|(due, date)| due < date),
+            Query::DueAfter(date) => due_date
+                .zip(date.resolve(context.today))
+                .is_some_and(|(due, date)| due > date),
+            Query::DueWithin(days) => due_date.is_some_and(|due| {
+                let end = Duration::try_days(*days).and_then(|days| context.today.checked_add_signed(days));
+                let Some(end) = end else {
+                    return false;
+                };
+                if *days >= 0 {
+                    context.today <= due && due <= end
+                } else {
+                    end <= due && due < context.today
+                }
+            }),
+            Query::Recurring => task.due.as_ref().is_some_and(|due| due.is_recurring),
+            Query::Subtask => task.parent_id.is_some(),
+            Query::Assigned => task.assignee_id.is_some(),
+            Query::Search(text) => task.content.to_lowercase().contains(&text.to_lowercase()),
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    /// Consumes `c` if it comes next, after any whitespace.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.position += 1;
+            return true;
+        }
+        false
+    }
+
+    fn or(&mut self) -> Result<Query, String> {
+        let mut query = self.and()?;
+        while self.eat('|') {
+            query = Query::Or(Box::new(query), Box::new(self.and()?));
+        }
+        Ok(query)
+    }
+
+    fn and(&mut self) -> Result<Query, String> {
+        let mut query = self.unary()?;
+        while self.eat('&') {
+            query = Query::And(Box::new(query), Box::new(self.unary()?));
+        }
+        Ok(query)
+    }
+
+    fn unary(&mut self) -> Result<Query, String> {
+        if self.eat('!') {
+            return Ok(Query::Not(Box::new(self.unary()?)));
+        }
+        if self.eat('(') {
+            let query = self.or()?;
+            if !self.eat(')') {
+                return Err("Missing ')'".to_string());
+            }
+            return Ok(query);
+        }
+        self.term()
+    }
+
+    /// Reads up to the next operator and works out what the words mean. A
+    /// backslash takes the character after it literally, for names with `&`
+    /// or `|` in them.
+    fn term(&mut self) -> Result<Query, String> {
+        self.skip_whitespace();
+        let mut text = String::new();
+        while let Some(c) = self.peek() {
+            match c {
+                '&' | '|' | '(' | ')' => break,
+                '\\' => {
+                    self.position += 1;
+                    if let Some(escaped) = self.peek() {
+                        text.push(escaped);
+                        self.position += 1;
+                    }
+                }
+                c => {
+                    text.push(c);
+                    self.position += 1;
+                }
+            }
+        }
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if text.is_empty() {
+            return Err(match self.peek() {
+                Some(c) => format!("Expected a filter before '{}'", c),
+                None => "Expected a filter".to_string(),
+            });
+        }
+        term(&text)
+    }
+}
+
+fn term(text: &str) -> Result<Query, String> {
+    let lower = text.to_lowercase();
+    if let Some(name) = text.strip_prefix("##") {
+        return Ok(Query::Project {
+            name: name.trim().to_string(),
+            with_subprojects: true,
+        });
+    }
+    if let Some(name) = text.strip_prefix('#') {
+        return Ok(Query::Project {
+            name: name.trim().to_string(),
+            with_subprojects: false,
+        });
+    }
+    if let Some(name) = text.strip_prefix('@') {
+        return Ok(Query::Label(name.trim().to_string()));
+    }
+    if let Some(name) = text.strip_prefix('/') {
+        return Ok(Query::Section(name.trim().to_string()));
+    }
+    if let Some(search) = lower.strip_prefix("search:") {
+        // Keep the text as typed, matching ignores case anyway
+        let search = text.get("search:".len()..).unwrap_or(search);
+        return Ok(Query::Search(search.trim().to_string()));
+    }
+    if let Some(date) = lower.strip_prefix("due before:") {
+        return date_expr(date.trim()).map(Query::DueBefore);
+    }
+    if let Some(date) = lower.strip_prefix("due after:") {
+        return date_expr(date.trim()).map(Query::DueAfter);
+    }
+    if let Some(date) = lower.strip_prefix("due:") {
+        return date_expr(date.trim()).map(Query::DueOn);
+    }
+
+    let query = match lower.as_str() {
+        "all" => Query::All,
+        "no labels" => Query::NoLabels,
+        "no date" | "no due date" => Query::NoDate,
+        "overdue" | "od" => Query::Overdue,
+        "recurring" => Query::Recurring,
+        "subtask" | "subtasks" => Query::Subtask,
+        "assigned" => Query::Assigned,
+        _ => {
+            if let Some(priority) = lower
+                .strip_prefix('p')
+                .and_then(|level| level.parse::<u8>().ok())
+                .and_then(Priority::from_level)
+            {
+                return Ok(Query::Priority(priority));
+            }
+            let within = lower.strip_prefix("next ").unwrap_or(&lower);
+            // `+3 days` is the single day, as in `due: +3 days`
+            let days = within.strip_suffix(" days").or(within.strip_suffix(" day"));
+            if let Some(days) = days.filter(|days| !days.starts_with('+')) {
+                if let Ok(days) = days.trim().parse::<i64>() {
+                    if days.abs() > MAX_DAYS {
+                        return Err(format!("'{}' is too far away", text));
+                    }
+                    return Ok(Query::DueWithin(days));
+                }
+            }
+            return date_expr(&lower)
+                .map(Query::DueOn)
+                .map_err(|_| format!("Unknown filter '{}'", text));
+        }
+    };
+    Ok(query)
+}
+
+/// `today`, `tomorrow`, `yesterday`, a weekday, `+3 days`, `-2 weeks` or
+/// `2024-10-21`.
+fn date_expr(text: &str) -> Result<DateExpr, String> {
+    match text {
+        "today" => return Ok(DateExpr::Relative(0)),
+        "tomorrow" => return Ok(DateExpr::Relative(1)),
+        "yesterday" => return Ok(DateExpr::Relative(-1)),
+        _ => {}
+    }
+    if let Ok(weekday) = text.parse::<Weekday>() {
+        return Ok(DateExpr::Weekday(weekday));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        return Ok(DateExpr::Date(date));
+    }
+    if let Some((count, unit)) = text.split_once(' ') {
+        let count = count.strip_prefix('+').unwrap_or(count);
+        if let Ok(count) = count.parse::<i64>() {
+            let days = match unit.trim() {
+                "day" | "days" => Some(count),
+                "week" | "weeks" => count.checked_mul(7),
+                _ => return Err(format!("Unknown date '{}'", text)),
+            };
+            return days
+                .filter(|days| days.abs() <= MAX_DAYS)
+                .map(DateExpr::Relative)
+                .ok_or_else(|| format!("Date '{}' is too far away", text));
+        }
+    }
+    Err(format!("Unknown date '{}'", text))
+}
+
+/// Compares ignoring case, with `*` standing for any run of characters.
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    let text = text.to_lowercase();
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No `*` at all
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{due::Due, sections::Section};
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    fn task(id: &str) -> Task {
+        Task {
+            id: id.to_string(),
+            project_id: "work".to_string(),
+            section_id: None,
+            content: format!("Task {}", id),
+            description: String::new(),
+            is_completed: false,
+            labels: Vec::new(),
+            parent_id: None,
+            order: 1,
+            priority: Priority::NONE,
+            due: None,
+            url: String::new(),
+            comment_count: 0,
+            created_at: String::new(),
+            creator_id: String::new(),
+            assignee_id: None,
+            assigner_id: None,
+            duration: None,
+        }
+    }
+
+    fn due(day: &str) -> Option<Due> {
+        Some(Due::new(day.to_string(), day, None, false, None).unwrap())
+    }
+
+    /// Matches against a fixed Wednesday, 2024-10-16, with a "Work" project
+    /// under "Jobs" and a "Next" section.
+    fn matches(query: &str, task: &Task) -> bool {
+        let projects = vec![
+            ("jobs".to_string(), "Jobs".to_string(), None),
+            ("work".to_string(), "Work".to_string(), Some("jobs".to_string())),
+            ("home".to_string(), "Home".to_string(), None),
+        ];
+        let sections = Sections::new(vec![Section {
+            id: "next".to_string(),
+            project_id: "work".to_string(),
+            order: 1,
+            name: "Next".to_string(),
+        }]);
+        let context = Context {
+            today: date("2024-10-16"),
+            now: date("2024-10-16").and_hms_opt(12, 0, 0).unwrap(),
+            projects: &projects,
+            sections: &sections,
+        };
+        Query::parse(query).unwrap().matches(task, &context)
+    }
+
+    fn project(name: &str, with_subprojects: bool) -> Query {
+        Query::Project {
+            name: name.to_string(),
+            with_subprojects,
+        }
+    }
+
+    #[test]
+    fn parses_priority_and_label() {
+        assert_eq!(
+            Query::parse("p1 & @work"),
+            Ok(Query::And(
+                Box::new(Query::Priority(Priority::URGENT)),
+                Box::new(Query::Label("work".to_string())),
+            ))
+        );
+
+        let mut urgent = task("1");
+        urgent.priority = Priority::URGENT;
+        urgent.labels = vec!["Work".to_string()];
+        assert!(matches("p1 & @work", &urgent));
+        urgent.labels.clear();
+        assert!(!matches("p1 & @work", &urgent));
+    }
+
+    #[test]
+    fn parses_due_before() {
+        assert_eq!(
+            Query::parse("due before: +3 days"),
+            Ok(Query::DueBefore(DateExpr::Relative(3)))
+        );
+
+        let mut soon = task("1");
+        soon.due = due("2024-10-18");
+        assert!(matches("due before: +3 days", &soon));
+        // The day itself is not before it
+        soon.due = due("2024-10-19");
+        assert!(!matches("due before: +3 days", &soon));
+        soon.due = None;
+        assert!(!matches("due before: +3 days", &soon));
+    }
+
+    #[test]
+    fn parses_projects_with_and_without_subprojects() {
+        assert_eq!(
+            Query::parse("#Project | ##Parent"),
+            Ok(Query::Or(
+                Box::new(project("Project", false)),
+                Box::new(project("Parent", true)),
+            ))
+        );
+
+        let work = task("1");
+        assert!(matches("#work", &work));
+        assert!(!matches("#Jobs", &work));
+        assert!(matches("##Jobs", &work));
+        assert!(!matches("##Home", &work));
+    }
+
+    #[test]
+    fn parses_the_keywords() {
+        assert_eq!(Query::parse("no date"), Ok(Query::NoDate));
+        assert_eq!(Query::parse("No Due Date"), Ok(Query::NoDate));
+        assert_eq!(Query::parse("search: Foo Bar"), Ok(Query::Search("Foo Bar".to_string())));
+        assert_eq!(Query::parse("subtask"), Ok(Query::Subtask));
+        assert_eq!(Query::parse("!assigned"), Ok(Query::Not(Box::new(Query::Assigned))));
+        assert_eq!(Query::parse("/Next"), Ok(Query::Section("Next".to_string())));
+        assert_eq!(Query::parse("next 3 days"), Ok(Query::DueWithin(3)));
+        assert_eq!(Query::parse("friday"), Ok(Query::DueOn(DateExpr::Weekday(Weekday::Fri))));
+
+        let mut plain = task("1");
+        plain.content = "Buy FOOD".to_string();
+        assert!(matches("no date", &plain));
+        assert!(matches("search: foo", &plain));
+        assert!(!matches("subtask", &plain));
+        assert!(matches("!assigned", &plain));
+
+        plain.parent_id = Some("2".to_string());
+        plain.assignee_id = Some("someone".to_string());
+        plain.section_id = Some("next".to_string());
+        plain.due = due("2024-10-18");
+        assert!(!matches("no date", &plain));
+        assert!(matches("subtask", &plain));
+        assert!(!matches("!assigned", &plain));
+        assert!(matches("/next", &plain));
+        assert!(matches("friday", &plain));
+        assert!(matches("3 days", &plain));
+        assert!(!matches("1 day", &plain));
+    }
+
+    #[test]
+    fn refuses_days_off_the_calendar() {
+        assert!(Query::parse("due: +100000000 days").is_err());
+        assert!(Query::parse("due before: -9223372036854775807 weeks").is_err());
+        assert!(Query::parse("100000000 days").is_err());
+        assert_eq!(Query::parse("due: +2 weeks"), Ok(Query::DueOn(DateExpr::Relative(14))));
+
+        // Days built in code are not checked, but still never match
+        let mut plain = task("1");
+        plain.due = due("2024-10-18");
+        let context = Context {
+            today: date("2024-10-15"),
+            now: date("2024-10-15").and_hms_opt(9, 0, 0).unwrap(),
+            projects: &[],
+            sections: &Sections::new(Vec::new()),
+        };
+        assert!(!Query::DueWithin(i64::MAX).matches(&plain, &context));
+        assert!(!Query::DueOn(DateExpr::Relative(i64::MIN)).matches(&plain, &context));
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(
+            Query::parse("p1 | p2 & @a"),
+            Ok(Query::Or(
+                Box::new(Query::Priority(Priority::URGENT)),
+                Box::new(Query::And(
+                    Box::new(Query::Priority(Priority::from_level(2).unwrap())),
+                    Box::new(Query::Label("a".to_string())),
+                )),
+            ))
+        );
+
+        let mut urgent = task("1");
+        urgent.priority = Priority::URGENT;
+        assert!(matches("p1 | p2 & @a", &urgent));
+        assert!(!matches("(p1 | p2) & @a", &urgent));
+    }
+
+    #[test]
+    fn not_applies_to_the_next_term_or_group() {
+        assert_eq!(
+            Query::parse("!p1 & @a"),
+            Ok(Query::And(
+                Box::new(Query::Not(Box::new(Query::Priority(Priority::URGENT)))),
+                Box::new(Query::Label("a".to_string())),
+            ))
+        );
+
+        let mut labelled = task("1");
+        labelled.labels = vec!["a".to_string()];
+        assert!(matches("!(p1 | @b) & @a", &labelled));
+        assert!(!matches("!(p1 | @a)", &labelled));
+        assert!(matches("!!@a", &labelled));
+    }
+
+    #[test]
+    fn backslash_escapes_operators() {
+        assert_eq!(
+            Query::parse(r"#Tom \& Jerry | @a\|b"),
+            Ok(Query::Or(
+                Box::new(project("Tom & Jerry", false)),
+                Box::new(Query::Label("a|b".to_string())),
+            ))
+        );
+        assert_eq!(Query::parse(r"@\(x\)"), Ok(Query::Label("(x)".to_string())));
+    }
+
+    #[test]
+    fn star_matches_any_text() {
+        assert!(wildcard_match("work*", "Work stuff"));
+        assert!(wildcard_match("*stuff", "Work stuff"));
+        assert!(wildcard_match("w*k*f", "Work stuff"));
+        assert!(wildcard_match("*", ""));
+        assert!(!wildcard_match("work", "Work stuff"));
+        assert!(!wildcard_match("*work", "Work stuff"));
+        assert!(!wildcard_match("w*x*", "Work stuff"));
+
+        let mut labelled = task("1");
+        labelled.labels = vec!["errand-shop".to_string()];
+        assert!(matches("@errand*", &labelled));
+        assert!(!matches("@errand", &labelled));
+        assert!(matches("#W*", &labelled));
+    }
+
+    #[test]
+    fn reports_what_went_wrong() {
+        assert_eq!(Query::parse(""), Err("Expected a filter".to_string()));
+        assert_eq!(Query::parse("p1 &"), Err("Expected a filter".to_string()));
+        assert_eq!(Query::parse("& p1"), Err("Expected a filter before '&'".to_string()));
+        assert_eq!(Query::parse("(p1 | p2"), Err("Missing ')'".to_string()));
+        assert_eq!(Query::parse("p1)"), Err("Unexpected ')'".to_string()));
+        assert_eq!(Query::parse("p5"), Err("Unknown filter 'p5'".to_string()));
+        assert_eq!(Query::parse("someday"), Err("Unknown filter 'someday'".to_string()));
+        assert_eq!(
+            Query::parse("due before: someday"),
+            Err("Unknown date 'someday'".to_string())
+        );
+    }
+}
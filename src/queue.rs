@@ -0,0 +1,723 @@
+use std::{
+    collections::VecDeque,
+    fs,
+    path::{Path, PathBuf},
+    sync::{mpsc::Sender, Arc},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api_calls,
+    api_error::ApiError,
+    backend::Backend,
+    priority::Priority,
+    sections::Section,
+    store::{self, Resource},
+    tasks::Task,
+    TaskResult,
+};
+
+/// Prefix of the ids given to tasks and sections created while their create
+/// request is still queued.
+pub const TEMP_ID_PREFIX: &str = "tmp-";
+
+/// A change made in the UI that still has to be sent to the server.
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Mutation {
+    CreateTask {
+        temp_id: String,
+        json: serde_json::Value,
+        /// Sent with the create so the server makes the task only once,
+        /// however often it is replayed.
+        #[serde(default = "create_request_id")]
+        request_id: String,
+    },
+    UpdateTask {
+        task_id: String,
+        json: serde_json::Value,
+    },
+    CloseTask {
+        task_id: String,
+        /// The server moves recurring tasks to their next date instead of
+        /// closing them, so they are fetched again afterwards.
+        #[serde(default)]
+        recurring: bool,
+        /// Sent with the close so the server applies it only once, however
+        /// often it is replayed.
+        #[serde(default = "close_uuid")]
+        uuid: String,
+    },
+    DeleteTask {
+        task_id: String,
+    },
+    /// Moves the task with its subtasks; `json` holds one of `parent_id`,
+    /// `section_id` or `project_id`.
+    MoveTask {
+        task_id: String,
+        json: serde_json::Value,
+    },
+    /// The new order of a group of sibling tasks; `json` holds `items`, each
+    /// with an `id` and a `child_order`.
+    ReorderTasks {
+        json: serde_json::Value,
+    },
+    CreateSection {
+        temp_id: String,
+        json: serde_json::Value,
+    },
+    UpdateSection {
+        section_id: String,
+        json: serde_json::Value,
+    },
+    /// The server deletes the tasks of the section along with it.
+    DeleteSection {
+        section_id: String,
+    },
+    /// The new order of a project's sections; `json` holds `sections`, each
+    /// with an `id` and a `section_order`.
+    ReorderSections {
+        json: serde_json::Value,
+    },
+}
+
+impl Mutation {
+    /// Points the mutation at the real id of a task or section that was
+    /// created offline.
+    fn remap_id(&mut self, temp_id: &str, id: &str) {
+        let remap = |value: &mut String| {
+            if value == temp_id {
+                *value = id.to_string();
+            }
+        };
+        let remap_json = |json: &mut serde_json::Value| {
+            for field in ["parent_id", "section_id"] {
+                if json[field] == temp_id {
+                    json[field] = serde_json::Value::String(id.to_string());
+                }
+            }
+        };
+        match self {
+            Mutation::CreateTask { json, .. } => remap_json(json),
+            Mutation::MoveTask { task_id, json } => {
+                remap(task_id);
+                remap_json(json);
+            }
+            Mutation::UpdateTask { task_id, json } => {
+                if task_id == temp_id {
+                    json["id"] = serde_json::Value::String(id.to_string());
+                }
+                remap(task_id);
+                remap_json(json);
+            }
+            Mutation::CloseTask { task_id, .. } | Mutation::DeleteTask { task_id } => remap(task_id),
+            Mutation::UpdateSection { section_id, .. } | Mutation::DeleteSection { section_id } => {
+                remap(section_id)
+            }
+            Mutation::CreateSection { .. } => {}
+            Mutation::ReorderTasks { json } | Mutation::ReorderSections { json } => {
+                let list = if json["items"].is_array() { "items" } else { "sections" };
+                for item in json[list].as_array_mut().into_iter().flatten() {
+                    if item["id"] == temp_id {
+                        item["id"] = serde_json::Value::String(id.to_string());
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Mutations waiting to be sent to the backend, persisted to disk so nothing is lost when the
+/// app is closed while offline. They are replayed strictly in order; a
+/// transient failure stops the replay until the next attempt.
+#[derive(Debug, Clone, Default)]
+pub struct MutationQueue {
+    path: Option<PathBuf>,
+    pending: Arc<std::sync::Mutex<VecDeque<Mutation>>>,
+    flushing: Arc<tokio::sync::Mutex<()>>,
+    /// The last failure to write the queue to disk, reported by the next flush
+    save_error: Arc<std::sync::Mutex<Option<String>>>,
+    /// Why the stored queue could not be loaded, for the UI to report
+    load_error: Option<String>,
+}
+
+impl MutationQueue {
+    /// Loads the queue stored next to the config. Without a path the queue
+    /// lives in memory only.
+    ///
+    /// A queue that cannot be read is moved aside rather than overwritten, and
+    /// the queue starts empty; `load_error` says what happened. If it cannot
+    /// be moved either, new changes are kept in memory only.
+    pub fn load(mut path: Option<PathBuf>) -> MutationQueue {
+        let mut load_error = None;
+        let pending = match path.as_deref().map(read) {
+            Some(Ok(pending)) => pending,
+            Some(Err(e)) => {
+                let stored = path.take().unwrap_or_default();
+                let secs = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|duration| duration.as_secs())
+                    .unwrap_or_default();
+                let aside = stored.with_extension(format!("json.bad-{}", secs));
+                load_error = Some(match fs::rename(&stored, &aside) {
+                    Ok(()) => {
+                        path = Some(stored);
+                        format!(
+                            "Failed to load pending changes ({}); they were moved to {}",
+                            e,
+                            aside.display()
+                        )
+                    }
+                    Err(rename_error) => format!(
+                        "Failed to load pending changes from {} ({}); new changes will not be kept: {}",
+                        stored.display(),
+                        e,
+                        rename_error
+                    ),
+                });
+                VecDeque::new()
+            }
+            None => VecDeque::new(),
+        };
+
+        let queue = MutationQueue {
+            path,
+            pending: Arc::new(std::sync::Mutex::new(pending)),
+            flushing: Arc::new(tokio::sync::Mutex::new(())),
+            save_error: Arc::default(),
+            load_error,
+        };
+        // Keep any uuids given to older closes and creates, so their replays
+        // match
+        if !queue.is_empty() {
+            queue.save(&queue.lock());
+        }
+        queue
+    }
+
+    pub fn load_error(&self) -> Option<&str> {
+        self.load_error.as_deref()
+    }
+
+    pub fn default_path() -> Option<PathBuf> {
+        crate::todoist_config_dir().map(|dir| dir.join("queue.json"))
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    pub fn push(&self, mutation: Mutation) {
+        let mut pending = self.lock();
+        pending.push_back(mutation);
+        self.save(&pending);
+    }
+
+    /// Ids of tasks with a queued close or delete, which should stay hidden
+    /// even if the server still reports them. Recurring tasks stay visible.
+    pub fn pending_removals(&self) -> Vec<String> {
+        self.lock()
+            .iter()
+            .filter_map(|mutation| match mutation {
+                Mutation::CloseTask {
+                    task_id,
+                    recurring: false,
+                    ..
+                }
+                | Mutation::DeleteTask { task_id } => Some(task_id.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Ids of sections with a queued delete; they and their tasks stay hidden.
+    pub fn pending_section_removals(&self) -> Vec<String> {
+        self.lock()
+            .iter()
+            .filter_map(|mutation| match mutation {
+                Mutation::DeleteSection { section_id } => Some(section_id.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Replays queued changes onto freshly fetched tasks: tasks still waiting
+    /// to be created reappear and tasks waiting to be removed, or in a
+    /// section waiting to be deleted, stay hidden.
+    pub fn apply_pending(&self, tasks: &mut Vec<Task>) {
+        let removals = self.pending_removals();
+        let section_removals = self.pending_section_removals();
+        tasks.retain(|task| {
+            !removals.contains(&task.id)
+                && !task.section_id.as_ref().is_some_and(|id| section_removals.contains(id))
+        });
+
+        for mutation in self.lock().iter() {
+            if let Mutation::CreateTask { temp_id, json, .. } = mutation {
+                if !removals.contains(temp_id) {
+                    tasks.push(local_task(temp_id.clone(), json));
+                }
+            }
+        }
+    }
+
+    /// The same for sections. Placeholders already in the list are kept as
+    /// they are, so this can follow every merge.
+    pub fn apply_pending_sections(&self, sections: &mut Vec<Section>) {
+        let removals = self.pending_section_removals();
+        sections.retain(|section| !removals.contains(&section.id));
+
+        for mutation in self.lock().iter() {
+            if let Mutation::CreateSection { temp_id, json } = mutation {
+                if !removals.contains(temp_id) && !sections.iter().any(|section| section.id == *temp_id) {
+                    sections.push(local_section(temp_id.clone(), json));
+                }
+            }
+        }
+    }
+
+    /// Sends queued mutations in the background.
+    pub fn spawn_flush(&self, api: Backend, tx: Sender<TaskResult>) {
+        let queue = self.clone();
+        tokio::spawn(async move {
+            queue.flush(&api, &tx).await;
+        });
+    }
+
+    pub fn spawn_periodic_flush(
+        &self,
+        interval: std::time::Duration,
+        api: Backend,
+        tx: Sender<TaskResult>,
+    ) {
+        let queue = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if !queue.is_empty() {
+                    queue.flush(&api, &tx).await;
+                }
+            }
+        });
+    }
+
+    async fn flush(&self, api: &Backend, tx: &Sender<TaskResult>) {
+        // Another flush is already working through the queue
+        let Ok(_guard) = self.flushing.try_lock() else {
+            return;
+        };
+
+        loop {
+            let Some(mutation) = self.lock().front().cloned() else {
+                break;
+            };
+
+            match send(api, &mutation).await {
+                Ok(result) => {
+                    let mut pending = self.lock();
+                    pending.pop_front();
+                    let created = match &result {
+                        Some(TaskResult::Created { temp_id, task }) => Some((temp_id, &task.id)),
+                        Some(TaskResult::Section {
+                            temp_id: Some(temp_id),
+                            section,
+                        }) => Some((temp_id, &section.id)),
+                        _ => None,
+                    };
+                    if let Some((temp_id, id)) = created {
+                        for queued in pending.iter_mut() {
+                            queued.remap_id(temp_id, id);
+                        }
+                    }
+                    self.save(&pending);
+                    drop(pending);
+
+                    if let Some(result) = result {
+                        let _ = tx.send(result);
+                    }
+                }
+                // Still offline or rate limited: keep it and try again later.
+                // Auth errors are kept too, the token may just need fixing.
+                Err(e) if e.is_transient() || matches!(e, ApiError::Auth(_)) => {
+                    if matches!(e, ApiError::Auth(_)) {
+                        let _ = tx.send(TaskResult::Error(e.to_string()));
+                    }
+                    break;
+                }
+                Err(e) => {
+                    // The server will never accept this change, drop it
+                    let mut pending = self.lock();
+                    pending.pop_front();
+                    self.save(&pending);
+                    drop(pending);
+                    let _ = tx.send(TaskResult::Error(format!(
+                        "Dropped a queued change: {}",
+                        e
+                    )));
+                }
+            }
+        }
+
+        let save_error = self.save_error.lock().unwrap_or_else(|e| e.into_inner()).take();
+        if let Some(e) = save_error {
+            let _ = tx.send(TaskResult::Error(format!("Failed to save pending changes: {}", e)));
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<Mutation>> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Writes the queue to disk. A failure is kept for the next flush to
+    /// report, as pushes happen where there is no channel to the UI.
+    /// Writes the queue through a temporary file, so a crash midway leaves
+    /// the previous queue rather than a truncated one.
+    fn save(&self, pending: &VecDeque<Mutation>) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let temp_path = path.with_extension("json.tmp");
+        let result = serde_json::to_string(pending)
+            .map_err(|e| e.to_string())
+            .and_then(|json| fs::write(&temp_path, json).map_err(|e| e.to_string()))
+            .and_then(|()| fs::rename(&temp_path, path).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            *self.save_error.lock().unwrap_or_else(|e| e.into_inner()) = Some(e);
+        }
+    }
+}
+
+/// Reads a stored queue; a missing file is an empty queue.
+fn read(path: &Path) -> Result<VecDeque<Mutation>, String> {
+    match fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json).map_err(|e| e.to_string()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(VecDeque::new()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Sends one mutation and returns what the UI should be told about it.
+async fn send(api: &Backend, mutation: &Mutation) -> Result<Option<TaskResult>, ApiError> {
+    match mutation {
+        Mutation::CreateTask {
+            temp_id,
+            json,
+            request_id,
+        } => {
+            let task = api.create_task(json.clone(), request_id.clone()).await?;
+            Ok(Some(TaskResult::Created {
+                temp_id: temp_id.clone(),
+                task: Box::new(task),
+            }))
+        }
+        Mutation::UpdateTask { task_id, json } => {
+            let task = api.update_task(task_id.clone(), json.clone()).await?;
+            Ok(Some(TaskResult::Task(Box::new(task))))
+        }
+        Mutation::CloseTask {
+            task_id,
+            recurring,
+            uuid,
+        } => {
+            match api.close_task(task_id.clone(), uuid.clone()).await {
+                Ok(()) => {}
+                Err(ApiError::NotFound) => return Ok(None),
+                Err(e) => return Err(e),
+            }
+            if !recurring {
+                return Ok(None);
+            }
+            // The close went through; failing to fetch the new date only
+            // means it shows up with the next sync instead
+            match api.get_task(task_id.clone()).await {
+                Ok(task) => Ok(Some(TaskResult::Rescheduled(Box::new(task)))),
+                Err(_) => Ok(None),
+            }
+        }
+        Mutation::DeleteTask { task_id } => {
+            api.delete_task(task_id.clone()).await?;
+            Ok(None)
+        }
+        Mutation::MoveTask { task_id, json } => {
+            api.move_task(task_id.clone(), json.clone()).await?;
+            Ok(None)
+        }
+        Mutation::ReorderTasks { json } => {
+            api.reorder_tasks(json.clone()).await?;
+            Ok(None)
+        }
+        Mutation::CreateSection { temp_id, json } => {
+            let section = api.create_section(json.clone()).await?;
+            Ok(Some(TaskResult::Section {
+                temp_id: Some(temp_id.clone()),
+                section: Box::new(section),
+            }))
+        }
+        Mutation::UpdateSection { section_id, json } => {
+            let section = api.update_section(section_id.clone(), json.clone()).await?;
+            Ok(Some(TaskResult::Section {
+                temp_id: None,
+                section: Box::new(section),
+            }))
+        }
+        Mutation::DeleteSection { section_id } => match api.delete_section(section_id.clone()).await {
+            Ok(()) | Err(ApiError::NotFound) => Ok(None),
+            Err(e) => Err(e),
+        },
+        Mutation::ReorderSections { json } => {
+            api.reorder_sections(json.clone()).await?;
+            Ok(None)
+        }
+    }
+}
+
+/// The uuid of a new queued close; closes queued before they had one get
+/// theirs when the queue is loaded.
+pub fn close_uuid() -> String {
+    api_calls::command_uuid("item_close")
+}
+
+/// The request id of a new queued create, handed out the same way.
+pub fn create_request_id() -> String {
+    api_calls::command_uuid("task_create")
+}
+
+pub fn new_temp_id() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default();
+    format!("{}{}", TEMP_ID_PREFIX, nanos)
+}
+
+/// Builds the placeholder shown for a task whose create request is queued.
+pub fn local_task(temp_id: String, json: &serde_json::Value) -> Task {
+    let text = |key: &str| json[key].as_str().unwrap_or_default().to_string();
+    let optional = |key: &str| json[key].as_str().map(str::to_string);
+    // The due date is worked out the way the server will, so the task is
+    // listed under the right day until it is created
+    let mut fields = serde_json::json!({});
+    store::apply_fields(Resource::Tasks, &mut fields, json);
+
+    Task {
+        id: temp_id,
+        project_id: text("project_id"),
+        section_id: optional("section_id"),
+        content: text("content"),
+        description: text("description"),
+        is_completed: false,
+        labels: serde_json::from_value(json["labels"].clone()).unwrap_or_default(),
+        parent_id: optional("parent_id"),
+        order: 0,
+        priority: Priority::from(json["priority"].as_u64().unwrap_or(1) as u8),
+        due: serde_json::from_value(fields["due"].take()).ok(),
+        url: String::new(),
+        comment_count: 0,
+        created_at: String::new(),
+        creator_id: String::new(),
+        assignee_id: None,
+        assigner_id: None,
+        duration: None,
+    }
+}
+
+/// Builds the placeholder shown for a section whose create request is queued.
+pub fn local_section(temp_id: String, json: &serde_json::Value) -> Section {
+    Section {
+        id: temp_id,
+        project_id: json["project_id"].as_str().unwrap_or_default().to_string(),
+        order: json["order"].as_i64().unwrap_or_default() as i32,
+        name: json["name"].as_str().unwrap_or_default().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn remaps_ids_and_references() {
+        let mut update = Mutation::UpdateTask {
+            task_id: "tmp-1".to_string(),
+            json: json!({ "id": "tmp-1", "parent_id": "tmp-1", "content": "a" }),
+        };
+        update.remap_id("tmp-1", "42");
+        let Mutation::UpdateTask { task_id, json } = update else {
+            unreachable!();
+        };
+        assert_eq!(task_id, "42");
+        assert_eq!(json, json!({ "id": "42", "parent_id": "42", "content": "a" }));
+
+        let mut create = Mutation::CreateTask {
+            temp_id: "tmp-2".to_string(),
+            json: json!({ "parent_id": "tmp-1", "section_id": "tmp-3" }),
+            request_id: create_request_id(),
+        };
+        create.remap_id("tmp-1", "42");
+        create.remap_id("tmp-3", "7");
+        let Mutation::CreateTask { temp_id, json, .. } = create else {
+            unreachable!();
+        };
+        // Its own placeholder is only replaced once it is created
+        assert_eq!(temp_id, "tmp-2");
+        assert_eq!(json, json!({ "parent_id": "42", "section_id": "7" }));
+
+        let mut moved = Mutation::MoveTask {
+            task_id: "tmp-1".to_string(),
+            json: json!({ "section_id": "tmp-3" }),
+        };
+        moved.remap_id("tmp-1", "42");
+        moved.remap_id("tmp-3", "7");
+        let Mutation::MoveTask { task_id, json } = moved else {
+            unreachable!();
+        };
+        assert_eq!(task_id, "42");
+        assert_eq!(json, json!({ "section_id": "7" }));
+    }
+
+    #[test]
+    fn remaps_closes_deletes_and_sections() {
+        let mut close = Mutation::CloseTask {
+            task_id: "tmp-1".to_string(),
+            recurring: false,
+            uuid: "uuid".to_string(),
+        };
+        close.remap_id("tmp-1", "42");
+        assert!(matches!(close, Mutation::CloseTask { task_id, .. } if task_id == "42"));
+
+        let mut delete = Mutation::DeleteTask {
+            task_id: "tmp-2".to_string(),
+        };
+        delete.remap_id("tmp-1", "42");
+        assert!(matches!(delete, Mutation::DeleteTask { task_id } if task_id == "tmp-2"));
+
+        let mut rename = Mutation::UpdateSection {
+            section_id: "tmp-3".to_string(),
+            json: json!({ "name": "Later" }),
+        };
+        rename.remap_id("tmp-3", "7");
+        assert!(matches!(rename, Mutation::UpdateSection { section_id, .. } if section_id == "7"));
+
+        let mut delete = Mutation::DeleteSection {
+            section_id: "tmp-3".to_string(),
+        };
+        delete.remap_id("tmp-3", "7");
+        assert!(matches!(delete, Mutation::DeleteSection { section_id } if section_id == "7"));
+    }
+
+    #[test]
+    fn remaps_reorders() {
+        let mut tasks = Mutation::ReorderTasks {
+            json: json!({ "items": [{ "id": "1", "child_order": 1 }, { "id": "tmp-1", "child_order": 2 }] }),
+        };
+        tasks.remap_id("tmp-1", "42");
+        let Mutation::ReorderTasks { json } = tasks else {
+            unreachable!();
+        };
+        assert_eq!(json["items"][0]["id"], "1");
+        assert_eq!(json["items"][1]["id"], "42");
+
+        let mut sections = Mutation::ReorderSections {
+            json: json!({ "sections": [{ "id": "tmp-3", "section_order": 1 }] }),
+        };
+        sections.remap_id("tmp-3", "7");
+        let Mutation::ReorderSections { json } = sections else {
+            unreachable!();
+        };
+        assert_eq!(json["sections"][0]["id"], "7");
+    }
+
+    #[test]
+    fn local_tasks_keep_their_due_date() {
+        let tomorrow = chrono::Local::now().date_naive().succ_opt().unwrap();
+        let task = local_task("tmp-1".to_string(), &json!({ "content": "Call", "due_string": "tomorrow" }));
+        assert_eq!(task.due.map(|due| due.local_date()), Some(tomorrow));
+
+        let task = local_task("tmp-2".to_string(), &json!({ "content": "Call", "due_string": "" }));
+        assert!(task.due.is_none());
+    }
+
+    #[test]
+    fn a_corrupt_queue_is_moved_aside() {
+        let dir = std::env::temp_dir().join(format!("todoist-queue-load-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("queue.json");
+        fs::write(&path, "[{\"CreateTask\"").unwrap();
+
+        let queue = MutationQueue::load(Some(path.clone()));
+        assert!(queue.is_empty());
+        assert!(queue.load_error().is_some());
+        let aside: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect();
+        assert_eq!(aside, ["[{\"CreateTask\""]);
+
+        // New changes are kept in a fresh queue
+        queue.push(Mutation::DeleteTask {
+            task_id: "1".to_string(),
+        });
+        assert_eq!(MutationQueue::load(Some(path)).len(), 1);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn flush_sends_later_changes_to_the_created_task() {
+        let path = std::env::temp_dir().join(format!("todoist-queue-test-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let api: Backend = Arc::new(crate::backend::LocalBackend::open(path.clone()).unwrap());
+        let (tx, rx) = std::sync::mpsc::channel();
+
+        let queue = MutationQueue::load(None);
+        queue.push(Mutation::CreateTask {
+            temp_id: "tmp-1".to_string(),
+            json: json!({ "content": "Parent" }),
+            request_id: create_request_id(),
+        });
+        queue.push(Mutation::CreateTask {
+            temp_id: "tmp-2".to_string(),
+            json: json!({ "content": "Child", "parent_id": "tmp-1" }),
+            request_id: create_request_id(),
+        });
+        queue.push(Mutation::UpdateTask {
+            task_id: "tmp-1".to_string(),
+            json: json!({ "id": "tmp-1", "content": "Renamed" }),
+        });
+
+        let mut pending = Vec::new();
+        queue.apply_pending(&mut pending);
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[1].parent_id.as_deref(), Some("tmp-1"));
+
+        queue.flush(&api, &tx).await;
+        assert!(queue.is_empty());
+
+        let created: Vec<(String, String)> = rx
+            .try_iter()
+            .filter_map(|result| match result {
+                TaskResult::Created { temp_id, task } => Some((temp_id, task.id)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(created.len(), 2);
+        let parent_id = &created[0].1;
+        let tasks = api.list_tasks().await.unwrap();
+        let parent = tasks.iter().find(|task| task.id == *parent_id).unwrap();
+        assert_eq!(parent.content, "Renamed");
+        let child = tasks.iter().find(|task| task.id == created[1].1).unwrap();
+        assert_eq!(child.parent_id.as_ref(), Some(parent_id));
+
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -1,6 +1,8 @@
+use ratatui::widgets::ListState;
 use serde::{Deserialize, Serialize};
+use tui_textarea::{CursorMove, TextArea};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Section {
     pub id: String,
     pub project_id: String,
@@ -17,4 +19,96 @@ impl Sections {
     pub fn new(items: Vec<Section>) -> Sections {
         Sections { sections: items }
     }
+
+    /// The sections of a project in the order they are shown.
+    pub fn for_project(&self, project_id: &str) -> Vec<&Section> {
+        let mut sections: Vec<&Section> = self
+            .sections
+            .iter()
+            .filter(|section| section.project_id == project_id)
+            .collect();
+        sections.sort_by_key(|section| section.order);
+        sections
+    }
+
+    /// Swaps the placeholder of a section created offline for the section
+    /// the server returned.
+    pub fn replace_temp(&mut self, temp_id: &str, section: Section) {
+        // A placeholder that is gone was deleted in the meantime
+        if let Some(existing) = self.sections.iter_mut().find(|s| s.id == temp_id) {
+            *existing = section;
+        }
+    }
+
+    pub fn merge(&mut self, updated: Vec<Section>, removed: Vec<String>, full_sync: bool) {
+        if full_sync {
+            self.sections
+                .retain(|section| updated.iter().any(|s| s.id == section.id));
+        }
+        self.sections.retain(|section| !removed.contains(&section.id));
+
+        for section in updated {
+            match self.sections.iter_mut().find(|s| s.id == section.id) {
+                Some(existing) => *existing = section,
+                None => self.sections.push(section),
+            }
+        }
+    }
+}
+
+/// The sections popup of a project. The first row stands for "no section", so
+/// the selected task can be moved out of its section too.
+#[derive(Debug, Default)]
+pub struct SectionsView<'a> {
+    pub project_id: String,
+    pub state: ListState,
+    /// Whether the name input has the keyboard.
+    pub composing: bool,
+    /// The section being renamed, or `None` when creating one.
+    pub renaming: Option<String>,
+    /// Set after the first `d`, until the deletion is confirmed or cancelled.
+    pub confirm_delete: bool,
+    pub input: TextArea<'a>,
+}
+
+impl<'a> SectionsView<'a> {
+    /// Opens on the row of the given section.
+    pub fn new(project_id: String, row: usize) -> Self {
+        let mut state = ListState::default();
+        state.select(Some(row));
+        SectionsView {
+            project_id,
+            state,
+            ..Default::default()
+        }
+    }
+
+    pub fn next(&mut self, rows: usize) {
+        let i = match self.state.selected() {
+            Some(i) if i + 1 < rows => i + 1,
+            _ => 0,
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn previous(&mut self, rows: usize) {
+        let i = match self.state.selected() {
+            Some(0) | None => rows.saturating_sub(1),
+            Some(i) => i - 1,
+        };
+        self.state.select(Some(i));
+    }
+
+    /// Starts naming a new section, or renaming `section` if given.
+    pub fn compose(&mut self, section: Option<&Section>) {
+        self.renaming = section.map(|section| section.id.clone());
+        self.input = TextArea::from([section.map_or("", |section| &section.name)]);
+        self.input.move_cursor(CursorMove::End);
+        self.composing = true;
+    }
+
+    pub fn stop_composing(&mut self) {
+        self.composing = false;
+        self.renaming = None;
+    }
 }
@@ -0,0 +1,1032 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Datelike, Duration, Local, Months, NaiveDate, NaiveTime, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::due::{self, Due};
+
+/// The kinds of resources the store keeps, named after their REST routes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Resource {
+    Projects,
+    Sections,
+    Tasks,
+    Labels,
+    Comments,
+}
+
+impl Resource {
+    pub fn from_path(path: &str) -> Option<Resource> {
+        match path {
+            "projects" => Some(Resource::Projects),
+            "sections" => Some(Resource::Sections),
+            "tasks" => Some(Resource::Tasks),
+            "labels" => Some(Resource::Labels),
+            "comments" => Some(Resource::Comments),
+            _ => None,
+        }
+    }
+
+    /// Fields a create or update request may set.
+    fn writable_fields(&self) -> &'static [&'static str] {
+        match self {
+            Resource::Projects => &["name", "color", "parent_id", "is_favorite", "view_style", "order"],
+            Resource::Sections => &["name", "project_id", "order"],
+            Resource::Tasks => &[
+                "content",
+                "description",
+                "labels",
+                "priority",
+                "project_id",
+                "section_id",
+                "parent_id",
+                "assignee_id",
+                "order",
+            ],
+            Resource::Labels => &["name", "color", "order", "is_favorite"],
+            Resource::Comments => &["content", "task_id", "project_id"],
+        }
+    }
+}
+
+/// Why a store refused a request, mirroring the REST API's 404 and 400.
+/// `Storage` is a failure of whatever holds the data, such as the database.
+#[derive(Debug, PartialEq)]
+pub enum StoreError {
+    NotFound,
+    Invalid(String),
+    Storage(String),
+}
+
+/// Where a local store keeps its resources, as REST JSON documents. The REST
+/// and Sync API semantics (defaults, due strings, moves, archiving, cascading
+/// deletes) are the provided methods, so every store behaves the same and
+/// only has to implement these few primitives.
+pub trait Storage {
+    fn next_id(&mut self) -> Result<String, StoreError>;
+
+    /// Every stored resource of a kind, oldest first.
+    fn all(&self, resource: Resource) -> Result<Vec<Value>, StoreError>;
+
+    fn get(&self, resource: Resource, id: &str) -> Result<Option<Value>, StoreError>;
+
+    /// Stores a resource, replacing the one with the same id.
+    fn put(&mut self, resource: Resource, value: Value) -> Result<(), StoreError>;
+
+    /// Removes a single resource; removing one that is gone does nothing.
+    fn remove(&mut self, resource: Resource, id: &str) -> Result<(), StoreError>;
+
+    fn count(&self, resource: Resource) -> Result<usize, StoreError> {
+        Ok(self.all(resource)?.len())
+    }
+
+    /// Called for resources that did not change themselves but should reach
+    /// clients again, such as the contents of an unarchived project.
+    fn mark_changed(&mut self, _id: &str) {}
+
+    fn inbox_id(&self) -> Result<Value, StoreError> {
+        Ok(self
+            .all(Resource::Projects)?
+            .into_iter()
+            .find(|project| project["is_inbox_project"] == true)
+            .map(|project| project["id"].clone())
+            .unwrap_or(Value::Null))
+    }
+
+    /// What `GET /<resource>` returns; completed tasks, archived projects and
+    /// what is in them are left out.
+    fn list(&self, resource: Resource) -> Result<Vec<Value>, StoreError> {
+        let archived = archived_ids(&self.all(Resource::Projects)?);
+        Ok(self
+            .all(resource)?
+            .into_iter()
+            .filter(|value| value["is_completed"] != true && is_active(resource, value, &archived))
+            .collect())
+    }
+
+    /// What `GET /projects/archived` returns.
+    fn archived_projects(&self) -> Result<Vec<Value>, StoreError> {
+        Ok(self
+            .all(Resource::Projects)?
+            .into_iter()
+            .filter(|project| project["is_archived"] == true)
+            .collect())
+    }
+
+    /// What `GET /comments?task_id=<id>` returns.
+    fn comments_for(&self, task_id: &str) -> Result<Vec<Value>, StoreError> {
+        Ok(self
+            .all(Resource::Comments)?
+            .into_iter()
+            .filter(|comment| comment["task_id"] == task_id)
+            .collect())
+    }
+
+    /// What `GET /labels/shared` returns.
+    fn shared_labels(&self) -> Result<Vec<String>, StoreError> {
+        Ok(shared_label_names(&self.all(Resource::Labels)?, &self.all(Resource::Tasks)?))
+    }
+
+    /// Creates a resource from a request body the same way `POST /<resource>`
+    /// does, without validating it.
+    fn insert(&mut self, resource: Resource, body: &Value) -> Result<Value, StoreError> {
+        let id = self.next_id()?;
+        let order = self.count(resource)? + 1;
+        let mut value = template(resource, &id, order, self.inbox_id()?);
+        if resource == Resource::Projects {
+            value["is_inbox_project"] = json!(body["is_inbox_project"] == true);
+        }
+        apply_fields(resource, &mut value, body);
+
+        // Subtasks live in their parent's project and section
+        if resource == Resource::Tasks {
+            if let Some(parent) = match value["parent_id"].as_str() {
+                Some(parent_id) => self.get(Resource::Tasks, parent_id)?,
+                None => None,
+            } {
+                value["project_id"] = parent["project_id"].clone();
+                value["section_id"] = parent["section_id"].clone();
+            }
+        }
+
+        if resource == Resource::Comments {
+            self.count_comment(&value, 1)?;
+        }
+
+        self.put(resource, value.clone())?;
+        Ok(value)
+    }
+
+    /// Keeps `comment_count` of the task or project a comment belongs to up to
+    /// date.
+    fn count_comment(&mut self, comment: &Value, change: i64) -> Result<(), StoreError> {
+        let (resource, id) = match (comment["task_id"].as_str(), comment["project_id"].as_str()) {
+            (Some(task_id), _) => (Resource::Tasks, task_id),
+            (None, Some(project_id)) => (Resource::Projects, project_id),
+            (None, None) => return Ok(()),
+        };
+        if let Some(mut parent) = self.get(resource, id)? {
+            let count = parent["comment_count"].as_i64().unwrap_or_default() + change;
+            parent["comment_count"] = json!(count.max(0));
+            self.put(resource, parent)?;
+        }
+        Ok(())
+    }
+
+    /// Refuses a request pointing at a project, section, parent or task that
+    /// does not exist.
+    fn check_references(&self, resource: Resource, body: &Value) -> Result<(), StoreError> {
+        let references = [
+            ("project_id", Resource::Projects),
+            ("section_id", Resource::Sections),
+            ("parent_id", resource),
+            ("task_id", Resource::Tasks),
+        ];
+        for (field, target) in references {
+            let Some(id) = body.get(field).and_then(Value::as_str) else {
+                continue;
+            };
+            if self.get(target, id)?.is_none() {
+                return Err(StoreError::Invalid(format!("Invalid reference: {} {}", field, id)));
+            }
+        }
+        Ok(())
+    }
+
+    /// `POST /<resource>`
+    fn create(&mut self, resource: Resource, body: &Value) -> Result<Value, StoreError> {
+        validate(resource, body)?;
+        self.check_references(resource, body)?;
+        self.insert(resource, body)
+    }
+
+    /// `POST /<resource>/<id>`
+    fn update(&mut self, resource: Resource, id: &str, body: &Value) -> Result<Value, StoreError> {
+        let mut value = self.get(resource, id)?.ok_or(StoreError::NotFound)?;
+        if resource == Resource::Tasks {
+            validate_due(&value, body)?;
+        }
+        self.check_references(resource, body)?;
+        apply_fields(resource, &mut value, body);
+        self.put(resource, value.clone())?;
+
+        // Subtasks always share their parent's section
+        if resource == Resource::Tasks && body.get("section_id").is_some() {
+            for task_id in self.descendants(Resource::Tasks, id)?.into_iter().skip(1) {
+                if let Some(mut task) = self.get(Resource::Tasks, &task_id)? {
+                    task["section_id"] = value["section_id"].clone();
+                    self.put(Resource::Tasks, task)?;
+                }
+            }
+        }
+        Ok(value)
+    }
+
+    /// The Sync API's `item_move`: the task goes under a parent, into a
+    /// section or to the top of a project, and its subtasks follow it into the
+    /// same project and section.
+    fn move_task(&mut self, id: &str, args: &Value) -> Result<(), StoreError> {
+        self.get(Resource::Tasks, id)?.ok_or(StoreError::NotFound)?;
+        let subtree = self.descendants(Resource::Tasks, id)?;
+
+        let (project_id, section_id, parent_id) = if let Some(parent_id) = args["parent_id"].as_str() {
+            if subtree.iter().any(|task_id| task_id == parent_id) {
+                return Err(StoreError::Invalid("A task cannot be moved under itself".to_string()));
+            }
+            let parent = self.get(Resource::Tasks, parent_id)?.ok_or(StoreError::NotFound)?;
+            (parent["project_id"].clone(), parent["section_id"].clone(), json!(parent_id))
+        } else if let Some(section_id) = args["section_id"].as_str() {
+            let section = self.get(Resource::Sections, section_id)?.ok_or(StoreError::NotFound)?;
+            (section["project_id"].clone(), json!(section_id), Value::Null)
+        } else if let Some(project_id) = args["project_id"].as_str() {
+            self.get(Resource::Projects, project_id)?.ok_or(StoreError::NotFound)?;
+            (json!(project_id), Value::Null, Value::Null)
+        } else {
+            return Err(StoreError::Invalid(
+                "parent_id, section_id or project_id is required".to_string(),
+            ));
+        };
+
+        for task_id in subtree {
+            if let Some(mut task) = self.get(Resource::Tasks, &task_id)? {
+                task["project_id"] = project_id.clone();
+                task["section_id"] = section_id.clone();
+                if task_id == id {
+                    task["parent_id"] = parent_id.clone();
+                }
+                self.put(Resource::Tasks, task)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// `item_reorder` and `section_reorder`: sets the order of each listed
+    /// task or section. Every id is checked first so a bad one changes nothing.
+    fn reorder(&mut self, resource: Resource, args: &Value) -> Result<(), StoreError> {
+        let (list, order_field) = match resource {
+            Resource::Sections => ("sections", "section_order"),
+            _ => ("items", "child_order"),
+        };
+        let Some(items) = args[list].as_array() else {
+            return Err(StoreError::Invalid(format!("{} is required", list)));
+        };
+        let mut values = Vec::with_capacity(items.len());
+        for item in items {
+            let (Some(id), Some(order)) = (item["id"].as_str(), item[order_field].as_i64()) else {
+                return Err(StoreError::Invalid(format!("Each of {} needs an id and {}", list, order_field)));
+            };
+            let value = self.get(resource, id)?.ok_or(StoreError::NotFound)?;
+            values.push((value, order));
+        }
+
+        for (mut value, order) in values {
+            value["order"] = json!(order);
+            self.put(resource, value)?;
+        }
+        Ok(())
+    }
+
+    /// Runs a single Sync API command.
+    fn run_command(&mut self, command_type: &str, args: &Value) -> Result<(), StoreError> {
+        let id = || args["id"].as_str().ok_or_else(|| StoreError::Invalid("id is required".to_string()));
+        match command_type {
+            "item_close" => self.set_completed(id()?, true),
+            "item_move" => self.move_task(id()?, args),
+            "item_reorder" => self.reorder(Resource::Tasks, args),
+            "section_reorder" => self.reorder(Resource::Sections, args),
+            _ => Err(StoreError::Invalid("Unknown command".to_string())),
+        }
+    }
+
+    /// `POST /tasks/<id>/close` and `/reopen`
+    fn set_completed(&mut self, id: &str, completed: bool) -> Result<(), StoreError> {
+        let mut task = self.get(Resource::Tasks, id)?.ok_or(StoreError::NotFound)?;
+        // Like the real API, closing a recurring task moves it to its next date
+        match reschedule(&task).filter(|_| completed) {
+            Some(due) => task["due"] = due,
+            None => task["is_completed"] = json!(completed),
+        }
+        self.put(Resource::Tasks, task)
+    }
+
+    /// `POST /projects/<id>/archive` and `/unarchive`. Sub-projects go along,
+    /// and everything in them is marked changed so a sync brings it back after
+    /// unarchiving.
+    fn set_archived(&mut self, id: &str, archived: bool) -> Result<(), StoreError> {
+        let project = self.get(Resource::Projects, id)?.ok_or(StoreError::NotFound)?;
+        if project["is_inbox_project"] == true {
+            return Err(StoreError::Invalid("The inbox cannot be archived".to_string()));
+        }
+
+        let projects = self.descendants(Resource::Projects, id)?;
+        for project_id in &projects {
+            if let Some(mut project) = self.get(Resource::Projects, project_id)? {
+                project["is_archived"] = json!(archived);
+                self.put(Resource::Projects, project)?;
+            }
+        }
+        let projects: HashSet<&str> = projects.iter().map(String::as_str).collect();
+        for resource in [Resource::Sections, Resource::Tasks] {
+            for id in ids_where(&self.all(resource)?, "project_id", &projects) {
+                self.mark_changed(&id);
+            }
+        }
+        Ok(())
+    }
+
+    /// `DELETE /<resource>/<id>`. Like the real API this takes everything
+    /// underneath along: subtasks, the tasks of a section, and the sections,
+    /// tasks and sub-projects of a project.
+    fn delete(&mut self, resource: Resource, id: &str) -> Result<(), StoreError> {
+        let value = self.get(resource, id)?.ok_or(StoreError::NotFound)?;
+        if resource == Resource::Projects && value["is_inbox_project"] == true {
+            return Err(StoreError::Invalid("The inbox cannot be deleted".to_string()));
+        }
+
+        match resource {
+            Resource::Projects => {
+                let projects = self.descendants(Resource::Projects, id)?;
+                let project_ids: HashSet<&str> = projects.iter().map(String::as_str).collect();
+                let tasks = ids_where(&self.all(Resource::Tasks)?, "project_id", &project_ids);
+                let sections = ids_where(&self.all(Resource::Sections)?, "project_id", &project_ids);
+                self.remove_all(Resource::Tasks, &tasks)?;
+                self.remove_all(Resource::Sections, &sections)?;
+                self.remove_all(Resource::Projects, &projects)?;
+            }
+            Resource::Sections => {
+                let tasks = ids_where(&self.all(Resource::Tasks)?, "section_id", &HashSet::from([id]));
+                self.remove_all(Resource::Tasks, &tasks)?;
+                self.remove(Resource::Sections, id)?;
+            }
+            Resource::Tasks => {
+                let tasks = self.descendants(Resource::Tasks, id)?;
+                self.remove_all(Resource::Tasks, &tasks)?;
+            }
+            Resource::Labels => self.remove(Resource::Labels, id)?,
+            Resource::Comments => {
+                self.count_comment(&value, -1)?;
+                self.remove(Resource::Comments, id)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes resources together with the comments posted on them, the
+    /// last ones first so subtasks and sub-projects go before their parents.
+    fn remove_all(&mut self, resource: Resource, ids: &[String]) -> Result<(), StoreError> {
+        let field = match resource {
+            Resource::Tasks => Some("task_id"),
+            Resource::Projects => Some("project_id"),
+            _ => None,
+        };
+        if let Some(field) = field {
+            let ids: HashSet<&str> = ids.iter().map(String::as_str).collect();
+            for comment_id in ids_where(&self.all(Resource::Comments)?, field, &ids) {
+                self.remove(Resource::Comments, &comment_id)?;
+            }
+        }
+        for id in ids.iter().rev() {
+            self.remove(resource, id)?;
+        }
+        Ok(())
+    }
+
+    /// The id of a resource followed by the ids of everything under it
+    /// through `parent_id`, recursively.
+    fn descendants(&self, resource: Resource, id: &str) -> Result<Vec<String>, StoreError> {
+        let mut children: HashMap<String, Vec<String>> = HashMap::new();
+        for value in self.all(resource)? {
+            if let (Some(parent_id), Some(child_id)) = (value["parent_id"].as_str(), value["id"].as_str()) {
+                children.entry(parent_id.to_string()).or_default().push(child_id.to_string());
+            }
+        }
+
+        let mut ids = vec![id.to_string()];
+        let mut seen = HashSet::from([id.to_string()]);
+        let mut index = 0;
+        while index < ids.len() {
+            for child_id in children.get(&ids[index]).into_iter().flatten() {
+                if seen.insert(child_id.clone()) {
+                    ids.push(child_id.clone());
+                }
+            }
+            index += 1;
+        }
+        Ok(ids)
+    }
+
+    /// A full Sync API response holding everything that is active.
+    fn snapshot(&self, sync_token: &str) -> Result<Value, StoreError> {
+        Ok(json!({
+            "sync_token": sync_token,
+            "full_sync": true,
+            "projects": self.list(Resource::Projects)?.iter().map(sync_project).collect::<Vec<_>>(),
+            "sections": self.list(Resource::Sections)?.iter().map(sync_section).collect::<Vec<_>>(),
+            "items": self.list(Resource::Tasks)?.iter().map(sync_item).collect::<Vec<_>>(),
+            "labels": self.list(Resource::Labels)?.iter().map(sync_label).collect::<Vec<_>>(),
+        }))
+    }
+}
+
+/// Ids of the archived projects.
+fn archived_ids(projects: &[Value]) -> HashSet<String> {
+    projects
+        .iter()
+        .filter(|project| project["is_archived"] == true)
+        .filter_map(|project| project["id"].as_str().map(str::to_string))
+        .collect()
+}
+
+/// Whether a resource is outside any archived project.
+fn is_active(resource: Resource, value: &Value, archived: &HashSet<String>) -> bool {
+    let project_id = match resource {
+        Resource::Projects => &value["id"],
+        Resource::Sections | Resource::Tasks => &value["project_id"],
+        _ => return true,
+    };
+    !project_id.as_str().is_some_and(|id| archived.contains(id))
+}
+
+/// Ids of the resources whose `field` is one of `ids`.
+fn ids_where(values: &[Value], field: &str, ids: &HashSet<&str>) -> Vec<String> {
+    values
+        .iter()
+        .filter(|value| value[field].as_str().is_some_and(|id| ids.contains(id)))
+        .filter_map(|value| value["id"].as_str().map(str::to_string))
+        .collect()
+}
+
+/// Todoist data kept as raw REST JSON documents, so anything reading it sees
+/// exactly the shape the real API returns. Backs both the mock server and the
+/// local file backend; on top of `Storage` it tracks a revision per resource
+/// for incremental syncs and answers Sync API writes.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JsonStore {
+    pub projects: Vec<Value>,
+    pub sections: Vec<Value>,
+    pub tasks: Vec<Value>,
+    #[serde(default)]
+    pub labels: Vec<Value>,
+    #[serde(default)]
+    pub comments: Vec<Value>,
+    next_id: u64,
+    /// Bumped on every change; doubles as the sync token handed to clients.
+    #[serde(default)]
+    revision: u64,
+    /// Revision at which each resource last changed, keyed by id.
+    #[serde(default)]
+    changed_at: HashMap<String, u64>,
+    /// Deleted resources as (revision, kind, last stored JSON).
+    #[serde(default)]
+    deleted: Vec<(u64, Resource, Value)>,
+    /// The status of each Sync API command already run, by uuid, so one that
+    /// is sent again is answered without running it twice.
+    #[serde(skip)]
+    command_statuses: HashMap<String, Value>,
+    /// What each REST create already made, by its `X-Request-Id`, for the
+    /// same reason.
+    #[serde(skip)]
+    created_by_request: HashMap<String, Value>,
+}
+
+impl JsonStore {
+    /// An empty store with just an inbox, like a new Todoist account.
+    pub fn new() -> JsonStore {
+        let mut store = JsonStore {
+            next_id: 1000,
+            ..Default::default()
+        };
+        store.add(
+            Resource::Projects,
+            json!({ "name": "Inbox", "color": "grey", "is_inbox_project": true }),
+        );
+        store
+    }
+
+    /// A small account with a few projects, sections and nested tasks, used by
+    /// `--mock` so the TUI has something to show.
+    pub fn demo() -> JsonStore {
+        let mut store = JsonStore::new();
+        let today = Local::now().date_naive();
+        let inbox = store.inbox_id().unwrap_or_default();
+        let mut add = |resource: Resource, body: Value| store.add(resource, body);
+
+        let work = add(Resource::Projects, json!({ "name": "Work", "color": "berry_red" }));
+        let personal = add(Resource::Projects, json!({ "name": "Personal", "color": "sky_blue" }));
+        let errands = add(Resource::Projects, json!({ "name": "Errands", "color": "green", "parent_id": personal }));
+
+        let planning = add(Resource::Sections, json!({ "project_id": work, "name": "Planning" }));
+        let review = add(Resource::Sections, json!({ "project_id": work, "name": "Review" }));
+
+        add(Resource::Labels, json!({ "name": "urgent", "color": "red" }));
+        add(Resource::Labels, json!({ "name": "home", "color": "green" }));
+
+        add(Resource::Tasks, json!({ "project_id": inbox, "content": "Try out todoist-vim", "priority": 4, "due_string": "today" }));
+        add(Resource::Tasks, json!({ "project_id": inbox, "content": "Read the help screen", "description": "Press z to toggle it" }));
+
+        let roadmap = add(Resource::Tasks, json!({ "project_id": work, "section_id": planning, "content": "Draft the roadmap", "priority": 3, "due_string": date_string(today + Duration::days(2)) }));
+        add(Resource::Tasks, json!({ "parent_id": roadmap, "content": "Collect feedback" }));
+        add(Resource::Tasks, json!({ "parent_id": roadmap, "content": "Write summary", "priority": 2 }));
+        add(Resource::Tasks, json!({ "project_id": work, "section_id": review, "content": "Review open pull requests", "priority": 4, "labels": ["urgent"], "due_string": date_string(today - Duration::days(1)) }));
+        add(Resource::Tasks, json!({ "project_id": work, "content": "Weekly sync", "due_string": "every monday" }));
+        add(Resource::Tasks, json!({ "project_id": work, "content": "Morning stand-up", "due_string": "today 09:00" }));
+
+        add(Resource::Tasks, json!({ "project_id": personal, "content": "Book dentist appointment", "priority": 2, "due_string": "tomorrow" }));
+        add(Resource::Tasks, json!({ "project_id": errands, "content": "Buy groceries", "labels": ["home"], "due_string": "today" }));
+
+        store
+    }
+
+    /// Inserts a resource and returns its id; a `JsonStore` never fails to.
+    fn add(&mut self, resource: Resource, body: Value) -> String {
+        self.insert(resource, &body)
+            .ok()
+            .and_then(|value| value["id"].as_str().map(str::to_string))
+            .unwrap_or_default()
+    }
+
+    fn touch(&mut self, id: &str) {
+        self.revision += 1;
+        self.changed_at.insert(id.to_string(), self.revision);
+    }
+
+    fn collection(&self, resource: Resource) -> &Vec<Value> {
+        match resource {
+            Resource::Projects => &self.projects,
+            Resource::Sections => &self.sections,
+            Resource::Tasks => &self.tasks,
+            Resource::Labels => &self.labels,
+            Resource::Comments => &self.comments,
+        }
+    }
+
+    fn collection_mut(&mut self, resource: Resource) -> &mut Vec<Value> {
+        match resource {
+            Resource::Projects => &mut self.projects,
+            Resource::Sections => &mut self.sections,
+            Resource::Tasks => &mut self.tasks,
+            Resource::Labels => &mut self.labels,
+            Resource::Comments => &mut self.comments,
+        }
+    }
+
+    /// `POST /<resource>`, made at most once per request id: a create sent
+    /// again with the same id gets the resource the first one made.
+    pub fn create_once(
+        &mut self,
+        resource: Resource,
+        body: &Value,
+        request_id: Option<&str>,
+    ) -> Result<Value, StoreError> {
+        let Some(request_id) = request_id else {
+            return self.create(resource, body);
+        };
+        if let Some(created) = self.created_by_request.get(request_id) {
+            return Ok(created.clone());
+        }
+        let created = self.create(resource, body)?;
+        self.created_by_request.insert(request_id.to_string(), created.clone());
+        Ok(created)
+    }
+
+    /// Runs the commands of a Sync API write and reports how each went in
+    /// `sync_status`, keyed by the command's uuid.
+    pub fn run_commands(&mut self, commands: &[Value]) -> Value {
+        let mut sync_status = serde_json::Map::new();
+        for command in commands {
+            let uuid = command["uuid"].as_str().unwrap_or_default().to_string();
+            if let Some(status) = self.command_statuses.get(&uuid) {
+                sync_status.insert(uuid, status.clone());
+                continue;
+            }
+            let command_type = command["type"].as_str().unwrap_or_default();
+            let status = match self.run_command(command_type, &command["args"]) {
+                Ok(()) => json!("ok"),
+                Err(StoreError::NotFound) => {
+                    json!({ "error_code": 21, "error": "Item not found", "http_code": 404 })
+                }
+                Err(StoreError::Invalid(message)) => json!({ "error_code": 20, "error": message, "http_code": 400 }),
+                Err(StoreError::Storage(message)) => json!({ "error_code": 1, "error": message, "http_code": 500 }),
+            };
+            self.command_statuses.insert(uuid.clone(), status.clone());
+            sync_status.insert(uuid, status);
+        }
+        json!({
+            "sync_token": self.revision.to_string(),
+            "sync_status": sync_status,
+        })
+    }
+
+    /// Answers a Sync API request. `since` is the revision from the client's
+    /// sync token, or `None` for a full sync (`"*"`).
+    pub fn sync(&self, since: Option<u64>) -> Value {
+        let changed = |resource: &&Value| match since {
+            None => true,
+            Some(revision) => resource["id"]
+                .as_str()
+                .and_then(|id| self.changed_at.get(id))
+                .is_some_and(|changed_at| *changed_at > revision),
+        };
+        let archived = archived_ids(&self.projects);
+
+        let mut projects: Vec<Value> = self.projects.iter().filter(changed).map(sync_project).collect();
+        let mut sections: Vec<Value> = self
+            .sections
+            .iter()
+            .filter(changed)
+            .filter(|section| is_active(Resource::Sections, section, &archived))
+            .map(sync_section)
+            .collect();
+        let mut items: Vec<Value> = self
+            .tasks
+            .iter()
+            .filter(changed)
+            .filter(|task| is_active(Resource::Tasks, task, &archived))
+            .filter(|task| since.is_some() || task["is_completed"] == false)
+            .map(sync_item)
+            .collect();
+        let mut labels: Vec<Value> = self.labels.iter().filter(changed).map(sync_label).collect();
+
+        if let Some(revision) = since {
+            for (deleted_at, resource, value) in &self.deleted {
+                if *deleted_at <= revision {
+                    continue;
+                }
+                let (list, mut value) = match resource {
+                    Resource::Projects => (&mut projects, sync_project(value)),
+                    Resource::Sections => (&mut sections, sync_section(value)),
+                    Resource::Tasks => (&mut items, sync_item(value)),
+                    Resource::Labels => (&mut labels, sync_label(value)),
+                    Resource::Comments => continue,
+                };
+                value["is_deleted"] = json!(true);
+                list.push(value);
+            }
+        }
+
+        json!({
+            "sync_token": self.revision.to_string(),
+            "full_sync": since.is_none(),
+            "projects": projects,
+            "sections": sections,
+            "items": items,
+            "labels": labels,
+        })
+    }
+}
+
+impl Storage for JsonStore {
+    fn next_id(&mut self) -> Result<String, StoreError> {
+        self.next_id += 1;
+        Ok(self.next_id.to_string())
+    }
+
+    fn all(&self, resource: Resource) -> Result<Vec<Value>, StoreError> {
+        Ok(self.collection(resource).clone())
+    }
+
+    fn get(&self, resource: Resource, id: &str) -> Result<Option<Value>, StoreError> {
+        Ok(self.collection(resource).iter().find(|value| value["id"] == id).cloned())
+    }
+
+    fn count(&self, resource: Resource) -> Result<usize, StoreError> {
+        Ok(self.collection(resource).len())
+    }
+
+    fn put(&mut self, resource: Resource, value: Value) -> Result<(), StoreError> {
+        let id = value["id"].as_str().unwrap_or_default().to_string();
+        let collection = self.collection_mut(resource);
+        match collection.iter_mut().find(|stored| stored["id"] == id.as_str()) {
+            Some(stored) => *stored = value,
+            None => collection.push(value),
+        }
+        self.touch(&id);
+        Ok(())
+    }
+
+    fn remove(&mut self, resource: Resource, id: &str) -> Result<(), StoreError> {
+        let collection = self.collection_mut(resource);
+        if let Some(index) = collection.iter().position(|value| value["id"] == id) {
+            let value = collection.remove(index);
+            self.revision += 1;
+            self.deleted.push((self.revision, resource, value));
+        }
+        Ok(())
+    }
+
+    fn mark_changed(&mut self, id: &str) {
+        self.touch(id);
+    }
+}
+
+/// Refuses a task's `due_string` or `due_date` that is not understood, as the
+/// API does, unless it is the string the task is already due by: the editors
+/// send it back with every change.
+pub fn validate_due(task: &Value, body: &Value) -> Result<(), StoreError> {
+    let due_string = body
+        .get("due_string")
+        .or_else(|| body.get("due_date"))
+        .and_then(Value::as_str);
+    match due_string {
+        Some(due_string) if task["due"]["string"] != due_string && due_from_string(due_string).is_none() => {
+            Err(StoreError::Invalid(format!("Unknown due date '{}'", due_string)))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Checks the fields the real API requires when creating a resource.
+pub fn validate(resource: Resource, body: &Value) -> Result<(), StoreError> {
+    let required = match resource {
+        Resource::Tasks | Resource::Comments => "content",
+        _ => "name",
+    };
+    if body[required].as_str().is_none_or(|value| value.trim().is_empty()) {
+        return Err(StoreError::Invalid(format!("{} is required", required)));
+    }
+    if resource == Resource::Sections && body["project_id"].as_str().is_none() {
+        return Err(StoreError::Invalid("project_id is required".to_string()));
+    }
+    if resource == Resource::Tasks {
+        validate_due(&Value::Null, body)?;
+    }
+    if resource == Resource::Comments
+        && body["task_id"].as_str().is_none()
+        && body["project_id"].as_str().is_none()
+    {
+        return Err(StoreError::Invalid("task_id or project_id is required".to_string()));
+    }
+    Ok(())
+}
+
+/// A new resource as the REST API returns it, before the fields of the create
+/// request are applied.
+pub fn template(resource: Resource, id: &str, order: usize, inbox_id: Value) -> Value {
+    match resource {
+        Resource::Projects => json!({
+            "id": id,
+            "name": "",
+            "comment_count": 0,
+            "order": order,
+            "color": "charcoal",
+            "is_shared": false,
+            "is_favorite": false,
+            "is_inbox_project": false,
+            "is_team_inbox": false,
+            "view_style": "list",
+            "url": format!("https://todoist.com/showProject?id={}", id),
+            "parent_id": null,
+        }),
+        Resource::Sections => json!({
+            "id": id,
+            "project_id": null,
+            "order": order,
+            "name": "",
+        }),
+        Resource::Tasks => json!({
+            "id": id,
+            "project_id": inbox_id,
+            "section_id": null,
+            "content": "",
+            "description": "",
+            "is_completed": false,
+            "labels": [],
+            "parent_id": null,
+            "order": order,
+            "priority": 1,
+            "due": null,
+            "url": format!("https://todoist.com/showTask?id={}", id),
+            "comment_count": 0,
+            "created_at": Utc::now().format("%Y-%m-%dT%H:%M:%S%.6fZ").to_string(),
+            "creator_id": "0",
+            "assignee_id": null,
+            "assigner_id": null,
+            "duration": null,
+        }),
+        Resource::Labels => json!({
+            "id": id,
+            "name": "",
+            "color": "charcoal",
+            "order": order,
+            "is_favorite": false,
+        }),
+        Resource::Comments => json!({
+            "id": id,
+            "task_id": null,
+            "project_id": null,
+            "posted_at": Utc::now().format("%Y-%m-%dT%H:%M:%S%.6fZ").to_string(),
+            "content": "",
+        }),
+    }
+}
+
+/// Copies the writable fields of a create/update request body onto a stored
+/// resource. Unknown and `null` fields are ignored, as are read-only ones such
+/// as a task's `due`, which the real API only changes through `due_*` fields.
+/// A due string the task already has, or one that is not understood, leaves
+/// its date alone; `validate_due` refuses the latter beforehand.
+pub fn apply_fields(resource: Resource, value: &mut Value, body: &Value) {
+    for field in resource.writable_fields() {
+        if let Some(new_value) = body.get(field).filter(|new_value| !new_value.is_null()) {
+            value[*field] = new_value.clone();
+        }
+    }
+
+    if resource != Resource::Tasks {
+        return;
+    }
+    let due_string = body
+        .get("due_string")
+        .or_else(|| body.get("due_date"))
+        .and_then(Value::as_str);
+    if let Some(due_string) = due_string {
+        if value["due"]["string"] != due_string {
+            if let Some(due) = due_from_string(due_string) {
+                value["due"] = due;
+            }
+        }
+    } else if let Some(due_datetime) = body.get("due_datetime").and_then(Value::as_str) {
+        value["due"] = json!({
+            "string": due_datetime,
+            "date": due_datetime.get(..10).unwrap_or(due_datetime),
+            "is_recurring": false,
+            "datetime": due_datetime,
+            "timezone": null,
+        });
+    }
+}
+
+/// Understands a handful of due strings: "today", "tomorrow", "yesterday",
+/// weekdays such as "friday" or "next monday", "in 3 days", "every ..." and
+/// ISO dates, each optionally followed by a time such as "09:30", which makes
+/// a floating due time. "no date" clears the due date. `None` for anything
+/// else.
+fn due_from_string(due_string: &str) -> Option<Value> {
+    let due_string = due_string.trim();
+    if due_string.is_empty() || due_string.eq_ignore_ascii_case("no date") {
+        return Some(Value::Null);
+    }
+
+    let lowercase = due_string.to_lowercase();
+    let (day, time) = match lowercase.rsplit_once(' ') {
+        Some((day, time)) => match NaiveTime::parse_from_str(time, "%H:%M") {
+            Ok(time) => (day, Some(time)),
+            Err(_) => (lowercase.as_str(), None),
+        },
+        None => (lowercase.as_str(), None),
+    };
+
+    let today = Local::now().date_naive();
+    let is_recurring = day.starts_with("every");
+    let date = if is_recurring {
+        due::first_occurrence(day, today)?
+    } else {
+        parse_day(day, today)?
+    };
+
+    Some(json!({
+        "string": due_string,
+        "date": date_string(date),
+        "is_recurring": is_recurring,
+        "datetime": time.map(|time| date.and_time(time).format("%Y-%m-%dT%H:%M:%S").to_string()),
+        "timezone": null,
+    }))
+}
+
+/// The day a single due date such as "tomorrow", "fri", "next monday", "in 2
+/// weeks" or "2024-10-21" falls on, or `None` if that is off the calendar.
+fn parse_day(day: &str, today: NaiveDate) -> Option<NaiveDate> {
+    match day {
+        "today" => return Some(today),
+        "tomorrow" => return today.succ_opt(),
+        "yesterday" => return today.pred_opt(),
+        _ => {}
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(day, "%Y-%m-%d") {
+        return Some(date);
+    }
+
+    // "friday" is today on a Friday, "next friday" is always a later one
+    let (weekday, at_least) = match day.strip_prefix("next ") {
+        Some(weekday) => (weekday, 1),
+        None => (day, 0),
+    };
+    if let Ok(weekday) = weekday.parse::<Weekday>() {
+        let days_ahead = (weekday.num_days_from_monday() as i64 - today.weekday().num_days_from_monday() as i64
+            - at_least)
+            .rem_euclid(7)
+            + at_least;
+        return today.checked_add_signed(Duration::try_days(days_ahead)?);
+    }
+
+    let (count, unit) = day.strip_prefix("in ")?.split_once(' ')?;
+    let count = count.parse::<u32>().ok()?;
+    match unit.trim_end_matches('s') {
+        "day" => today.checked_add_signed(Duration::try_days(count as i64)?),
+        "week" => today.checked_add_signed(Duration::try_days(7 * count as i64)?),
+        "month" => today.checked_add_months(Months::new(count)),
+        _ => None,
+    }
+}
+
+/// The due a recurring task moves to when it is closed today, or `None` if
+/// the task does not recur (or its rule is not understood).
+pub fn reschedule(task: &Value) -> Option<Value> {
+    let due: Due = serde_json::from_value(task["due"].clone()).ok()?;
+    let next = due.next_occurrence(Local::now().date_naive())?;
+    serde_json::to_value(next).ok()
+}
+
+pub fn date_string(date: NaiveDate) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
+
+pub fn sync_project(project: &Value) -> Value {
+    json!({
+        "id": project["id"],
+        "name": project["name"],
+        "color": project["color"],
+        "parent_id": project["parent_id"],
+        "child_order": project["order"],
+        "shared": project["is_shared"],
+        "is_favorite": project["is_favorite"],
+        "is_deleted": false,
+        "is_archived": project["is_archived"] == true,
+        "view_style": project["view_style"],
+        "inbox_project": project["is_inbox_project"],
+        "team_inbox": project["is_team_inbox"],
+    })
+}
+
+pub fn sync_section(section: &Value) -> Value {
+    json!({
+        "id": section["id"],
+        "name": section["name"],
+        "project_id": section["project_id"],
+        "section_order": section["order"],
+        "is_deleted": false,
+    })
+}
+
+pub fn sync_label(label: &Value) -> Value {
+    json!({
+        "id": label["id"],
+        "name": label["name"],
+        "color": label["color"],
+        "item_order": label["order"],
+        "is_favorite": label["is_favorite"],
+        "is_deleted": false,
+    })
+}
+
+/// Label names used on open tasks that are not personal labels. With no
+/// collaborators, this is the closest local equivalent of shared labels.
+pub fn shared_label_names(labels: &[Value], tasks: &[Value]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for task in tasks.iter().filter(|task| task["is_completed"] != true) {
+        for name in task["labels"].as_array().into_iter().flatten().filter_map(Value::as_str) {
+            let personal = labels.iter().any(|label| label["name"] == name);
+            if !personal && !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names
+}
+
+/// Converts a stored REST task into the Sync API "item" shape, where a due
+/// date with a time is folded into `due.date`.
+pub fn sync_item(task: &Value) -> Value {
+    let due = match task.get("due").filter(|due| !due.is_null()) {
+        Some(due) => json!({
+            "date": if due["datetime"].is_null() { &due["date"] } else { &due["datetime"] },
+            "timezone": due["timezone"],
+            "string": due["string"],
+            "is_recurring": due["is_recurring"],
+            "lang": "en",
+        }),
+        None => Value::Null,
+    };
+
+    json!({
+        "id": task["id"],
+        "project_id": task["project_id"],
+        "section_id": task["section_id"],
+        "content": task["content"],
+        "description": task["description"],
+        "checked": task["is_completed"],
+        "is_deleted": false,
+        "labels": task["labels"],
+        "parent_id": task["parent_id"],
+        "child_order": task["order"],
+        "priority": task["priority"],
+        "due": due,
+        "added_at": task["created_at"],
+        "added_by_uid": task["creator_id"],
+        "responsible_uid": task["assignee_id"],
+        "assigned_by_uid": task["assigner_id"],
+        "duration": task["duration"],
+    })
+}
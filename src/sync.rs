@@ -0,0 +1,409 @@
+use std::sync::{mpsc::Sender, Arc};
+
+use serde::Deserialize;
+use tokio::sync::Mutex;
+
+use crate::{
+    backend::Backend,
+    priority::Priority,
+    projects::{ListType, Project},
+    sections::Section,
+    due::Due,
+    filters::SavedFilter,
+    labels::Label,
+    tasks::{Duration, Task},
+    App, TaskResult,
+};
+
+/// Token that asks the Sync API for a full sync.
+pub const FULL_SYNC_TOKEN: &str = "*";
+
+/// Response of the Todoist Sync API for the resource types we request.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SyncResponse {
+    pub sync_token: String,
+    #[serde(default)]
+    pub full_sync: bool,
+    #[serde(default)]
+    pub projects: Vec<SyncProject>,
+    #[serde(default)]
+    pub sections: Vec<SyncSection>,
+    #[serde(default)]
+    pub items: Vec<SyncItem>,
+    #[serde(default)]
+    pub labels: Vec<SyncLabel>,
+    #[serde(default)]
+    pub filters: Vec<SyncFilter>,
+    /// Only sent when the user's settings changed.
+    pub user: Option<SyncUser>,
+}
+
+/// Response of the Sync API to a batch of commands: `"ok"` or an error
+/// object for each command, keyed by its uuid.
+#[derive(Debug, Deserialize)]
+pub struct CommandResponse {
+    #[serde(default)]
+    pub sync_status: std::collections::HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SyncUser {
+    /// First day of the week, 1 for Monday to 7 for Sunday.
+    pub start_day: Option<u8>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SyncProject {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub color: String,
+    pub parent_id: Option<String>,
+    #[serde(default)]
+    pub child_order: i32,
+    #[serde(default)]
+    pub shared: bool,
+    #[serde(default)]
+    pub is_favorite: bool,
+    #[serde(default)]
+    pub is_deleted: bool,
+    #[serde(default)]
+    pub is_archived: bool,
+    pub view_style: Option<ListType>,
+    #[serde(default)]
+    pub inbox_project: bool,
+    #[serde(default)]
+    pub team_inbox: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SyncSection {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub project_id: String,
+    #[serde(default)]
+    pub section_order: i32,
+    #[serde(default)]
+    pub is_deleted: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SyncItem {
+    pub id: String,
+    #[serde(default)]
+    pub project_id: String,
+    pub section_id: Option<String>,
+    #[serde(default)]
+    pub content: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub checked: bool,
+    #[serde(default)]
+    pub is_deleted: bool,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    pub parent_id: Option<String>,
+    #[serde(default)]
+    pub child_order: i32,
+    #[serde(default)]
+    pub priority: Priority,
+    pub due: Option<SyncDue>,
+    #[serde(default)]
+    pub added_at: String,
+    pub added_by_uid: Option<String>,
+    pub responsible_uid: Option<String>,
+    pub assigned_by_uid: Option<String>,
+    pub duration: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SyncLabel {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub color: String,
+    #[serde(default)]
+    pub item_order: i32,
+    #[serde(default)]
+    pub is_favorite: bool,
+    #[serde(default)]
+    pub is_deleted: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SyncFilter {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub query: String,
+    #[serde(default)]
+    pub color: String,
+    #[serde(default)]
+    pub item_order: i32,
+    #[serde(default)]
+    pub is_favorite: bool,
+    #[serde(default)]
+    pub is_deleted: bool,
+}
+
+/// The Sync API folds the time of day into `date` ("2024-03-01T09:00:00").
+#[derive(Debug, Clone, Deserialize)]
+pub struct SyncDue {
+    pub date: String,
+    pub timezone: Option<String>,
+    #[serde(default)]
+    pub string: String,
+    #[serde(default)]
+    pub is_recurring: bool,
+}
+
+impl SyncProject {
+    fn is_removed(&self) -> bool {
+        self.is_deleted || self.is_archived
+    }
+
+    fn into_project(self) -> Project {
+        Project {
+            url: format!("https://todoist.com/showProject?id={}", self.id),
+            id: self.id,
+            name: self.name,
+            comment_count: 0,
+            order: self.child_order,
+            color: self.color,
+            is_shared: self.shared,
+            is_favorite: self.is_favorite,
+            is_inbox_project: self.inbox_project,
+            is_team_inbox: self.team_inbox,
+            view_style: self.view_style.unwrap_or(ListType::List),
+            parent_id: self.parent_id,
+        }
+    }
+}
+
+impl SyncSection {
+    fn into_section(self) -> Section {
+        Section {
+            id: self.id,
+            project_id: self.project_id,
+            order: self.section_order,
+            name: self.name,
+        }
+    }
+}
+
+impl SyncLabel {
+    fn into_label(self) -> Label {
+        Label {
+            id: self.id,
+            name: self.name,
+            color: self.color,
+            order: self.item_order,
+            is_favorite: self.is_favorite,
+        }
+    }
+}
+
+impl SyncFilter {
+    fn into_filter(self) -> SavedFilter {
+        SavedFilter {
+            id: self.id,
+            name: self.name,
+            query: self.query,
+            color: self.color,
+            order: self.item_order,
+            is_favorite: self.is_favorite,
+        }
+    }
+}
+
+impl SyncItem {
+    fn is_removed(&self) -> bool {
+        self.is_deleted || self.checked
+    }
+
+    fn into_task(self) -> Task {
+        let due = self.due.and_then(|due| {
+            let datetime = (due.date.len() > 10).then_some(due.date.as_str());
+            Due::new(due.string, &due.date, datetime, due.is_recurring, due.timezone).ok()
+        });
+
+        Task {
+            url: format!("https://todoist.com/showTask?id={}", self.id),
+            id: self.id,
+            project_id: self.project_id,
+            section_id: self.section_id,
+            content: self.content,
+            description: self.description,
+            is_completed: self.checked,
+            labels: self.labels,
+            parent_id: self.parent_id,
+            order: self.child_order,
+            priority: self.priority,
+            due,
+            comment_count: 0,
+            created_at: self.added_at,
+            creator_id: self.added_by_uid.unwrap_or_default(),
+            assignee_id: self.responsible_uid,
+            assigner_id: self.assigned_by_uid,
+            duration: self.duration,
+        }
+    }
+}
+
+/// Merges a sync response into the app state. Deltas update or append
+/// resources and drop deleted ones; a full sync also drops anything the server
+/// no longer returned.
+pub fn apply(app: &mut App, response: SyncResponse) {
+    let full_sync = response.full_sync;
+
+    let (removed, updated): (Vec<SyncProject>, Vec<SyncProject>) =
+        response.projects.into_iter().partition(SyncProject::is_removed);
+    app.projects.merge(
+        updated.into_iter().map(SyncProject::into_project).collect(),
+        removed.into_iter().map(|project| project.id).collect(),
+        full_sync,
+    );
+    app.tasks.projects = crate::query_projects(&app.projects);
+
+    let (removed, updated): (Vec<SyncSection>, Vec<SyncSection>) =
+        response.sections.into_iter().partition(|section| section.is_deleted);
+    app.tasks.sections.merge(
+        updated.into_iter().map(SyncSection::into_section).collect(),
+        removed.into_iter().map(|section| section.id).collect(),
+        full_sync,
+    );
+    app.queue.apply_pending_sections(&mut app.tasks.sections.sections);
+
+    let (removed, updated): (Vec<SyncLabel>, Vec<SyncLabel>) =
+        response.labels.into_iter().partition(|label| label.is_deleted);
+    app.labels.merge(
+        updated.into_iter().map(SyncLabel::into_label).collect(),
+        removed.into_iter().map(|label| label.id).collect(),
+        full_sync,
+    );
+
+    apply_settings(app, response.filters, response.user, full_sync);
+
+    let (removed, mut updated): (Vec<SyncItem>, Vec<SyncItem>) =
+        response.items.into_iter().partition(SyncItem::is_removed);
+    // Tasks completed or deleted offline, or in a section deleted offline,
+    // stay hidden until the queue catches up
+    let pending_removals = app.queue.pending_removals();
+    let section_removals = app.queue.pending_section_removals();
+    updated.retain(|item| {
+        !pending_removals.contains(&item.id)
+            && !item.section_id.as_ref().is_some_and(|id| section_removals.contains(id))
+    });
+    app.tasks.merge(
+        updated.into_iter().map(SyncItem::into_task).collect(),
+        removed.into_iter().map(|item| item.id).collect(),
+        full_sync,
+    );
+
+    // Fall back to the first project if the selected one went away
+    if let Some(selected_id) = app.projects.selected_project.clone() {
+        if !app.projects.projects.iter().any(|p| p.id == selected_id) {
+            app.projects.selected_project = None;
+            app.projects.state.select(None);
+            if let Some(&(index, _)) = app.projects.rows.first() {
+                let project_id = app.projects.projects[index].id.clone();
+                app.projects.state.select(Some(0));
+                crate::show_project(app, project_id);
+            }
+        }
+    }
+}
+
+/// Merges what only the Sync API knows about: saved filters and the user's
+/// settings.
+pub fn apply_settings(app: &mut App, filters: Vec<SyncFilter>, user: Option<SyncUser>, full_sync: bool) {
+    let (removed, updated): (Vec<SyncFilter>, Vec<SyncFilter>) =
+        filters.into_iter().partition(|filter| filter.is_deleted);
+    app.filters.merge(
+        updated.into_iter().map(SyncFilter::into_filter).collect(),
+        removed.into_iter().map(|filter| filter.id).collect(),
+        full_sync,
+    );
+
+    if let Some(start_day) = user.and_then(|user| user.start_day) {
+        app.tasks.start_day = crate::tasks::start_day(start_day);
+    }
+}
+
+/// Keeps the sync token and runs incremental syncs against the backend. Only
+/// one sync runs at a time; a request made while one is in flight is dropped.
+#[derive(Debug, Clone)]
+pub struct SyncEngine {
+    api: Backend,
+    sync_token: Arc<Mutex<String>>,
+    persist_token: bool,
+}
+
+impl SyncEngine {
+    pub fn new(api: Backend, sync_token: Option<String>, persist_token: bool) -> SyncEngine {
+        SyncEngine {
+            api,
+            sync_token: Arc::new(Mutex::new(
+                sync_token.unwrap_or_else(|| FULL_SYNC_TOKEN.to_string()),
+            )),
+            persist_token,
+        }
+    }
+
+    /// Syncs in the background. Errors are only reported for syncs the user
+    /// asked for, so a periodic sync while offline does not keep popping up.
+    pub fn spawn_sync(&self, tx: Sender<TaskResult>, report_errors: bool) {
+        let engine = self.clone();
+        tokio::spawn(async move {
+            engine.sync(tx, report_errors).await;
+        });
+    }
+
+    pub fn spawn_periodic(&self, interval: std::time::Duration, tx: Sender<TaskResult>) {
+        let engine = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            // The first tick fires immediately; startup already fetched everything
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                engine.sync(tx.clone(), false).await;
+            }
+        });
+    }
+
+
+    async fn sync(&self, tx: Sender<TaskResult>, report_errors: bool) {
+        let Ok(mut sync_token) = self.sync_token.try_lock() else {
+            return;
+        };
+
+        match self.api.sync(sync_token.clone()).await {
+            Ok(response) => {
+                *sync_token = response.sync_token.clone();
+                let _ = tx.send(TaskResult::Synced(Box::new(response)));
+            }
+            Err(e) => {
+                if report_errors {
+                    let _ = tx.send(TaskResult::Error(format!("Sync failed: {}", e)));
+                }
+            }
+        }
+    }
+
+    /// Keeps the token of a sync for the next session, when this one has
+    /// state worth keeping. Only call it once the sync has been applied and
+    /// cached, or the next session would skip changes it never saw.
+    pub fn save_token(&self, sync_token: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if self.persist_token {
+            crate::save_sync_token(sync_token)?;
+        }
+        Ok(())
+    }
+}
@@ -10,10 +10,13 @@ pub struct TaskEdit<'a> {
     pub description: TextArea<'a>,
     pub priority_string: TextArea<'a>,
     pub due_string: TextArea<'a>,
+    pub labels: Vec<String>,
     pub currently_editing: CurrentlyEditing,
-    pub children: Vec<usize>,
+    /// Ids of the subtasks, which are looked up again when shown because a
+    /// sync can move tasks around while the editor is open.
+    pub children: Vec<String>,
     pub children_list_state: ListState,
-    pub current_task_index: usize,
+    pub task_id: String,
 }
 
 impl<'a> TaskEdit<'a> {
@@ -22,8 +25,8 @@ impl<'a> TaskEdit<'a> {
         description: String,
         priority_string: String,
         due_string: String,
-        children: Vec<usize>,
-        current_task_index: usize,
+        children: Vec<String>,
+        task_id: String,
         currently_editing: CurrentlyEditing,
     ) -> Self {
         let mut task_edit = TaskEdit {
@@ -31,10 +34,11 @@ impl<'a> TaskEdit<'a> {
             description: TextArea::from(vec![description]),
             priority_string: TextArea::from(vec![priority_string]),
             due_string: TextArea::from(vec![due_string]),
+            labels: Vec::new(),
             currently_editing,
             children,
             children_list_state: ListState::default(),
-            current_task_index,
+            task_id,
         };
 
         // Automatically update cursor styles
@@ -44,7 +48,7 @@ impl<'a> TaskEdit<'a> {
     }
 
     pub fn next(&mut self) {
-        if self.children.len() == 0 {
+        if self.children.is_empty() {
             self.children_list_state.select(None);
             return;
         }
@@ -62,7 +66,7 @@ impl<'a> TaskEdit<'a> {
     }
 
     pub fn previous(&mut self) {
-        if self.children.len() == 0 {
+        if self.children.is_empty() {
             self.children_list_state.select(None);
             return;
         }
@@ -93,7 +97,7 @@ impl<'a> TaskEdit<'a> {
             CurrentlyEditing::Description => self.description.set_cursor_style(active_style),
             CurrentlyEditing::Priority => self.priority_string.set_cursor_style(active_style),
             CurrentlyEditing::DueString => self.due_string.set_cursor_style(active_style),
-            CurrentlyEditing::ChildTasks => {}
+            CurrentlyEditing::Labels | CurrentlyEditing::ChildTasks => {}
         }
     }
 }
@@ -105,5 +109,6 @@ pub enum CurrentlyEditing {
     Description,
     Priority,
     DueString,
+    Labels,
     ChildTasks,
 }
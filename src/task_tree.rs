@@ -0,0 +1,306 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::tasks::Task;
+
+/// Where a task is filed among its siblings: under its parent, or at the top
+/// of its project and section.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Slot {
+    Child(String),
+    Root(String, Option<String>),
+}
+
+impl Slot {
+    fn of(task: &Task) -> Slot {
+        match &task.parent_id {
+            Some(parent_id) => Slot::Child(parent_id.clone()),
+            None => Slot::Root(task.project_id.clone(), task.section_id.clone()),
+        }
+    }
+}
+
+/// An index over a task list for walking the subtask hierarchy without
+/// scanning every task: positions by id, siblings by parent id or by project
+/// and section (in their `order`) and each task's depth.
+#[derive(Debug, Default)]
+pub struct TaskTree {
+    positions: HashMap<String, usize>,
+    /// Keyed by parent id, including parents that are not (yet) in the list,
+    /// and by project and section for top-level tasks.
+    siblings: HashMap<Slot, Vec<usize>>,
+    /// The slot each task is filed under, so it can be moved out of it.
+    slots: Vec<Option<Slot>>,
+    depths: Vec<u8>,
+}
+
+impl TaskTree {
+    pub fn build(tasks: &[Task]) -> TaskTree {
+        let mut tree = TaskTree {
+            positions: HashMap::with_capacity(tasks.len()),
+            siblings: HashMap::new(),
+            slots: Vec::with_capacity(tasks.len()),
+            depths: vec![0; tasks.len()],
+        };
+        for (index, task) in tasks.iter().enumerate() {
+            tree.positions.insert(task.id.clone(), index);
+            let slot = Slot::of(task);
+            tree.siblings.entry(slot.clone()).or_default().push(index);
+            tree.slots.push(Some(slot));
+        }
+        for siblings in tree.siblings.values_mut() {
+            siblings.sort_by_key(|&index| tasks[index].order);
+        }
+        for index in 0..tasks.len() {
+            if tasks[index].parent_id.as_ref().is_none_or(|id| !tree.positions.contains_key(id)) {
+                tree.set_depths(index, 0, tasks);
+            }
+        }
+        tree
+    }
+
+    /// Indexes a task that was appended to the list.
+    pub fn insert(&mut self, tasks: &[Task], index: usize) {
+        let task = &tasks[index];
+        self.positions.insert(task.id.clone(), index);
+        self.depths.resize(tasks.len(), 0);
+        self.slots.resize(tasks.len(), None);
+        self.attach(tasks, index);
+    }
+
+    /// Re-indexes a task that was replaced in place, as its parent, section
+    /// or order may have changed.
+    pub fn update(&mut self, tasks: &[Task], index: usize) {
+        if let Some(slot) = self.slots[index].take() {
+            if let Some(siblings) = self.siblings.get_mut(&slot) {
+                siblings.retain(|&sibling| sibling != index);
+            }
+        }
+        self.attach(tasks, index);
+    }
+
+    /// Re-sorts the siblings of a task after their orders were changed
+    /// together.
+    pub fn sort_siblings(&mut self, tasks: &[Task], index: usize) {
+        if let Some(siblings) = self.slots[index].as_ref().and_then(|slot| self.siblings.get_mut(slot)) {
+            siblings.sort_by_key(|&sibling| tasks[sibling].order);
+        }
+    }
+
+    /// Files a task among its siblings and works out the depths of it and of
+    /// any subtasks that arrived before it.
+    fn attach(&mut self, tasks: &[Task], index: usize) {
+        let task = &tasks[index];
+        let slot = Slot::of(task);
+        let siblings = self.siblings.entry(slot.clone()).or_default();
+        let at = siblings.partition_point(|&sibling| tasks[sibling].order <= task.order);
+        siblings.insert(at, index);
+        self.slots[index] = Some(slot);
+
+        let depth = task
+            .parent_id
+            .as_ref()
+            .and_then(|parent_id| self.positions.get(parent_id))
+            .map_or(0, |&parent| self.depths[parent] + 1);
+        self.set_depths(index, depth, tasks);
+    }
+
+    fn set_depths(&mut self, index: usize, depth: u8, tasks: &[Task]) {
+        // Tasks that are each other's parents must not be walked forever
+        let mut seen = HashSet::new();
+        let mut stack = vec![(index, depth)];
+        while let Some((index, depth)) = stack.pop() {
+            if !seen.insert(index) {
+                continue;
+            }
+            self.depths[index] = depth;
+            stack.extend(self.children(&tasks[index].id).iter().map(|&child| (child, depth.saturating_add(1))));
+        }
+    }
+
+    pub fn position(&self, task_id: &str) -> Option<usize> {
+        self.positions.get(task_id).copied()
+    }
+
+    /// The direct subtasks of a task, in their order.
+    pub fn children(&self, task_id: &str) -> &[usize] {
+        self.siblings
+            .get(&Slot::Child(task_id.to_string()))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// The tasks filed next to a task, itself included, in their order: the
+    /// subtasks of the same parent, or the top-level tasks of the same
+    /// project and section.
+    pub fn siblings(&self, index: usize) -> &[usize] {
+        self.slots
+            .get(index)
+            .and_then(Option::as_ref)
+            .and_then(|slot| self.siblings.get(slot))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    pub fn child_count(&self, task_id: &str) -> u16 {
+        self.children(task_id).len() as u16
+    }
+
+    /// How many ancestors of the task are in the list.
+    pub fn depth(&self, index: usize) -> u8 {
+        self.depths.get(index).copied().unwrap_or(0)
+    }
+
+    /// Every subtask below a task, depth first.
+    pub fn descendants(&self, tasks: &[Task], task_id: &str) -> Vec<usize> {
+        let mut descendants = Vec::new();
+        let mut seen = HashSet::new();
+        let mut stack: Vec<usize> = self.children(task_id).iter().rev().copied().collect();
+        while let Some(index) = stack.pop() {
+            if !seen.insert(index) {
+                continue;
+            }
+            descendants.push(index);
+            stack.extend(self.children(&tasks[index].id).iter().rev());
+        }
+        descendants
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::priority::Priority;
+
+    fn task(id: &str, parent_id: Option<&str>, order: i32) -> Task {
+        Task {
+            id: id.to_string(),
+            project_id: "project".to_string(),
+            section_id: None,
+            content: id.to_string(),
+            description: String::new(),
+            is_completed: false,
+            labels: Vec::new(),
+            parent_id: parent_id.map(str::to_string),
+            order,
+            priority: Priority::NONE,
+            due: None,
+            url: String::new(),
+            comment_count: 0,
+            created_at: String::new(),
+            creator_id: String::new(),
+            assignee_id: None,
+            assigner_id: None,
+            duration: None,
+        }
+    }
+
+    fn ids(tasks: &[Task], indexes: &[usize]) -> Vec<String> {
+        indexes.iter().map(|&index| tasks[index].id.clone()).collect()
+    }
+
+    /// a
+    /// ├─ b
+    /// │  └─ d
+    /// └─ c
+    /// e
+    fn sample() -> Vec<Task> {
+        vec![
+            task("d", Some("b"), 1),
+            task("c", Some("a"), 2),
+            task("a", None, 1),
+            task("b", Some("a"), 1),
+            task("e", None, 2),
+        ]
+    }
+
+    #[test]
+    fn build_indexes_children_in_order_and_depths() {
+        let tasks = sample();
+        let tree = TaskTree::build(&tasks);
+
+        assert_eq!(tree.position("a"), Some(2));
+        assert_eq!(tree.position("x"), None);
+        assert_eq!(ids(&tasks, tree.children("a")), ["b", "c"]);
+        assert_eq!(tree.child_count("a"), 2);
+        assert_eq!(tree.child_count("c"), 0);
+        assert_eq!(ids(&tasks, tree.siblings(2)), ["a", "e"]);
+        assert_eq!(ids(&tasks, tree.siblings(1)), ["b", "c"]);
+        assert_eq!(ids(&tasks, &tree.descendants(&tasks, "a")), ["b", "d", "c"]);
+        let depths: Vec<u8> = ["a", "b", "c", "d", "e"]
+            .iter()
+            .map(|id| tree.depth(tree.position(id).unwrap()))
+            .collect();
+        assert_eq!(depths, [0, 1, 1, 2, 0]);
+    }
+
+    #[test]
+    fn subtasks_of_missing_parents_are_at_the_top() {
+        let tasks = vec![task("b", Some("gone"), 1), task("c", Some("b"), 1)];
+        let tree = TaskTree::build(&tasks);
+
+        assert_eq!(tree.depth(0), 0);
+        assert_eq!(tree.depth(1), 1);
+        assert_eq!(ids(&tasks, tree.children("gone")), ["b"]);
+    }
+
+    #[test]
+    fn insert_places_a_task_among_its_siblings() {
+        let mut tasks = sample();
+        let mut tree = TaskTree::build(&tasks);
+
+        tasks.push(task("f", Some("a"), 2));
+        tree.insert(&tasks, 5);
+        // Equal orders keep the newest last
+        assert_eq!(ids(&tasks, tree.children("a")), ["b", "c", "f"]);
+        assert_eq!(tree.depth(5), 1);
+
+        tasks.push(task("g", Some("f"), 1));
+        tree.insert(&tasks, 6);
+        assert_eq!(tree.depth(6), 2);
+    }
+
+    #[test]
+    fn update_moves_a_task_with_its_subtasks() {
+        let mut tasks = sample();
+        let mut tree = TaskTree::build(&tasks);
+
+        // b, and d with it, goes under e
+        tasks[3].parent_id = Some("e".to_string());
+        tree.update(&tasks, 3);
+        assert_eq!(ids(&tasks, tree.children("a")), ["c"]);
+        assert_eq!(ids(&tasks, tree.children("e")), ["b"]);
+        assert_eq!(tree.depth(3), 1);
+        assert_eq!(tree.depth(0), 2);
+
+        // and then to the top, after a
+        tasks[3].parent_id = None;
+        tasks[3].order = 1;
+        tree.update(&tasks, 3);
+        assert!(tree.children("e").is_empty());
+        assert_eq!(ids(&tasks, tree.siblings(3)), ["a", "b", "e"]);
+        assert_eq!(tree.depth(3), 0);
+        assert_eq!(tree.depth(0), 1);
+    }
+
+    #[test]
+    fn sort_siblings_follows_new_orders() {
+        let mut tasks = sample();
+        let mut tree = TaskTree::build(&tasks);
+
+        tasks[3].order = 3;
+        tree.sort_siblings(&tasks, 3);
+        assert_eq!(ids(&tasks, tree.children("a")), ["c", "b"]);
+    }
+
+    #[test]
+    fn parent_loops_do_not_hang() {
+        let tasks = vec![task("a", Some("b"), 1), task("b", Some("a"), 1)];
+        let tree = TaskTree::build(&tasks);
+
+        assert_eq!(ids(&tasks, &tree.descendants(&tasks, "a")), ["b", "a"]);
+
+        let mut tasks = sample();
+        let mut tree = TaskTree::build(&tasks);
+        tasks[2].parent_id = Some("d".to_string());
+        tree.update(&tasks, 2);
+        assert_eq!(ids(&tasks, &tree.descendants(&tasks, "a")), ["b", "d", "a", "c"]);
+    }
+}
@@ -1,54 +1,264 @@
 extern crate chrono;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use chrono::{Local, NaiveDate};
+use chrono::{Datelike, Local, NaiveDate, Weekday};
 use ratatui::widgets::ListState;
-use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    due::Due,
+    priority::Priority,
+    query::{self, Query},
+    queue::TEMP_ID_PREFIX,
+    sections::Sections,
+    task_tree::TaskTree,
+};
 
 #[derive(Debug, Default)]
 pub struct Tasks {
     pub tasks: Vec<Task>,
     pub filter: Filter,
     pub state: ListState,
-    pub tasks_with_children: HashMap<String, u16>,
+    /// Index of `tasks`, kept up to date as tasks are added, changed and
+    /// removed.
+    pub tree: TaskTree,
     pub display_tasks: Vec<usize>,
+    /// The sections of every project, for grouping a project's tasks.
+    pub sections: Sections,
+    /// Section headings shown in a project, as the position in `display_tasks`
+    /// they come before and the section name.
+    pub headings: Vec<(usize, String)>,
+    /// Sub-projects (id and name) whose tasks are shown with their parent's.
+    pub subprojects: Vec<(String, String)>,
+    /// Ids of the tasks whose subtasks are hidden.
+    pub folded: HashSet<String>,
+    /// Every project as id, name and parent id, for queries naming projects.
+    /// Taken from the sidebar when a query is applied and after each sync.
+    pub projects: Vec<(String, String, Option<String>)>,
+    /// How top-level tasks are ordered; subtasks always follow `order`.
+    pub sort: SortCriterion,
+    pub move_mode: bool,
+    /// Whether the task picked up in move mode was moved, so the new order of
+    /// its siblings still has to be saved.
+    pub reordered: bool,
+    /// The first day of the week from the account settings, Monday if unset.
+    pub start_day: Option<Weekday>,
 }
 
-#[derive(Debug)]
+/// How many days ahead the upcoming view looks by default.
+pub const UPCOMING_DAYS: i64 = 7;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub enum SortCriterion {
+    #[default]
     Priority,
     Date,
+    /// The order set by hand in move mode, as in the official apps.
+    Manual,
 }
 
 impl Tasks {
     pub fn new(items: Vec<Task>) -> Tasks {
         Tasks {
+            tree: TaskTree::build(&items),
             tasks: items,
             filter: Filter::All,
             state: ListState::default(),
-            tasks_with_children: HashMap::new(),
             display_tasks: Vec::new(),
+            sections: Sections::default(),
+            headings: Vec::new(),
+            subprojects: Vec::new(),
+            folded: HashSet::new(),
+            projects: Vec::new(),
+            sort: SortCriterion::default(),
+            move_mode: false,
+            reordered: false,
+            start_day: None,
         }
     }
 
-    pub fn find_tasks_with_children(&mut self) {
-        for task in &self.tasks {
-            if let Some(parent_id) = &task.parent_id {
-                *self
-                    .tasks_with_children
-                    .entry(parent_id.clone())
-                    .or_insert(0) += 1;
+    /// Rebuilds the index after `tasks` was changed wholesale.
+    pub fn reindex(&mut self) {
+        self.tree = TaskTree::build(&self.tasks);
+    }
+
+    /// Adds a task, or replaces the one with the same id.
+    pub fn upsert(&mut self, task: Task) {
+        match self.tree.position(&task.id) {
+            Some(index) => {
+                self.tasks[index] = task;
+                self.tree.update(&self.tasks, index);
+            }
+            None => {
+                self.tasks.push(task);
+                self.tree.insert(&self.tasks, self.tasks.len() - 1);
             }
         }
     }
 
+    /// Removes tasks by id. Positions shift, so the index is rebuilt.
+    pub fn remove_tasks(&mut self, task_ids: &[String]) {
+        let task_ids: HashSet<&String> = task_ids.iter().collect();
+        self.tasks.retain(|task| !task_ids.contains(&task.id));
+        self.reindex();
+    }
+
+    /// Puts a task under another parent, into a section or at the top of a
+    /// project, taking its subtasks along into the same project and section.
+    pub fn move_task(
+        &mut self,
+        index: usize,
+        project_id: String,
+        section_id: Option<String>,
+        parent_id: Option<String>,
+    ) {
+        for child in self.tree.descendants(&self.tasks, &self.tasks[index].id) {
+            self.tasks[child].project_id = project_id.clone();
+            self.tasks[child].section_id = section_id.clone();
+        }
+        let task = &mut self.tasks[index];
+        task.project_id = project_id;
+        task.section_id = section_id;
+        task.parent_id = parent_id;
+        self.tree.update(&self.tasks, index);
+        self.refresh();
+    }
+
+    /// The ids of a task's direct subtasks.
+    pub fn child_ids(&self, index: usize) -> Vec<String> {
+        self.tree
+            .children(&self.tasks[index].id)
+            .iter()
+            .map(|&child| self.tasks[child].id.clone())
+            .collect()
+    }
+
+    /// The ids of every subtask below a task.
+    pub fn descendant_ids(&self, task_id: &str) -> Vec<String> {
+        self.tree
+            .descendants(&self.tasks, task_id)
+            .into_iter()
+            .map(|index| self.tasks[index].id.clone())
+            .collect()
+    }
+
+    /// Applies synced changes. Completed and deleted tasks arrive in `removed`;
+    /// the cursor stays on the same task if it is still visible.
+    pub fn merge(&mut self, updated: Vec<Task>, removed: Vec<String>, full_sync: bool) {
+        let selected_task_id = self.selected_task_id();
+
+        let removed: HashSet<String> = removed.into_iter().collect();
+        if full_sync {
+            // Tasks still waiting to be created are not on the server yet
+            let updated_ids: HashSet<&str> = updated.iter().map(|task| task.id.as_str()).collect();
+            self.tasks.retain(|task| {
+                task.id.starts_with(TEMP_ID_PREFIX) || updated_ids.contains(task.id.as_str())
+            });
+        }
+        self.tasks.retain(|task| !removed.contains(&task.id));
+
+        let mut positions: HashMap<String, usize> = self
+            .tasks
+            .iter()
+            .enumerate()
+            .map(|(index, task)| (task.id.clone(), index))
+            .collect();
+        for task in updated {
+            match positions.get(&task.id) {
+                Some(&index) => {
+                    // The Sync API does not report comment counts
+                    let comment_count = self.tasks[index].comment_count;
+                    self.tasks[index] = task;
+                    self.tasks[index].comment_count = comment_count;
+                }
+                None => {
+                    positions.insert(task.id.clone(), self.tasks.len());
+                    self.tasks.push(task);
+                }
+            }
+        }
+
+        self.reindex();
+        self.rebuild(selected_task_id);
+    }
+
+    /// Rebuilds the displayed list after tasks were changed, keeping the
+    /// cursor on the same task.
+    pub fn refresh(&mut self) {
+        let selected_task_id = self.selected_task_id();
+        self.rebuild(selected_task_id);
+    }
+
+    fn rebuild(&mut self, selected_task_id: Option<String>) {
+        self.filter_task_list(false);
+        if let Some(task_id) = selected_task_id {
+            self.select_task_id(&task_id);
+        }
+    }
+
+    /// Swaps the placeholder of a task created offline for the task the server
+    /// created, re-pointing its subtasks at the real id.
+    pub fn replace_temp(&mut self, temp_id: &str, task: Task) {
+        let mut selected_task_id = self.selected_task_id();
+        if selected_task_id.as_deref() == Some(temp_id) {
+            selected_task_id = Some(task.id.clone());
+        }
+
+        if self.folded.remove(temp_id) {
+            self.folded.insert(task.id.clone());
+        }
+        for existing in &mut self.tasks {
+            if existing.parent_id.as_deref() == Some(temp_id) {
+                existing.parent_id = Some(task.id.clone());
+            }
+        }
+        // A placeholder that is gone was completed or deleted in the meantime
+        if let Some(existing) = self.tasks.iter_mut().find(|t| t.id == temp_id) {
+            *existing = task;
+        }
+
+        self.reindex();
+        self.rebuild(selected_task_id);
+    }
+
+    pub fn selected_task_id(&self) -> Option<String> {
+        let selected = self.state.selected()?;
+        let index = *self.display_tasks.get(selected)?;
+        self.tasks.get(index).map(|task| task.id.clone())
+    }
+
+    /// Moves the cursor to the given task, or to the closest parent shown if
+    /// it is folded away, or to the first row if it is not displayed.
+    pub fn select_task_id(&mut self, task_id: &str) {
+        let mut position = None;
+        let mut current = self.tree.position(task_id);
+        // Tasks that are each other's parents must not be walked forever
+        let mut seen = HashSet::new();
+        while let Some(index) = current.filter(|&index| seen.insert(index)) {
+            position = self.display_tasks.iter().position(|&shown| shown == index);
+            if position.is_some() {
+                break;
+            }
+            current = self.tasks[index]
+                .parent_id
+                .as_ref()
+                .and_then(|parent_id| self.tree.position(parent_id));
+        }
+        match position {
+            Some(position) => self.state.select(Some(position)),
+            None if !self.display_tasks.is_empty() => self.state.select(Some(0)),
+            None => self.state.select(None),
+        }
+    }
+
     pub fn sort_tasks(&mut self, criterion: SortCriterion) {
         // Instead of sorting the entire display_tasks vector, we need to rebuild it
         // with proper hierarchical sorting that preserves parent-child relationships
-        self.sort_tasks_hierarchically(criterion);
+        self.sort = criterion;
+        self.sort_tasks_hierarchically();
     }
 
-    fn sort_tasks_hierarchically(&mut self, criterion: SortCriterion) {
+    fn sort_tasks_hierarchically(&mut self) {
         // Store the currently selected task ID to preserve selection after sorting
         let selected_task_id = if let Some(selected_index) = self.state.selected() {
             if selected_index < self.display_tasks.len() {
@@ -60,46 +270,103 @@ impl Tasks {
             None
         };
         
-        // Get root tasks (tasks without parent_id) that match the filter
-        let mut root_tasks = Vec::new();
-        for (index, task) in self.tasks.iter().enumerate() {
-            if task.parent_id.is_none() {
-                let matches_filter = match &self.filter {
-                    Filter::All => true,
-                    Filter::Today => {
-                        let today = Local::now().date_naive();
-                        if let Some(due) = &task.due {
-                            due.date == today
-                        } else {
-                            false
-                        }
-                    }
-                    Filter::ProjectId(project_id) => {
-                        task.project_id == *project_id
-                    }
-                    Filter::Overdue => {
-                        let today = Local::now().date_naive();
-                        if let Some(due) = &task.due {
-                            due.date < today
-                        } else {
-                            false
-                        }
-                    }
-                };
-                
-                if matches_filter {
-                    root_tasks.push(index);
+        let mut root_tasks = self.root_tasks();
+        self.sort_roots(&mut root_tasks);
+        
+        // Rebuild display_tasks with sorted root tasks and their subtasks
+        self.group_by_section(root_tasks);
+        
+        // Restore selection to the same task if it still exists in the display list
+        if let Some(task_id) = selected_task_id {
+            if let Some(new_index) = self.display_tasks.iter().position(|&idx| self.tasks[idx].id == task_id) {
+                self.state.select(Some(new_index));
+            } else {
+                // If the selected task is no longer visible, select the first task
+                if !self.display_tasks.is_empty() {
+                    self.state.select(Some(0));
+                } else {
+                    self.state.select(None);
                 }
             }
         }
-        
-        // Sort root tasks by the specified criterion
-        match criterion {
+    }
+
+    /// The tasks the filter picks out that are not already shown under a
+    /// parent: top-level tasks, plus for queries and the upcoming view any
+    /// subtask whose ancestors all fall outside it.
+    fn root_tasks(&self) -> Vec<usize> {
+        let now = Local::now().naive_local();
+        let context = query::Context {
+            today: now.date(),
+            now,
+            projects: &self.projects,
+            sections: &self.sections,
+        };
+        let is_query = matches!(self.filter, Filter::Query { .. } | Filter::Upcoming { .. });
+        let shown_above = |task: &Task| {
+            let mut parent = task.parent_id.as_ref().and_then(|id| self.tree.position(id));
+            let mut seen = HashSet::new();
+            while let Some(index) = parent.filter(|&index| seen.insert(index)) {
+                if self.matches_filter(&self.tasks[index], &context) {
+                    return true;
+                }
+                parent = self.tasks[index].parent_id.as_ref().and_then(|id| self.tree.position(id));
+            }
+            false
+        };
+
+        (0..self.tasks.len())
+            .filter(|&index| {
+                let task = &self.tasks[index];
+                match &task.parent_id {
+                    None => self.matches_filter(task, &context),
+                    Some(_) => is_query && self.matches_filter(task, &context) && !shown_above(task),
+                }
+            })
+            .collect()
+    }
+
+    /// Whether a task belongs in the current view.
+    fn matches_filter(&self, task: &Task, context: &query::Context) -> bool {
+        match &self.filter {
+            Filter::All => true,
+            Filter::Today => task.due.as_ref().is_some_and(|due| due.is_due_on(context.today)),
+            Filter::ProjectId(project_id) => {
+                task.project_id == *project_id
+                    || self.subprojects.iter().any(|(id, _)| *id == task.project_id)
+            }
+            Filter::Overdue => task.due.as_ref().is_some_and(|due| due.is_overdue(context.now)),
+            Filter::Label(name) => task.labels.contains(name),
+            Filter::Query { query, .. } => query.matches(task, context),
+            Filter::Upcoming { days } => task
+                .due
+                .as_ref()
+                .is_some_and(|due| due.local_date() <= self.upcoming_end(context.today, *days)),
+        }
+    }
+
+    /// How many days are left in the current week, today included, with
+    /// weeks starting on the account's first day of the week.
+    pub fn days_left_in_week(&self, today: NaiveDate) -> i64 {
+        let first_day = self.start_day.unwrap_or(Weekday::Mon);
+        7 - today.weekday().days_since(first_day) as i64
+    }
+
+    /// The last day the upcoming view shows: exactly `days` days, today
+    /// included.
+    fn upcoming_end(&self, today: NaiveDate, days: i64) -> NaiveDate {
+        today + chrono::Duration::days(days.max(1) - 1)
+    }
+
+    /// Orders the top-level tasks by the chosen criterion.
+    fn sort_roots(&self, root_tasks: &mut [usize]) {
+        match self.sort {
             SortCriterion::Priority => {
+                // Most urgent first
                 root_tasks.sort_by(|a, b| {
                     let task_a = &self.tasks[*a];
                     let task_b = &self.tasks[*b];
-                    task_a.priority.cmp(&task_b.priority)
+                    task_b.priority.cmp(&task_a.priority)
                 });
             }
             SortCriterion::Date => {
@@ -107,34 +374,14 @@ impl Tasks {
                     let task_a = &self.tasks[*a];
                     let task_b = &self.tasks[*b];
                     match (&task_a.due, &task_b.due) {
-                        (Some(due_a), Some(due_b)) => due_a.date.cmp(&due_b.date),
+                        (Some(due_a), Some(due_b)) => due_a.sort_key().cmp(&due_b.sort_key()),
                         (Some(_), None) => std::cmp::Ordering::Less,
                         (None, Some(_)) => std::cmp::Ordering::Greater,
                         (None, None) => std::cmp::Ordering::Equal,
                     }
                 });
             }
-        }
-        
-        // Rebuild display_tasks with sorted root tasks and their subtasks
-        self.display_tasks = Vec::new();
-        for root_index in root_tasks {
-            self.display_tasks.push(root_index);
-            self.add_subtasks_recursively(root_index);
-        }
-        
-        // Restore selection to the same task if it still exists in the display list
-        if let Some(task_id) = selected_task_id {
-            if let Some(new_index) = self.display_tasks.iter().position(|&idx| self.tasks[idx].id == task_id) {
-                self.state.select(Some(new_index));
-            } else {
-                // If the selected task is no longer visible, select the first task
-                if !self.display_tasks.is_empty() {
-                    self.state.select(Some(0));
-                } else {
-                    self.state.select(None);
-                }
-            }
+            SortCriterion::Manual => root_tasks.sort_by_key(|&index| self.tasks[index].order),
         }
     }
 
@@ -142,51 +389,11 @@ impl Tasks {
         self.state = ListState::default();
         self.display_tasks = Vec::new();
         
-        // Get root tasks (tasks without parent_id) that match the filter
-        let mut root_tasks = Vec::new();
-        for (index, task) in self.tasks.iter().enumerate() {
-            if task.parent_id.is_none() {
-                let matches_filter = match &self.filter {
-                    Filter::All => true,
-                    Filter::Today => {
-                        let today = Local::now().date_naive();
-                        if let Some(due) = &task.due {
-                            due.date == today
-                        } else {
-                            false
-                        }
-                    }
-                    Filter::ProjectId(project_id) => {
-                        task.project_id == *project_id
-                    }
-                    Filter::Overdue => {
-                        let today = Local::now().date_naive();
-                        if let Some(due) = &task.due {
-                            due.date < today
-                        } else {
-                            false
-                        }
-                    }
-                };
-                
-                if matches_filter {
-                    root_tasks.push(index);
-                }
-            }
-        }
-        
-        // Sort root tasks by priority by default
-        root_tasks.sort_by(|a, b| {
-            let task_a = &self.tasks[*a];
-            let task_b = &self.tasks[*b];
-            task_a.priority.cmp(&task_b.priority)
-        });
+        let mut root_tasks = self.root_tasks();
+        self.sort_roots(&mut root_tasks);
         
         // Build tree structure by adding subtasks after their parents
-        for root_index in root_tasks {
-            self.display_tasks.push(root_index);
-            self.add_subtasks_recursively(root_index);
-        }
+        self.group_by_section(root_tasks);
         
         // Automatically select the first item if there are any tasks and auto_select is true
         if auto_select && !self.display_tasks.is_empty() {
@@ -194,28 +401,256 @@ impl Tasks {
         }
     }
     
-    fn add_subtasks_recursively(&mut self, parent_index: usize) {
-        let parent_id = &self.tasks[parent_index].id;
-        
-        // Find all direct children of this parent
-        let mut children: Vec<usize> = self.tasks.iter()
-            .enumerate()
-            .filter(|(_, task)| task.parent_id.as_ref() == Some(parent_id))
-            .map(|(index, _)| index)
+    /// Fills `display_tasks` with the root tasks in the given order, each
+    /// followed by its subtasks. In a project, tasks without a section come
+    /// first and the rest are grouped under their section's heading, followed
+    /// by the tasks of included sub-projects.
+    fn group_by_section(&mut self, root_tasks: Vec<usize>) {
+        self.display_tasks = Vec::new();
+        self.headings = Vec::new();
+
+        if let Filter::Upcoming { .. } = self.filter {
+            self.group_by_day(root_tasks);
+            return;
+        }
+        let Filter::ProjectId(project_id) = &self.filter else {
+            self.add_roots(root_tasks);
+            return;
+        };
+        let sections: Vec<(String, String)> = self
+            .sections
+            .for_project(project_id)
+            .into_iter()
+            .map(|section| (section.id.clone(), section.name.clone()))
             .collect();
-        
-        // Sort children by order field
-        children.sort_by_key(|&index| self.tasks[index].order);
-        
-        // Add children and their subtasks recursively
+
+        let (own, mut from_subprojects): (Vec<usize>, Vec<usize>) = root_tasks
+            .into_iter()
+            .partition(|&index| self.tasks[index].project_id == *project_id);
+
+        // Tasks of a section that no longer exists count as having none
+        let (unsectioned, mut rest): (Vec<usize>, Vec<usize>) =
+            own.into_iter().partition(|&index| {
+                !self.tasks[index]
+                    .section_id
+                    .as_ref()
+                    .is_some_and(|id| sections.iter().any(|(section_id, _)| section_id == id))
+            });
+        self.add_roots(unsectioned);
+
+        for (section_id, name) in sections {
+            self.headings.push((self.display_tasks.len(), name));
+            let (in_section, others): (Vec<usize>, Vec<usize>) = rest
+                .into_iter()
+                .partition(|&index| self.tasks[index].section_id.as_ref() == Some(&section_id));
+            rest = others;
+            self.add_roots(in_section);
+        }
+
+        // Then each included sub-project under its own name
+        for (subproject_id, name) in self.subprojects.clone() {
+            self.headings.push((self.display_tasks.len(), name));
+            let (in_project, others): (Vec<usize>, Vec<usize>) = from_subprojects
+                .into_iter()
+                .partition(|&index| self.tasks[index].project_id == subproject_id);
+            from_subprojects = others;
+            self.add_roots(in_project);
+        }
+    }
+
+    /// Groups the root tasks under a heading for each day they are due on,
+    /// after a block of the overdue ones. Days without tasks are left out.
+    fn group_by_day(&mut self, root_tasks: Vec<usize>) {
+        let today = Local::now().date_naive();
+        let due_date = |index: usize| self.tasks[index].due.as_ref().map(|due| due.local_date());
+
+        // Tasks due earlier today are still today's, as in the official apps
+        let (overdue, mut rest): (Vec<usize>, Vec<usize>) = root_tasks
+            .into_iter()
+            .partition(|&index| due_date(index).is_some_and(|date| date < today));
+        let mut days: Vec<NaiveDate> = rest.iter().filter_map(|&index| due_date(index)).collect();
+        days.sort();
+        days.dedup();
+
+        if !overdue.is_empty() {
+            self.headings.push((self.display_tasks.len(), "Overdue".to_string()));
+            self.add_roots(overdue);
+        }
+        for day in days {
+            self.headings.push((self.display_tasks.len(), day_heading(day, today)));
+            let (on_day, others): (Vec<usize>, Vec<usize>) = rest
+                .into_iter()
+                .partition(|&index| self.tasks[index].due.as_ref().is_some_and(|due| due.is_due_on(day)));
+            rest = others;
+            self.add_roots(on_day);
+        }
+    }
+
+    /// Moves the cursor to the first task under the next heading.
+    pub fn next_group(&mut self) -> bool {
+        let Some(selected) = self.state.selected() else {
+            return false;
+        };
+        let next = self
+            .headings
+            .iter()
+            .map(|(position, _)| *position)
+            .find(|&position| position > selected && position < self.display_tasks.len());
+        match next {
+            Some(position) => {
+                self.state.select(Some(position));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves the cursor to the first task of its group, or of the group
+    /// above if it is already there.
+    pub fn previous_group(&mut self) -> bool {
+        let Some(selected) = self.state.selected().filter(|&selected| selected > 0) else {
+            return false;
+        };
+        let previous = self
+            .headings
+            .iter()
+            .rev()
+            .map(|(position, _)| *position)
+            .find(|&position| position < selected)
+            .unwrap_or(0);
+        self.state.select(Some(previous));
+        true
+    }
+
+    fn add_roots(&mut self, root_tasks: Vec<usize>) {
+        for root_index in root_tasks {
+            self.display_tasks.push(root_index);
+            self.add_subtasks_recursively(root_index);
+        }
+    }
+
+    /// The section of the selected task, or `None` if it has none.
+    pub fn selected_section_id(&self) -> Option<String> {
+        let selected = self.state.selected()?;
+        let index = *self.display_tasks.get(selected)?;
+        self.tasks[index].section_id.clone()
+    }
+
+    fn add_subtasks_recursively(&mut self, parent_index: usize) {
+        if self.folded.contains(&self.tasks[parent_index].id) {
+            return;
+        }
+        let children = self.tree.children(&self.tasks[parent_index].id).to_vec();
         for child_index in children {
             self.display_tasks.push(child_index);
             self.add_subtasks_recursively(child_index);
         }
     }
 
+    /// How many subtasks a folded task hides, or `None` if it is not folded.
+    pub fn hidden_count(&self, index: usize) -> Option<usize> {
+        let task_id = &self.tasks[index].id;
+        if !self.folded.contains(task_id) {
+            return None;
+        }
+        Some(self.tree.descendants(&self.tasks, task_id).len())
+    }
+
+    /// Folds or unfolds the selected task, `None` toggling it. Closing a task
+    /// without subtasks closes its parent instead, as with projects.
+    pub fn fold(&mut self, fold: Option<bool>) -> bool {
+        let Some(&index) = self.state.selected().and_then(|selected| self.display_tasks.get(selected)) else {
+            return false;
+        };
+        let task = &self.tasks[index];
+        let mut id = task.id.clone();
+        if self.tree.children(&id).is_empty() {
+            match (&task.parent_id, fold) {
+                (Some(parent_id), Some(true) | None) if self.tree.position(parent_id).is_some() => {
+                    id = parent_id.clone()
+                }
+                _ => return false,
+            }
+        }
+        let fold = fold.unwrap_or(!self.folded.contains(&id));
+        let changed = if fold {
+            self.folded.insert(id.clone())
+        } else {
+            self.folded.remove(&id)
+        };
+        self.rebuild(Some(id));
+        changed
+    }
+
+    /// Folds every task with subtasks, or unfolds them all.
+    pub fn fold_all(&mut self, fold: bool) -> bool {
+        let folded = if fold {
+            self
+                .tasks
+                .iter()
+                .filter(|task| !self.tree.children(&task.id).is_empty())
+                .map(|task| task.id.clone())
+                .collect()
+        } else {
+            HashSet::new()
+        };
+        let changed = folded != self.folded;
+        self.folded = folded;
+        self.refresh();
+        changed
+    }
+
+    /// The tasks sharing the task's parent, or at the top level its project
+    /// and section, in their order. Includes the task itself.
+    pub fn siblings(&self, index: usize) -> Vec<usize> {
+        self.tree.siblings(index).to_vec()
+    }
+
+    /// Moves the selected task, with its subtasks, above the previous
+    /// sibling on screen.
+    pub fn move_up(&mut self) -> bool {
+        self.move_among_siblings(false)
+    }
+
+    /// Moves the selected task, with its subtasks, below the next sibling
+    /// on screen.
+    pub fn move_down(&mut self) -> bool {
+        self.move_among_siblings(true)
+    }
+
+    /// Swaps places with the neighbouring sibling that is shown and numbers
+    /// all siblings afresh, so tasks that shared an `order` end up distinct.
+    fn move_among_siblings(&mut self, down: bool) -> bool {
+        let Some(&index) = self.state.selected().and_then(|selected| self.display_tasks.get(selected)) else {
+            return false;
+        };
+        let mut siblings = self.siblings(index);
+        let Some(position) = siblings.iter().position(|&i| i == index) else {
+            return false;
+        };
+        let display_tasks: HashSet<usize> = self.display_tasks.iter().copied().collect();
+        let shown = |i: &usize| display_tasks.contains(i);
+        let target = if down {
+            siblings[position + 1..].iter().position(shown).map(|offset| position + 1 + offset)
+        } else {
+            siblings[..position].iter().rposition(shown)
+        };
+        let Some(target) = target else {
+            return false;
+        };
+
+        siblings.remove(position);
+        siblings.insert(target, index);
+        for (order, &i) in siblings.iter().enumerate() {
+            self.tasks[i].order = order as i32 + 1;
+        }
+        self.tree.sort_siblings(&self.tasks, index);
+        self.refresh();
+        true
+    }
+
     pub fn next(&mut self) {
-        if self.display_tasks.len() == 0 {
+        if self.display_tasks.is_empty() {
             self.state.select(None);
             return;
         }
@@ -233,7 +668,7 @@ impl Tasks {
     }
 
     pub fn previous(&mut self) {
-        if self.display_tasks.len() == 0 {
+        if self.display_tasks.is_empty() {
             self.state.select(None);
             return;
         }
@@ -264,6 +699,26 @@ pub enum Filter {
     Today,
     Overdue,
     ProjectId(String),
+    /// Tasks carrying the label with this name.
+    Label(String),
+    /// Tasks matching a query typed in Todoist's filter syntax.
+    Query { text: String, query: Query },
+    /// Overdue tasks and those due in the next `days` days, grouped by day.
+    Upcoming { days: i64 },
+}
+
+/// "Today", "Tomorrow", or the date as in "Wed 21 Oct".
+fn day_heading(date: NaiveDate, today: NaiveDate) -> String {
+    match (date - today).num_days() {
+        0 => "Today".to_string(),
+        1 => "Tomorrow".to_string(),
+        _ => date.format("%a %-d %b").to_string(),
+    }
+}
+
+/// Todoist's `start_day` setting, 1 for Monday to 7 for Sunday.
+pub fn start_day(setting: u8) -> Option<Weekday> {
+    Weekday::try_from(setting.checked_sub(1)?).ok()
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -277,51 +732,23 @@ pub struct Task {
     pub labels: Vec<String>,
     pub parent_id: Option<String>,
     pub order: i32,
-    pub priority: u8,
+    pub priority: Priority,
     pub due: Option<Due>,
     pub url: String,
     pub comment_count: u16,
-    #[serde(skip_serializing)]
+    // Read-only fields are not sent back to the API, so they are missing from
+    // the cache too
+    #[serde(skip_serializing, default)]
     pub created_at: String,
-    #[serde(skip_serializing)]
+    #[serde(skip_serializing, default)]
     pub creator_id: String,
     pub assignee_id: Option<String>,
     pub assigner_id: Option<String>,
     pub duration: Option<Duration>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Due {
-    pub string: String,
-    #[serde(with = "date_format")]
-    pub date: NaiveDate,
-    pub is_recurring: bool,
-    pub datetime: Option<String>,
-    pub timezone: Option<String>,
-}
-
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Duration {
     amount: u32,
     unit: String,
 }
-
-pub mod date_format {
-    use super::*;
-
-    pub fn serialize<S>(date: &NaiveDate, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        let s = format!("{}", date.format("%Y-%m-%d"));
-        serializer.serialize_str(&s)
-    }
-
-    pub fn deserialize<'de, D>(deserializer: D) -> Result<NaiveDate, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        let s = String::deserialize(deserializer)?;
-        NaiveDate::parse_from_str(&s, "%Y-%m-%d").map_err(serde::de::Error::custom)
-    }
-}
@@ -0,0 +1,172 @@
+use std::{collections::HashMap, fmt};
+
+use serde_json::{json, Value};
+
+use crate::{
+    api_error::ApiError,
+    backend::TaskBackend,
+    due::DueAt,
+    queue,
+    tasks::Task,
+};
+
+/// How much a transfer copied.
+#[derive(Debug, Default)]
+pub struct Transferred {
+    pub projects: usize,
+    pub sections: usize,
+    pub labels: usize,
+    pub tasks: usize,
+    pub comments: usize,
+}
+
+impl fmt::Display for Transferred {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} projects, {} sections, {} labels, {} tasks and {} comments",
+            self.projects, self.sections, self.labels, self.tasks, self.comments
+        )
+    }
+}
+
+/// Copies all open projects, sections, labels, tasks and comments from one
+/// backend to another, e.g. from Todoist into the local database. Everything
+/// gets a new id in the destination; the source inbox is merged into the
+/// destination inbox and labels that already exist there are reused.
+pub async fn copy(from: &dyn TaskBackend, to: &dyn TaskBackend) -> Result<Transferred, ApiError> {
+    let mut copied = Transferred::default();
+    let mut ids: HashMap<String, String> = HashMap::new();
+
+    let existing_projects = to.list_projects().await?;
+    let projects = parents_first(from.list_projects().await?, |p| &p.id, |p| p.parent_id.as_ref());
+    for project in projects {
+        if project.is_inbox_project {
+            if let Some(inbox) = existing_projects.iter().find(|p| p.is_inbox_project) {
+                ids.insert(project.id, inbox.id.clone());
+                continue;
+            }
+        }
+        let created = to
+            .create_project(json!({
+                "name": project.name,
+                "color": project.color,
+                "is_favorite": project.is_favorite,
+                "view_style": project.view_style,
+                "parent_id": project.parent_id.and_then(|id| ids.get(&id).cloned()),
+            }))
+            .await?;
+        ids.insert(project.id, created.id);
+        copied.projects += 1;
+    }
+
+    for section in from.list_sections().await? {
+        let Some(project_id) = ids.get(&section.project_id).cloned() else {
+            continue;
+        };
+        let created = to
+            .create_section(json!({
+                "name": section.name,
+                "project_id": project_id,
+                "order": section.order,
+            }))
+            .await?;
+        ids.insert(section.id, created.id);
+        copied.sections += 1;
+    }
+
+    let existing_labels = to.list_labels().await?;
+    for label in from.list_labels().await? {
+        if existing_labels.iter().any(|l| l.name == label.name) {
+            continue;
+        }
+        to.create_label(json!({
+            "name": label.name,
+            "color": label.color,
+            "is_favorite": label.is_favorite,
+        }))
+        .await?;
+        copied.labels += 1;
+    }
+
+    let tasks = parents_first(from.list_tasks().await?, |t| &t.id, |t| t.parent_id.as_ref());
+    for task in tasks {
+        let Some(project_id) = ids.get(&task.project_id).cloned() else {
+            continue;
+        };
+        let mut json = json!({
+            "content": task.content,
+            "description": task.description,
+            "labels": task.labels,
+            "priority": task.priority,
+            "project_id": project_id,
+            "section_id": task.section_id.as_ref().and_then(|id| ids.get(id)),
+            "parent_id": task.parent_id.as_ref().and_then(|id| ids.get(id)),
+            "order": task.order,
+        });
+        add_due(&mut json, &task);
+
+        let created = to.create_task(json, queue::create_request_id()).await?;
+        copied.tasks += 1;
+
+        if task.comment_count > 0 {
+            for comment in from.list_comments(task.id.clone()).await? {
+                to.create_comment(json!({ "task_id": created.id, "content": comment.content }))
+                    .await?;
+                copied.comments += 1;
+            }
+        }
+        ids.insert(task.id, created.id);
+    }
+
+    Ok(copied)
+}
+
+/// Recurring dates keep their rule; everything else is copied as the exact
+/// date or time it currently falls on.
+fn add_due(json: &mut Value, task: &Task) {
+    let Some(due) = &task.due else {
+        return;
+    };
+    if due.is_recurring {
+        json["due_string"] = json!(due.string);
+        return;
+    }
+    match due.at {
+        DueAt::Date(date) => json["due_date"] = json!(date.format("%Y-%m-%d").to_string()),
+        // A floating time can only be given as text
+        DueAt::Floating(datetime) => {
+            json["due_string"] = json!(datetime.format("%Y-%m-%d %H:%M").to_string())
+        }
+        DueAt::Fixed(datetime) => {
+            json["due_datetime"] = json!(datetime.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+        }
+    }
+}
+
+/// Orders items so every parent comes before its children. Items whose parent
+/// is missing are treated as top level.
+fn parents_first<T>(
+    mut items: Vec<T>,
+    id: impl Fn(&T) -> &String,
+    parent_id: impl Fn(&T) -> Option<&String>,
+) -> Vec<T> {
+    let all_ids: Vec<String> = items.iter().map(|item| id(item).clone()).collect();
+    let mut placed: Vec<String> = Vec::new();
+    let mut ordered = Vec::new();
+
+    while !items.is_empty() {
+        let (ready, waiting): (Vec<T>, Vec<T>) = items.into_iter().partition(|item| {
+            parent_id(item).is_none_or(|parent| !all_ids.contains(parent) || placed.contains(parent))
+        });
+        if ready.is_empty() {
+            // A cycle; give up on the hierarchy for what is left
+            ordered.extend(waiting);
+            break;
+        }
+        placed.extend(ready.iter().map(|item| id(item).clone()));
+        ordered.extend(ready);
+        items = waiting;
+    }
+    ordered
+}
@@ -6,13 +6,23 @@ use ratatui::{
     widgets::{Block, Borders, HighlightSpacing, List, ListItem, Paragraph},
 };
 
+mod board;
+mod colors;
+mod command_line;
+mod comments;
 mod error;
+mod filter_dialog;
 mod help;
+mod label_picker;
+mod loading;
+mod move_picker;
 mod new_task;
+mod project_dialog;
+mod sections;
 mod task_editor;
-mod utils;
+pub mod utils;
 
-use crate::{tasks::Filter, App, CurrentFocus};
+use crate::{board as task_board, tasks::Filter, App, CurrentFocus};
 
 pub type Tui = Terminal<CrosstermBackend<Stdout>>;
 
@@ -43,11 +53,48 @@ pub fn ui(f: &mut Frame, app: &mut App) {
         .constraints(vec![Constraint::Percentage(25), Constraint::Percentage(75)])
         .split(chunks[1]);
 
-    let title_block = Block::default()
+    let mut title_block = Block::default()
         .borders(Borders::ALL)
         .border_type(ratatui::widgets::BorderType::Rounded)
         .style(Style::default());
 
+    if let Some(cached_at) = app.cached_at {
+        title_block = title_block.title(
+            Line::from(format!(" cached {} ", utils::format_age(cached_at)))
+                .fg(Color::DarkGray)
+                .right_aligned(),
+        );
+    }
+
+    if app.loading.in_progress() && !app.projects.projects.is_empty() {
+        title_block = title_block.title(
+            Line::from(format!(
+                " refreshing {}/{} ",
+                app.loading.loaded_count(),
+                app.loading.resources.len()
+            ))
+            .fg(Color::DarkGray)
+            .right_aligned(),
+        );
+    }
+
+    if let Some(notice) = app.notice() {
+        title_block = title_block.title(
+            Line::from(format!(" {} ", notice))
+                .fg(Color::Indexed(47))
+                .right_aligned(),
+        );
+    }
+
+    let pending = app.queue.len();
+    if pending > 0 {
+        title_block = title_block.title(
+            Line::from(format!(" {} pending ", pending))
+                .fg(Color::Yellow)
+                .right_aligned(),
+        );
+    }
+
     let title = Paragraph::new(Text::styled(
         "Todoist",
         Style::default().fg(Color::Indexed(47)),
@@ -58,7 +105,14 @@ pub fn ui(f: &mut Frame, app: &mut App) {
 
     let mut list_items = Vec::<ListItem>::new();
 
-    for project in &app.projects.projects {
+    let favorites = app.projects.favorites;
+    for (row, &(index, depth)) in app.projects.rows.iter().enumerate() {
+        if favorites > 0 && row == 0 {
+            list_items.push(group_heading("Favorites"));
+        } else if favorites > 0 && row == favorites {
+            list_items.push(group_heading("My projects"));
+        }
+        let project = &app.projects.projects[index];
         let color = if let Some(selected_project) = &app.projects.selected_project {
             if project.id == *selected_project {
                 Color::Indexed(214)
@@ -69,10 +123,21 @@ pub fn ui(f: &mut Frame, app: &mut App) {
             Color::Yellow
         };
 
-        list_items.push(ListItem::new(Line::from(Span::styled(
-            format!("{}", project.name),
-            Style::default().fg(color),
-        ))));
+        // Favorites are listed flat, so they get no fold marker
+        let marker = if row < favorites || !app.projects.has_children(&project.id) {
+            "  "
+        } else if app.projects.folded.contains(&project.id) {
+            "▸ "
+        } else {
+            "▾ "
+        };
+
+        list_items.push(ListItem::new(Line::from(vec![
+            Span::raw("  ".repeat(depth as usize)),
+            Span::styled(marker, Style::default().fg(Color::DarkGray)),
+            Span::styled("● ", Style::default().fg(colors::project_color(&project.color))),
+            Span::styled(project.name.clone(), Style::default().fg(color)),
+        ])));
     }
 
     let title_text = if app.projects.move_mode {
@@ -104,13 +169,85 @@ pub fn ui(f: &mut Frame, app: &mut App) {
         .highlight_style(highlight_style)
         .highlight_symbol(">")
         .highlight_spacing(HighlightSpacing::Always);
-    f.render_stateful_widget(list, inner_layout[0], &mut app.projects.state);
+    // Labels get the bottom of the sidebar, up to a third of it, with the
+    // saved filters above them taking up to a quarter
+    let label_names = app.labels.names();
+    let labels_height = match label_names.len() {
+        0 => 0,
+        count => (count as u16 + 2).min(inner_layout[0].height / 3).max(3),
+    };
+    let filters_height = (app.filters.filters.len().max(1) as u16 + 2)
+        .min(inner_layout[0].height / 4)
+        .max(3);
+    let [projects_area, filters_area, labels_area] = Layout::vertical([
+        Constraint::Min(3),
+        Constraint::Length(filters_height),
+        Constraint::Length(labels_height),
+    ])
+    .areas(inner_layout[0]);
 
-    let task_title = match app.tasks.filter {
-        Filter::All => " All ",
-        Filter::Today => " Today ",
-        Filter::ProjectId(_) => " Tasks ",
-        Filter::Overdue => " Overdue ",
+    // As with the tasks, the cursor skips the group headings
+    let mut project_list_state = app.projects.state.clone();
+    if let Some(selected) = app.projects.state.selected() {
+        let headings_above = match favorites {
+            0 => 0,
+            _ if selected < favorites => 1,
+            _ => 2,
+        };
+        project_list_state.select(Some(selected + headings_above));
+    }
+    f.render_stateful_widget(list, projects_area, &mut project_list_state);
+    *app.projects.state.offset_mut() = project_list_state.offset();
+
+    saved_filters(f, app, filters_area);
+
+    if !label_names.is_empty() {
+        let label_items: Vec<ListItem> = label_names
+            .into_iter()
+            .map(|name| {
+                let color = if app.labels.selected_label.as_ref() == Some(&name) {
+                    Color::Indexed(214)
+                } else {
+                    Color::Magenta
+                };
+                ListItem::new(Line::from(Span::styled(
+                    format!("@{}", name),
+                    Style::default().fg(color),
+                )))
+            })
+            .collect();
+
+        let labels_block = Block::default()
+            .title(" Labels ".bold())
+            .borders(Borders::ALL)
+            .border_type(ratatui::widgets::BorderType::Rounded)
+            .fg(match app.current_focus {
+                CurrentFocus::Labels => Color::Indexed(47),
+                _ => Color::White,
+            });
+
+        let labels_list = List::new(label_items)
+            .block(labels_block)
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+            .highlight_symbol(">")
+            .highlight_spacing(HighlightSpacing::Always);
+        f.render_stateful_widget(labels_list, labels_area, &mut app.labels.state);
+    }
+
+    let task_title = match &app.tasks.filter {
+        Filter::All => " All ".to_string(),
+        Filter::Today => " Today ".to_string(),
+        Filter::ProjectId(_) => " Tasks ".to_string(),
+        Filter::Overdue => " Overdue ".to_string(),
+        Filter::Label(name) => format!(" @{} ", name),
+        Filter::Query { text, .. } => format!(" {} ", text),
+        Filter::Upcoming { days } => format!(" Upcoming, next {} days ", days),
+    };
+
+    let task_title = if app.tasks.move_mode {
+        format!("{}(Move Mode) ", task_title)
+    } else {
+        task_title
     };
 
     let instructions = Line::from(vec![" For help, press ".into(), "z ".blue().bold()]);
@@ -125,66 +262,182 @@ pub fn ui(f: &mut Frame, app: &mut App) {
             _ => Color::White,
         });
 
+    if task_board::showing(app) {
+        let board_area = tasks_block.inner(inner_layout[1]);
+        f.render_widget(tasks_block, inner_layout[1]);
+        board::board(f, app, board_area);
+    } else {
+        task_list(f, app, tasks_block, inner_layout[1]);
+    }
+
+    // Nothing to show yet: report what is being fetched instead
+    if app.projects.projects.is_empty()
+        && (app.loading.in_progress() || app.loading.has_failed())
+    {
+        loading::loading(f, app);
+    }
+
+    // help popup
+    if app.show_help {
+        help::help(f);
+    }
+
+    if app.show_task_editor {
+        task_editor::editor(f, app)
+    }
+
+    if app.show_new_task {
+        new_task::editor(f, app)
+    }
+
+    if app.show_comments {
+        comments::comments(f, app);
+    }
+
+    if app.show_sections {
+        sections::sections(f, app);
+    }
+
+    if app.show_project_dialog {
+        project_dialog::dialog(f, app);
+    }
+
+    if app.show_filter_dialog {
+        filter_dialog::dialog(f, app);
+    }
+
+    if app.show_move_picker {
+        move_picker::picker(f, app);
+    }
+
+    if app.show_label_picker {
+        label_picker::picker(f, app);
+    }
+
+    if app.show_command_line {
+        command_line::command_line(f, app);
+    }
+
+    if app.show_error {
+        error::render_error_modal(f, app);
+    }
+}
+
+/// The saved filters, the account's and then the local ones marked with a
+/// dimmed "local".
+fn saved_filters(f: &mut Frame, app: &mut App, area: Rect) {
+    let block = Block::default()
+        .title(" Filters ".bold())
+        .borders(Borders::ALL)
+        .border_type(ratatui::widgets::BorderType::Rounded)
+        .fg(match app.current_focus {
+            CurrentFocus::Filters => Color::Indexed(47),
+            _ => Color::White,
+        });
+
+    if app.filters.filters.is_empty() {
+        let hint = Line::from(vec![" No filters, ".dark_gray(), "n".blue().bold(), " to add one".dark_gray()]);
+        f.render_widget(Paragraph::new(hint).block(block), area);
+        return;
+    }
+
+    let items: Vec<ListItem> = app
+        .filters
+        .filters
+        .iter()
+        .map(|filter| {
+            let color = if app.filters.selected_filter.as_ref() == Some(&filter.id) {
+                Color::Indexed(214)
+            } else {
+                Color::Yellow
+            };
+            let mut spans = vec![
+                Span::styled("● ", Style::default().fg(colors::project_color(&filter.color))),
+                Span::styled(filter.name.clone(), Style::default().fg(color)),
+            ];
+            if filter.is_local() {
+                spans.push(Span::styled(" local", Style::default().fg(Color::DarkGray)));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol(">")
+        .highlight_spacing(HighlightSpacing::Always);
+    f.render_stateful_widget(list, area, &mut app.filters.state);
+}
+
+/// The tasks as one list, with section headings between them.
+fn task_list(f: &mut Frame, app: &mut App, tasks_block: Block, area: Rect) {
     let mut task_list_item = Vec::new();
-    let task_list_width = inner_layout[1].width as usize;
-    for i in &app.tasks.display_tasks {
+    let task_list_width = area.width as usize;
+    let mut headings = app.tasks.headings.iter().peekable();
+    for (position, i) in app.tasks.display_tasks.iter().enumerate() {
+        while let Some((_, name)) = headings.next_if(|(before, _)| *before == position) {
+            task_list_item.push(section_heading(name));
+        }
         let task = &app.tasks.tasks[*i];
-        let children: u16 = *app.tasks.tasks_with_children.get(&task.id).unwrap_or(&0);
-        let indentation_level = calculate_indentation_level(&app.tasks.tasks, task);
+        let children = app.tasks.tree.child_count(&task.id);
+        let indentation_level = app.tasks.tree.depth(*i);
         task_list_item.push(utils::generate_list_item(
             &task.content,
+            &task.labels,
+            task.comment_count,
             &task.due,
             task.priority,
             task.is_completed,
             children,
+            app.tasks.hidden_count(*i),
             task_list_width - 4,
             indentation_level,
         ))
     }
 
+    // Headings of sections with no tasks left come last
+    task_list_item.extend(headings.map(|(_, name)| section_heading(name)));
+
+    let highlight_color = if app.tasks.move_mode { Color::Red } else { Color::Cyan };
     let task_list = List::new(task_list_item)
         .block(tasks_block)
         .highlight_style(
             Style::default()
                 .add_modifier(Modifier::BOLD)
                 .add_modifier(Modifier::REVERSED)
-                .fg(Color::Cyan),
+                .fg(highlight_color),
         )
         .highlight_symbol(">")
         .highlight_spacing(HighlightSpacing::Always);
 
-    f.render_stateful_widget(task_list, inner_layout[1], &mut app.tasks.state);
-
-    // help popup
-    if app.show_help {
-        help::help(f);
-    }
-
-    if app.show_task_editor {
-        task_editor::editor(f, app)
-    }
-
-    if app.show_new_task {
-        new_task::editor(f, app)
+    // The cursor counts tasks only, so skip past the headings above it
+    let mut task_list_state = app.tasks.state.clone();
+    if let Some(selected) = app.tasks.state.selected() {
+        let headings_above = app
+            .tasks
+            .headings
+            .iter()
+            .filter(|(before, _)| *before <= selected)
+            .count();
+        task_list_state.select(Some(selected + headings_above));
     }
+    f.render_stateful_widget(task_list, area, &mut task_list_state);
+    *app.tasks.state.offset_mut() = task_list_state.offset();
+}
 
-    if app.show_error {
-        error::render_error_modal(f, app);
-    }
+fn section_heading(name: &str) -> ListItem<'static> {
+    ListItem::new(Line::from(Span::styled(
+        format!(" {} ", name),
+        Style::default()
+            .fg(Color::Indexed(214))
+            .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+    )))
 }
 
-fn calculate_indentation_level(tasks: &Vec<crate::tasks::Task>, task: &crate::tasks::Task) -> u8 {
-    let mut level = 0;
-    let mut current_parent_id = task.parent_id.clone();
-    
-    while let Some(parent_id) = current_parent_id {
-        if let Some(parent_task) = tasks.iter().find(|t| t.id == parent_id) {
-            level += 1;
-            current_parent_id = parent_task.parent_id.clone();
-        } else {
-            break;
-        }
-    }
-    
-    level
+fn group_heading(name: &str) -> ListItem<'static> {
+    ListItem::new(Line::from(Span::styled(
+        name.to_string(),
+        Style::default().fg(Color::DarkGray).add_modifier(Modifier::BOLD),
+    )))
 }
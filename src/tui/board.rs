@@ -0,0 +1,75 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, HighlightSpacing, List, ListState},
+};
+
+use crate::{board, tui::utils, App, CurrentFocus};
+
+/// Narrowest a column gets before the board scrolls sideways.
+const COLUMN_WIDTH: u16 = 28;
+
+/// The project's tasks as side-by-side columns, scrolled to keep the
+/// selected card's column in view.
+pub fn board(f: &mut Frame, app: &mut App, area: Rect) {
+    let columns = board::columns(app);
+    let current = board::selected_column(app, &columns);
+    let visible = (area.width / COLUMN_WIDTH).clamp(1, columns.len() as u16) as usize;
+    let first = (current + 1).saturating_sub(visible);
+
+    let areas = Layout::horizontal(vec![Constraint::Ratio(1, visible as u32); visible]).split(area);
+    for (column, &column_area) in columns.iter().skip(first).zip(areas.iter()) {
+        let selected = app
+            .tasks
+            .state
+            .selected()
+            .filter(|selected| column.tasks.contains(selected));
+        let focused = selected.is_some() && app.current_focus == CurrentFocus::Tasks;
+
+        let width = column_area.width.saturating_sub(4) as usize;
+        let cards: Vec<_> = column
+            .tasks
+            .clone()
+            .map(|position| {
+                let index = app.tasks.display_tasks[position];
+                let task = &app.tasks.tasks[index];
+                let children = app.tasks.tree.child_count(&task.id);
+                utils::generate_list_item(
+                    &task.content,
+                    &task.labels,
+                    task.comment_count,
+                    &task.due,
+                    task.priority,
+                    task.is_completed,
+                    children,
+                    app.tasks.hidden_count(index),
+                    width,
+                    app.tasks.tree.depth(index),
+                )
+            })
+            .collect();
+
+        let title = format!(" {} ({}) ", column.name, column.tasks.len());
+        let block = Block::default()
+            .title(Span::styled(
+                title,
+                Style::default().fg(Color::Indexed(214)).add_modifier(Modifier::BOLD),
+            ))
+            .borders(Borders::ALL)
+            .border_type(ratatui::widgets::BorderType::Rounded)
+            .fg(if focused { Color::Indexed(47) } else { Color::DarkGray });
+
+        let list = List::new(cards)
+            .block(block)
+            .highlight_style(
+                Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .add_modifier(Modifier::REVERSED)
+                    .fg(Color::Cyan),
+            )
+            .highlight_symbol(">")
+            .highlight_spacing(HighlightSpacing::Always);
+
+        let mut state = ListState::default().with_selected(selected.map(|s| s - column.tasks.start));
+        f.render_stateful_widget(list, column_area, &mut state);
+    }
+}
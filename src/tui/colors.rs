@@ -0,0 +1,77 @@
+use std::sync::OnceLock;
+
+use ratatui::style::Color;
+
+/// Todoist's named project colors as RGB.
+fn rgb(name: &str) -> Option<(u8, u8, u8)> {
+    let rgb = match name {
+        "berry_red" => (0xb8, 0x25, 0x6f),
+        "red" => (0xdb, 0x40, 0x35),
+        "orange" => (0xff, 0x99, 0x33),
+        "yellow" => (0xfa, 0xd0, 0x00),
+        "olive_green" => (0xaf, 0xb8, 0x3b),
+        "lime_green" => (0x7e, 0xcc, 0x49),
+        "green" => (0x29, 0x94, 0x38),
+        "mint_green" => (0x6a, 0xcc, 0xbc),
+        "teal" => (0x15, 0x8f, 0xad),
+        "sky_blue" => (0x14, 0xaa, 0xf5),
+        "light_blue" => (0x96, 0xc3, 0xeb),
+        "blue" => (0x40, 0x73, 0xff),
+        "grape" => (0x88, 0x4d, 0xff),
+        "violet" => (0xaf, 0x38, 0xeb),
+        "lavender" => (0xeb, 0x96, 0xeb),
+        "magenta" => (0xe0, 0x51, 0x94),
+        "salmon" => (0xff, 0x8d, 0x85),
+        "charcoal" => (0x80, 0x80, 0x80),
+        "grey" => (0xb8, 0xb8, 0xb8),
+        "taupe" => (0xcc, 0xac, 0x93),
+        _ => return None,
+    };
+    Some(rgb)
+}
+
+fn true_color() -> bool {
+    static TRUE_COLOR: OnceLock<bool> = OnceLock::new();
+    *TRUE_COLOR.get_or_init(|| {
+        std::env::var("COLORTERM").is_ok_and(|value| value == "truecolor" || value == "24bit")
+    })
+}
+
+/// The terminal color for a Todoist color name: exact where the terminal
+/// supports true color, otherwise the nearest of the 256 indexed colors.
+pub fn project_color(name: &str) -> Color {
+    let Some((r, g, b)) = rgb(name) else {
+        return Color::Gray;
+    };
+    if true_color() {
+        Color::Rgb(r, g, b)
+    } else {
+        Color::Indexed(nearest_indexed(r, g, b))
+    }
+}
+
+/// Nearest entry of the xterm 6x6x6 color cube or the grey ramp.
+fn nearest_indexed(r: u8, g: u8, b: u8) -> u8 {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let level = |value: u8| {
+        (0..6)
+            .min_by_key(|&i| (LEVELS[i] as i32 - value as i32).abs())
+            .unwrap_or(0)
+    };
+    let (ri, gi, bi) = (level(r), level(g), level(b));
+    let cube = (LEVELS[ri], LEVELS[gi], LEVELS[bi]);
+
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let grey_index = (average.saturating_sub(8) / 10).min(23) as u8;
+    let grey = 8 + grey_index * 10;
+
+    let distance = |(cr, cg, cb): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(cr, r) + d(cg, g) + d(cb, b)
+    };
+    if distance((grey, grey, grey)) < distance(cube) {
+        232 + grey_index
+    } else {
+        16 + 36 * ri as u8 + 6 * gi as u8 + bi as u8
+    }
+}
@@ -0,0 +1,19 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Clear, Paragraph},
+};
+
+use crate::App;
+
+/// The `:` prompt, drawn over the bottom row like vim's command line.
+pub fn command_line(f: &mut Frame, app: &mut App) {
+    let screen = f.area();
+    let area = Rect::new(screen.x, screen.bottom().saturating_sub(1), screen.width, 1);
+    let [prompt_area, input_area] =
+        Layout::horizontal([Constraint::Length(1), Constraint::Min(1)]).areas(area);
+
+    f.render_widget(Clear, area);
+    f.render_widget(Paragraph::new(":".bold()), prompt_area);
+    app.command_line.input.set_cursor_line_style(Style::default());
+    f.render_widget(&app.command_line.input, input_area);
+}
@@ -0,0 +1,219 @@
+use chrono::{DateTime, Local};
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, HighlightSpacing, List, ListItem, Paragraph},
+};
+use textwrap::wrap;
+
+use crate::{comments::Comment, tui::utils, App};
+
+pub fn comments(f: &mut Frame, app: &mut App) {
+    let area = utils::centered_rect(
+        Constraint::Percentage(70),
+        Constraint::Percentage(70),
+        f.area(),
+    );
+    f.render_widget(Clear, area);
+
+    let instructions = if app.comments.composing {
+        Line::from(vec![
+            " Save ".into(),
+            "Enter".blue().bold(),
+            " cancel ".into(),
+            "Esc ".blue().bold(),
+        ])
+    } else {
+        Line::from(vec![
+            " New ".into(),
+            "n".blue().bold(),
+            " edit ".into(),
+            "e".blue().bold(),
+            " delete ".into(),
+            "d".blue().bold(),
+            " close ".into(),
+            "Esc ".blue().bold(),
+        ])
+    };
+
+    let block = Block::default()
+        .title(format!(" Comments ({}) ", app.comments.comments.len()))
+        .title_bottom(instructions.centered())
+        .borders(Borders::ALL)
+        .border_type(ratatui::widgets::BorderType::Rounded)
+        .fg(Color::Indexed(47));
+    let inner_area = block.inner(area);
+    f.render_widget(block, area);
+
+    let [list_area, input_area] = Layout::vertical([
+        Constraint::Min(1),
+        Constraint::Length(if app.comments.composing { 5 } else { 0 }),
+    ])
+    .areas(inner_area);
+
+    if app.comments.loading {
+        f.render_widget(Paragraph::new(" Loading comments...".dark_gray()), list_area);
+    } else if app.comments.comments.is_empty() {
+        f.render_widget(
+            Paragraph::new(" No comments yet. Press n to write one.".dark_gray()),
+            list_area,
+        );
+    } else {
+        let width = list_area.width.saturating_sub(3) as usize;
+        let items: Vec<ListItem> = app
+            .comments
+            .comments
+            .iter()
+            .map(|comment| comment_item(comment, width))
+            .collect();
+        let list = List::new(items)
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+            .highlight_symbol(">")
+            .highlight_spacing(HighlightSpacing::Always);
+        f.render_stateful_widget(list, list_area, &mut app.comments.state);
+    }
+
+    if app.comments.composing {
+        let title = match app.comments.editing {
+            Some(_) => " Edit comment ",
+            None => " New comment ",
+        };
+        app.comments.input.set_block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(ratatui::widgets::BorderType::Rounded)
+                .title(title),
+        );
+        f.render_widget(&app.comments.input, input_area);
+    }
+}
+
+/// A header with the time and author, then the rendered comment.
+fn comment_item(comment: &Comment, width: usize) -> ListItem<'static> {
+    let posted_at = DateTime::parse_from_rfc3339(&comment.posted_at)
+        .map(|posted_at| {
+            posted_at
+                .with_timezone(&Local)
+                .format("%d %b, %Y %H:%M")
+                .to_string()
+        })
+        .unwrap_or_else(|_| comment.posted_at.clone());
+    let header = match &comment.posted_uid {
+        Some(author) => format!("{} · {}", posted_at, author),
+        None => posted_at,
+    };
+
+    let mut lines = vec![Line::from(header.dark_gray())];
+    lines.extend(markdown(&comment.content, width));
+    lines.push(Line::default());
+    ListItem::new(lines)
+}
+
+/// Renders the markdown Todoist comments commonly use: headings, bullet
+/// lists, **bold**, *italic*, `code` and [links](url). Anything else is shown
+/// as written.
+fn markdown(text: &str, width: usize) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        let heading = trimmed
+            .strip_prefix('#')
+            .and_then(|rest| rest.trim_start_matches('#').strip_prefix(' '));
+        let (prefix, rest, heading) = if let Some(rest) = heading {
+            ("", rest.trim_start(), true)
+        } else if let Some(rest) = trimmed.strip_prefix("- ").or(trimmed.strip_prefix("* ")) {
+            ("• ", rest, false)
+        } else {
+            ("", line, false)
+        };
+
+        if rest.is_empty() {
+            lines.push(Line::default());
+            continue;
+        }
+        let wrapped = wrap(rest, width.saturating_sub(prefix.len()).max(1));
+        for (i, part) in wrapped.iter().enumerate() {
+            // Wrapped list items line up under the text, not the bullet
+            let indent = match (prefix.is_empty(), i) {
+                (true, _) => "",
+                (false, 0) => prefix,
+                (false, _) => "  ",
+            };
+            let mut spans = vec![Span::raw(indent)];
+            spans.extend(inline(part));
+            let line = Line::from(spans);
+            lines.push(if heading { line.bold() } else { line });
+        }
+    }
+    lines
+}
+
+/// Splits a line into styled spans for the inline markup.
+fn inline(text: &str) -> Vec<Span<'static>> {
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    let mut current = String::new();
+    let mut bold = false;
+    let mut italic = false;
+    let mut code = false;
+    let style = |bold: bool, italic: bool, code: bool| {
+        let mut style = Style::default();
+        if bold {
+            style = style.add_modifier(Modifier::BOLD);
+        }
+        if italic {
+            style = style.add_modifier(Modifier::ITALIC);
+        }
+        if code {
+            style = style.fg(Color::Cyan);
+        }
+        style
+    };
+
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let toggle = if code {
+            (c == '`').then_some(1)
+        } else if rest.starts_with("**") {
+            Some(2)
+        } else if c == '*' || c == '`' {
+            Some(1)
+        } else {
+            None
+        };
+
+        // Links keep their text and drop the address
+        if !code && c == '[' {
+            if let Some((label, after)) = rest[1..].split_once("](") {
+                if let Some((_, after)) = after.split_once(')') {
+                    let text_style = style(bold, italic, code);
+                    spans.push(Span::styled(std::mem::take(&mut current), text_style));
+                    spans.push(Span::styled(
+                        label.to_string(),
+                        text_style.fg(Color::Blue).add_modifier(Modifier::UNDERLINED),
+                    ));
+                    rest = after;
+                    continue;
+                }
+            }
+        }
+
+        match toggle {
+            Some(len) => {
+                let text_style = style(bold, italic, code);
+                spans.push(Span::styled(std::mem::take(&mut current), text_style));
+                match &rest[..len] {
+                    "**" => bold = !bold,
+                    "*" => italic = !italic,
+                    _ => code = !code,
+                }
+                rest = &rest[len..];
+            }
+            None => {
+                current.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    spans.push(Span::styled(current, style(bold, italic, code)));
+    spans.retain(|span| !span.content.is_empty());
+    spans
+}
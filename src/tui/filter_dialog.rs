@@ -0,0 +1,98 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+};
+
+use crate::{
+    filter_dialog::{FilterDialogKind, FilterField},
+    projects::COLORS,
+    tui::{colors, utils},
+    App,
+};
+
+pub fn dialog(f: &mut Frame, app: &mut App) {
+    let filter_name = |id: &str| {
+        app.filters
+            .filters
+            .iter()
+            .find(|filter| filter.id == id)
+            .map_or(String::new(), |filter| filter.name.clone())
+    };
+    let (title, height) = match &app.filter_dialog.kind {
+        FilterDialogKind::Create => (" New filter ".to_string(), 11),
+        FilterDialogKind::Edit(id) => (format!(" Edit {} ", filter_name(id)), 11),
+        FilterDialogKind::Delete(_) => (" Delete filter ".to_string(), 4),
+    };
+
+    let instructions = match &app.filter_dialog.kind {
+        FilterDialogKind::Delete(_) => Line::from(vec![
+            " Delete ".into(),
+            "y".red().bold(),
+            " cancel ".into(),
+            "n ".blue().bold(),
+        ]),
+        _ => Line::from(vec![
+            " Save ".into(),
+            "Enter".blue().bold(),
+            " next field ".into(),
+            "Tab".blue().bold(),
+            " cancel ".into(),
+            "Esc ".blue().bold(),
+        ]),
+    };
+
+    let block = Block::default()
+        .title(title)
+        .title_bottom(instructions.centered())
+        .borders(Borders::ALL)
+        .border_type(ratatui::widgets::BorderType::Rounded)
+        .fg(Color::Indexed(47));
+
+    let area = utils::centered_rect(Constraint::Percentage(50), Constraint::Length(height), f.area());
+    f.render_widget(Clear, area);
+
+    if let FilterDialogKind::Delete(id) = &app.filter_dialog.kind {
+        let text = format!(" Delete the filter {}?", filter_name(id));
+        f.render_widget(Paragraph::new(text).wrap(Wrap { trim: false }).block(block), area);
+        return;
+    }
+
+    let inner_area = block.inner(area);
+    f.render_widget(block, area);
+    let [name_area, query_area, color_area, error_area] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Length(3),
+        Constraint::Length(1),
+        Constraint::Length(1),
+    ])
+    .areas(inner_area);
+
+    let dialog = &mut app.filter_dialog;
+    let field_block = |title: &'static str, field: FilterField| {
+        Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_type(ratatui::widgets::BorderType::Rounded)
+            .fg(if dialog.field == field { Color::Indexed(47) } else { Color::White })
+    };
+    let name_block = field_block(" Name ", FilterField::Name);
+    let query_block = field_block(" Query ", FilterField::Query);
+    dialog.name.set_block(name_block);
+    dialog.query.set_block(query_block);
+    f.render_widget(&dialog.name, name_area);
+    f.render_widget(&dialog.query, query_area);
+
+    let color = COLORS[dialog.color];
+    let label_color = if dialog.field == FilterField::Color { Color::Indexed(47) } else { Color::White };
+    let color_line = Line::from(vec![
+        Span::styled(" Color ", Style::default().fg(label_color)),
+        Span::styled("● ", Style::default().fg(colors::project_color(color))),
+        Span::raw(color.replace('_', " ")),
+        Span::styled("  ←/→", Style::default().fg(Color::DarkGray)),
+    ]);
+    f.render_widget(Paragraph::new(color_line), color_area);
+
+    if let Some(error) = &dialog.error {
+        f.render_widget(Paragraph::new(format!(" {}", error).red()), error_area);
+    }
+}
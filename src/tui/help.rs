@@ -22,7 +22,7 @@ pub fn help(f: &mut Frame) {
         Row::new(vec!["q", "Quit"]),
         Row::new(vec![
             "x",
-            "Press x while a task is highlighted to mark as done",
+            "Press x while a task is highlighted to mark as done (recurring tasks move to their next date)",
         ]),
         Row::new(vec![
             "d",
@@ -34,12 +34,35 @@ pub fn help(f: &mut Frame) {
         ]),
         Row::new(vec!["o", "Overdue tasks (or create subtask when task selected)"]),
         Row::new(vec!["e", "All tasks"]),
+        Row::new(vec!["u", "Upcoming tasks by day (:upcoming 14 for the next 14 days, :upcoming week for the rest of the week)"]),
+        Row::new(vec!["[ / ]", "Jump to the previous or next day or section"]),
+        Row::new(vec![":filter", "Show the tasks matching a query, e.g. p1 & @work or due before: +3 days"]),
+        Row::new(vec!["n / e / x", "New, edit or delete a local saved filter (in the filters pane)"]),
         Row::new(vec!["p", "Sort by priority"]),
         Row::new(vec!["d", "Sort by date"]),
-        Row::new(vec!["1-4", "Set task priority (1=highest, 4=lowest)"]),
-        Row::new(vec!["Tab", "Switch between projects and tasks"]),
+        Row::new(vec!["O", "Sort in manual order"]),
+        Row::new(vec!["r", "Sync with Todoist"]),
+        Row::new(vec!["1-4", "Set task priority (p1=urgent, p4=none)"]),
+        Row::new(vec!["S", "Manage the project's sections and move the task into one"]),
+        Row::new(vec!["M", "Move the task and its subtasks to a project, section or parent"]),
+        Row::new(vec![">> / <<", "Indent the task under the one above or outdent it from its parent"]),
+        Row::new(vec!["h/l / H/L", "On a board: change column / move the card to the next column"]),
+        Row::new(vec!["Tab", "Switch between projects, filters, labels and tasks"]),
+        Row::new(vec!["Space", "Choose labels (in the task editors)"]),
+        Row::new(vec!["Ctrl+o", "Show and write comments (in the task editor)"]),
         Row::new(vec!["h", "Switch to project view"]),
         Row::new(vec!["l", "Switch to task view"]),
+        Row::new(vec!["za/zo/zc", "Toggle, open or close the selected project's sub-projects"]),
+        Row::new(vec!["za/zo/zc", "Toggle, open or close the selected task's subtasks (in the task list)"]),
+        Row::new(vec!["zR / zM", "Open or close every task's subtasks"]),
+        Row::new(vec!["s", "Show the tasks of sub-projects with their parent (in the projects pane)"]),
+        Row::new(vec!["m", "Move the selected project and its sub-projects with j/k"]),
+        Row::new(vec!["m", "Move the selected task and its subtasks among its siblings with j/k (in the task list)"]),
+        Row::new(vec!["n / N", "New project / new sub-project of the selected one"]),
+        Row::new(vec!["R / c / v", "Rename, recolor or switch list/board view of the project"]),
+        Row::new(vec!["f", "Toggle the project as a favorite"]),
+        Row::new(vec!["A / U", "Archive the project / unarchive one"]),
+        Row::new(vec!["x", "Delete the project (asks first)"]),
         Row::new(vec!["z", "Show/hide help"]),
     ];
     let row_count = rows.len();
@@ -0,0 +1,54 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, HighlightSpacing, List, ListItem, Paragraph},
+};
+
+use crate::{tui::utils, App};
+
+pub fn picker(f: &mut Frame, app: &mut App) {
+    let items: Vec<ListItem> = app
+        .label_picker
+        .names
+        .iter()
+        .map(|name| {
+            let checked = app.label_picker.chosen.contains(name);
+            ListItem::new(format!("[{}] @{}", if checked { "x" } else { " " }, name))
+        })
+        .collect();
+    let empty = items.is_empty();
+
+    let instructions = Line::from(vec![
+        " Toggle ".into(),
+        "Space".blue().bold(),
+        " save ".into(),
+        "Enter".blue().bold(),
+        " cancel ".into(),
+        "Esc ".blue().bold(),
+    ]);
+
+    let block = Block::default()
+        .title(" Labels ")
+        .title_bottom(instructions.centered())
+        .borders(Borders::ALL)
+        .border_type(ratatui::widgets::BorderType::Rounded)
+        .fg(Color::Indexed(47));
+
+    let area = utils::centered_rect(
+        Constraint::Percentage(40),
+        Constraint::Length(app.label_picker.names.len().clamp(1, 12) as u16 + 2),
+        f.area(),
+    );
+    f.render_widget(Clear, area);
+
+    if empty {
+        f.render_widget(Paragraph::new(" No labels yet").block(block), area);
+        return;
+    }
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol(">")
+        .highlight_spacing(HighlightSpacing::Always);
+    f.render_stateful_widget(list, area, &mut app.label_picker.state);
+}
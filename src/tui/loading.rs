@@ -0,0 +1,76 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+};
+
+use crate::{loading::LoadStatus, tui::utils, App};
+
+const SPINNER: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// Shown while there is nothing to display yet: one line per resource being
+/// fetched, with the error of any that failed.
+pub fn loading(f: &mut Frame, app: &App) {
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_millis())
+        .unwrap_or_default();
+    let spinner = SPINNER[(millis / 100) as usize % SPINNER.len()];
+
+    let mut lines = Vec::new();
+    for (name, status) in &app.loading.resources {
+        let line = match status {
+            LoadStatus::Pending => Line::from(vec![
+                format!(" {} ", spinner).fg(Color::Indexed(47)),
+                format!("Loading {}...", name).into(),
+            ]),
+            LoadStatus::Loaded(count) => Line::from(vec![
+                " ✓ ".fg(Color::Indexed(47)),
+                format!("{} ({})", capitalize(name), count).into(),
+            ]),
+            LoadStatus::Failed(error) => Line::from(vec![
+                " ✗ ".fg(Color::Red),
+                format!("{}: {}", capitalize(name), error).fg(Color::Red),
+            ]),
+        };
+        lines.push(line);
+    }
+
+    let instructions = if app.loading.has_failed() {
+        Line::from(vec![
+            " Press ".into(),
+            "r".blue().bold(),
+            " to retry or ".into(),
+            "q".blue().bold(),
+            " to quit ".into(),
+        ])
+    } else {
+        Line::from(" Fetching your Todoist data ")
+    };
+
+    let block = Block::default()
+        .title(" Loading ")
+        .title_bottom(instructions.centered())
+        .borders(Borders::ALL)
+        .border_type(ratatui::widgets::BorderType::Rounded);
+
+    let height = lines.len() as u16 + 6;
+    let paragraph = Paragraph::new(Text::from(lines))
+        .block(block)
+        .wrap(Wrap { trim: true });
+
+    let area = utils::centered_rect(
+        Constraint::Percentage(60),
+        Constraint::Length(height),
+        f.area(),
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
+fn capitalize(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,71 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, HighlightSpacing, List, ListItem, Paragraph},
+};
+
+use crate::{move_picker::Destination, tui::utils, App};
+
+pub fn picker(f: &mut Frame, app: &mut App) {
+    let picker = &app.move_picker;
+    let items: Vec<ListItem> = picker
+        .matches
+        .iter()
+        .map(|&index| {
+            let target = &picker.targets[index];
+            let color = match target.destination {
+                Destination::Project(_) => Color::Yellow,
+                Destination::Section(_) => Color::Indexed(214),
+                Destination::Parent(_) => Color::White,
+            };
+            ListItem::new(Span::styled(target.label.clone(), Style::default().fg(color)))
+        })
+        .collect();
+    let empty = items.is_empty();
+
+    let instructions = Line::from(vec![
+        " Move ".into(),
+        "Enter".blue().bold(),
+        " choose ".into(),
+        "↑/↓".blue().bold(),
+        " cancel ".into(),
+        "Esc ".blue().bold(),
+    ]);
+
+    let block = Block::default()
+        .title(" Move to ")
+        .title_bottom(instructions.centered())
+        .borders(Borders::ALL)
+        .border_type(ratatui::widgets::BorderType::Rounded)
+        .fg(Color::Indexed(47));
+
+    let area = utils::centered_rect(
+        Constraint::Percentage(60),
+        Constraint::Percentage(60),
+        f.area(),
+    );
+    f.render_widget(Clear, area);
+    let inner_area = block.inner(area);
+    f.render_widget(block, area);
+
+    let [input_area, list_area] =
+        Layout::vertical([Constraint::Length(3), Constraint::Min(1)]).areas(inner_area);
+
+    app.move_picker.input.set_block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(ratatui::widgets::BorderType::Rounded)
+            .title(" Project, section or parent task "),
+    );
+    f.render_widget(&app.move_picker.input, input_area);
+
+    if empty {
+        f.render_widget(Paragraph::new(" Nothing matches".dark_gray()), list_area);
+        return;
+    }
+
+    let list = List::new(items)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol(">")
+        .highlight_spacing(HighlightSpacing::Always);
+    f.render_stateful_widget(list, list_area, &mut app.move_picker.state);
+}
@@ -8,7 +8,7 @@ use crate::{new_task::CurrentlyEditing, tui::utils, App};
 pub fn editor(f: &mut Frame, app: &mut App) {
     let area = utils::centered_rect(
         Constraint::Percentage(60),
-        Constraint::Percentage(40),
+        Constraint::Percentage(60),
         f.area(),
     );
 
@@ -25,6 +25,7 @@ pub fn editor(f: &mut Frame, app: &mut App) {
             Constraint::Length(3),
             Constraint::Length(5),
             Constraint::Length(3),
+            Constraint::Length(3),
             Constraint::Min(1),
         ])
         .split(inner_area);
@@ -80,6 +81,13 @@ pub fn editor(f: &mut Frame, app: &mut App) {
     f.render_widget(description, vertical_split[1]);
     f.render_widget(priority_string, vertical_split[2]);
     f.render_widget(due_string, vertical_split[3]);
+    f.render_widget(
+        utils::labels_field(
+            app.new_task.labels.as_deref().unwrap_or_default(),
+            app.new_task.currently_editing == CurrentlyEditing::Labels,
+        ),
+        vertical_split[4],
+    );
 
     let close_modal_desc = Line::from(vec![
         " To save, press ".into(),
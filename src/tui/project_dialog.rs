@@ -0,0 +1,124 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, HighlightSpacing, List, ListItem, Paragraph, Wrap},
+};
+
+use crate::{
+    project_dialog::DialogKind,
+    projects::COLORS,
+    tui::{colors, utils},
+    App,
+};
+
+pub fn dialog(f: &mut Frame, app: &mut App) {
+    let project_name = |id: &str| {
+        app.projects
+            .projects
+            .iter()
+            .find(|p| p.id == id)
+            .map_or(String::new(), |p| p.name.clone())
+    };
+    let (title, height) = match &app.project_dialog.kind {
+        DialogKind::Create => (" New project ".to_string(), 3),
+        DialogKind::CreateChild(parent_id) => (format!(" New project in {} ", project_name(parent_id)), 3),
+        DialogKind::Rename(_) => (" Rename project ".to_string(), 3),
+        DialogKind::Color(id) => (format!(" Color of {} ", project_name(id)), 12),
+        DialogKind::Delete(_) => (" Delete project ".to_string(), 4),
+        DialogKind::Archived => (
+            " Archived projects ".to_string(),
+            app.project_dialog.archived.len().clamp(1, 12) as u16 + 2,
+        ),
+    };
+
+    let instructions = match &app.project_dialog.kind {
+        DialogKind::Delete(_) => Line::from(vec![
+            " Delete ".into(),
+            "y".red().bold(),
+            " cancel ".into(),
+            "n ".blue().bold(),
+        ]),
+        DialogKind::Archived => Line::from(vec![
+            " Unarchive ".into(),
+            "Enter".blue().bold(),
+            " close ".into(),
+            "Esc ".blue().bold(),
+        ]),
+        _ => Line::from(vec![
+            " Save ".into(),
+            "Enter".blue().bold(),
+            " cancel ".into(),
+            "Esc ".blue().bold(),
+        ]),
+    };
+
+    let block = Block::default()
+        .title(title)
+        .title_bottom(instructions.centered())
+        .borders(Borders::ALL)
+        .border_type(ratatui::widgets::BorderType::Rounded)
+        .fg(Color::Indexed(47));
+
+    let area = utils::centered_rect(Constraint::Percentage(40), Constraint::Length(height), f.area());
+    f.render_widget(Clear, area);
+
+    match app.project_dialog.kind.clone() {
+        DialogKind::Create | DialogKind::CreateChild(_) | DialogKind::Rename(_) => {
+            app.project_dialog.input.set_block(block);
+            f.render_widget(&app.project_dialog.input, area);
+        }
+        DialogKind::Color(_) => {
+            let items: Vec<ListItem> = COLORS
+                .iter()
+                .map(|color| {
+                    ListItem::new(Line::from(vec![
+                        Span::styled("● ", Style::default().fg(colors::project_color(color))),
+                        Span::raw(color.replace('_', " ")),
+                    ]))
+                })
+                .collect();
+            let list = List::new(items)
+                .block(block)
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+                .highlight_symbol(">")
+                .highlight_spacing(HighlightSpacing::Always);
+            f.render_stateful_widget(list, area, &mut app.project_dialog.state);
+        }
+        DialogKind::Delete(id) => {
+            let tasks = app
+                .tasks
+                .tasks
+                .iter()
+                .filter(|task| {
+                    task.project_id == id
+                        || app.projects.descendants(&id).iter().any(|p| p.id == task.project_id)
+                })
+                .count();
+            let text = format!(
+                " Delete {} with its sub-projects and {} tasks?",
+                project_name(&id),
+                tasks
+            );
+            f.render_widget(Paragraph::new(text).wrap(Wrap { trim: false }).block(block), area);
+        }
+        DialogKind::Archived => {
+            if app.project_dialog.loading {
+                f.render_widget(Paragraph::new(" Loading...".dark_gray()).block(block), area);
+            } else if app.project_dialog.archived.is_empty() {
+                f.render_widget(Paragraph::new(" No archived projects".dark_gray()).block(block), area);
+            } else {
+                let items: Vec<ListItem> = app
+                    .project_dialog
+                    .archived
+                    .iter()
+                    .map(|project| ListItem::new(project.name.clone()))
+                    .collect();
+                let list = List::new(items)
+                    .block(block)
+                    .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+                    .highlight_symbol(">")
+                    .highlight_spacing(HighlightSpacing::Always);
+                f.render_stateful_widget(list, area, &mut app.project_dialog.state);
+            }
+        }
+    }
+}
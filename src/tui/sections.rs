@@ -0,0 +1,88 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, HighlightSpacing, List, ListItem},
+};
+
+use crate::{tui::utils, App};
+
+pub fn sections(f: &mut Frame, app: &mut App) {
+    let sections = app.tasks.sections.for_project(&app.sections_view.project_id);
+    let mut items = vec![ListItem::new("(No section)".dark_gray())];
+    items.extend(
+        sections
+            .iter()
+            .map(|section| ListItem::new(section.name.clone())),
+    );
+    let rows = items.len() as u16;
+
+    let instructions = if app.sections_view.composing {
+        Line::from(vec![
+            " Save ".into(),
+            "Enter".blue().bold(),
+            " cancel ".into(),
+            "Esc ".blue().bold(),
+        ])
+    } else if app.sections_view.confirm_delete {
+        Line::from(vec![
+            " Delete the section and its tasks? ".into(),
+            "y".red().bold(),
+            "/".into(),
+            "n ".blue().bold(),
+        ])
+    } else {
+        Line::from(vec![
+            " Move task ".into(),
+            "Enter".blue().bold(),
+            " new ".into(),
+            "n".blue().bold(),
+            " rename ".into(),
+            "r".blue().bold(),
+            " delete ".into(),
+            "d".blue().bold(),
+            " reorder ".into(),
+            "J/K".blue().bold(),
+            " close ".into(),
+            "Esc ".blue().bold(),
+        ])
+    };
+
+    let block = Block::default()
+        .title(" Sections ")
+        .title_bottom(instructions.centered())
+        .borders(Borders::ALL)
+        .border_type(ratatui::widgets::BorderType::Rounded)
+        .fg(Color::Indexed(47));
+
+    let input_height = if app.sections_view.composing { 3 } else { 0 };
+    let area = utils::centered_rect(
+        Constraint::Percentage(60),
+        Constraint::Length(rows.min(12) + input_height + 2),
+        f.area(),
+    );
+    f.render_widget(Clear, area);
+    let inner_area = block.inner(area);
+    f.render_widget(block, area);
+
+    let [list_area, input_area] =
+        Layout::vertical([Constraint::Min(1), Constraint::Length(input_height)]).areas(inner_area);
+
+    let list = List::new(items)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol(">")
+        .highlight_spacing(HighlightSpacing::Always);
+    f.render_stateful_widget(list, list_area, &mut app.sections_view.state);
+
+    if app.sections_view.composing {
+        let title = match app.sections_view.renaming {
+            Some(_) => " Rename section ",
+            None => " New section ",
+        };
+        app.sections_view.input.set_block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(ratatui::widgets::BorderType::Rounded)
+                .title(title),
+        );
+        f.render_widget(&app.sections_view.input, input_area);
+    }
+}
@@ -8,7 +8,7 @@ use crate::{task_edit::CurrentlyEditing, tui::utils, App};
 pub fn editor(f: &mut Frame, app: &mut App) {
     let area = utils::centered_rect(
         Constraint::Percentage(60),
-        Constraint::Percentage(40),
+        Constraint::Percentage(60),
         f.area(),
     );
 
@@ -26,6 +26,7 @@ pub fn editor(f: &mut Frame, app: &mut App) {
             Constraint::Length(5),
             Constraint::Length(3),
             Constraint::Length(3),
+            Constraint::Length(3),
             Constraint::Min(1),
         ])
         .split(inner_area);
@@ -42,16 +43,19 @@ pub fn editor(f: &mut Frame, app: &mut App) {
         });
 
     let mut task_list_item = Vec::new();
-    for i in &app.task_edit.children {
-        let task = &app.tasks.tasks[*i];
-        let children: u16 = *app.tasks.tasks_with_children.get(&task.id).unwrap_or(&0);
-        let indentation_level = calculate_indentation_level(&app.tasks.tasks, task);
+    for i in app.task_edit.children.iter().filter_map(|id| app.tasks.tree.position(id)) {
+        let task = &app.tasks.tasks[i];
+        let children = app.tasks.tree.child_count(&task.id);
+        let indentation_level = app.tasks.tree.depth(i);
         task_list_item.push(utils::generate_list_item(
             &task.content,
+            &task.labels,
+            task.comment_count,
             &task.due,
             task.priority,
             task.is_completed,
             children,
+            None,
             task_list_width - 4,
             indentation_level,
         ))
@@ -117,12 +121,21 @@ pub fn editor(f: &mut Frame, app: &mut App) {
     f.render_widget(description, vertical_split[1]);
     f.render_widget(priority_string, vertical_split[2]);
     f.render_widget(due_string, vertical_split[3]);
+    f.render_widget(
+        utils::labels_field(
+            &app.task_edit.labels,
+            app.task_edit.currently_editing == CurrentlyEditing::Labels,
+        ),
+        vertical_split[4],
+    );
 
     let close_modal_desc = Line::from(vec![
         " To save, press ".into(),
         "Enter".blue().bold(),
         " and to close, press ".into(),
         "Esc".blue().bold(),
+        ". Comments: ".into(),
+        "Ctrl+o ".blue().bold(),
     ]);
 
     let block = Block::default()
@@ -133,25 +146,9 @@ pub fn editor(f: &mut Frame, app: &mut App) {
 
     f.render_stateful_widget(
         task_list,
-        vertical_split[3],
+        vertical_split[5],
         &mut app.task_edit.children_list_state,
     );
 
     f.render_widget(block, area);
 }
-
-fn calculate_indentation_level(tasks: &Vec<crate::tasks::Task>, task: &crate::tasks::Task) -> u8 {
-    let mut level = 0;
-    let mut current_parent_id = task.parent_id.clone();
-    
-    while let Some(parent_id) = current_parent_id {
-        if let Some(parent_task) = tasks.iter().find(|t| t.id == parent_id) {
-            level += 1;
-            current_parent_id = parent_task.parent_id.clone();
-        } else {
-            break;
-        }
-    }
-    
-    level
-}
@@ -1,8 +1,11 @@
-use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike};
-use ratatui::{prelude::*, widgets::ListItem};
+use chrono::{Datelike, NaiveDate, Timelike};
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, ListItem, Paragraph},
+};
 use textwrap::fill;
 
-use crate::tasks::Due;
+use crate::{due::Due, priority::Priority};
 
 pub fn centered_rect(horizontal: Constraint, vertical: Constraint, r: Rect) -> Rect {
     let popup_layout = match vertical {
@@ -44,50 +47,56 @@ pub fn centered_rect(horizontal: Constraint, vertical: Constraint, r: Rect) -> R
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn generate_list_item<'a>(
     content: &String,
+    labels: &[String],
+    comment_count: u16,
     due: &Option<Due>,
-    priority: u8,
+    priority: Priority,
     is_completed: bool,
     children: u16,
+    folded: Option<usize>,
     width: usize,
     indentation_level: u8,
 ) -> ListItem<'a> {
-    let priority_emoji = match priority {
-        1 => "🔴", // Red circle for highest priority
-        2 => "🟠", // Orange circle for high priority
-        3 => "🟡", // Yellow circle for medium priority
-        4 => "  ", // Two spaces for lowest priority (P4)
-        _ => "  ", // Default to two spaces (P4)
+    let priority_emoji = match priority.level() {
+        1 => "🔴", // Red circle for p1 (urgent)
+        2 => "🟠", // Orange circle for p2
+        3 => "🟡", // Yellow circle for p3
+        _ => "  ", // Two spaces for p4 (no priority)
     };
 
-    let formatted_due = if let Some(due) = due {
-        if let Some(datetime_str) = &due.datetime {
-            if let Ok(datetime) = NaiveDateTime::parse_from_str(datetime_str, "%Y-%m-%dT%H:%M:%S") {
-                format!(
-                    "(due: {} at {:02}:{:02})",
-                    format_date(datetime.date()),
-                    datetime.hour(),
-                    datetime.minute()
-                )
-            } else {
-                format!("(due: {})", format_date(due.date))
-            }
-        } else {
-            format!("(due: {})", format_date(due.date))
-        }
+    let formatted_due = match due {
+        Some(due) => format!("(due: {})", format_due(due)),
+        None => String::new(),
+    };
+
+    let chips: String = labels.iter().map(|label| format!(" @{}", label)).collect();
+    let comments = if comment_count > 0 {
+        format!(" 💬{}", comment_count)
     } else {
         String::new()
     };
 
+    // A folded task says how many subtasks it hides
+    let (marker, hidden) = match folded {
+        Some(hidden) => ("▸", format!(" [+{}]", hidden)),
+        None if children > 0 => ("⤷", String::new()),
+        None => (" ", String::new()),
+    };
+
     let indent = "  ".repeat(indentation_level as usize);
     let formatted_text = format!(
-        "{}[{}]{}{} {} {}",
+        "{}[{}]{}{} {}{}{}{} {}",
         indent,
         if is_completed { "✓" } else { " " },
-        if children > 0 { "⤷" } else { " " },
+        marker,
         priority_emoji,
         content,
+        hidden,
+        chips,
+        comments,
         formatted_due
     );
 
@@ -99,6 +108,57 @@ pub fn generate_list_item<'a>(
     ))
 }
 
+/// The chosen labels as chips, or how to choose some.
+pub fn labels_field<'a>(labels: &[String], focused: bool) -> Paragraph<'a> {
+    let text = if labels.is_empty() {
+        Line::from(" Press Space to choose".dark_gray())
+    } else {
+        Line::from(
+            labels
+                .iter()
+                .map(|label| format!("@{} ", label).magenta())
+                .collect::<Vec<_>>(),
+        )
+    };
+    Paragraph::new(text).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(ratatui::widgets::BorderType::Rounded)
+            .title(" Labels ")
+            .fg(if focused { Color::Indexed(47) } else { Color::White }),
+    )
+}
+
+/// A due date on the user's clock, whatever timezone the task was set in,
+/// e.g. "20 Oct, 2026 at 09:00".
+pub fn format_due(due: &Due) -> String {
+    match due.local_datetime() {
+        Some(datetime) => format!(
+            "{} at {:02}:{:02}",
+            format_date(datetime.date()),
+            datetime.hour(),
+            datetime.minute()
+        ),
+        None => format_date(due.local_date()),
+    }
+}
+
+/// How long ago a unix timestamp was, e.g. "5m ago".
+pub fn format_age(timestamp: u64) -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(timestamp);
+    let age = now.saturating_sub(timestamp);
+
+    match age {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{}m ago", age / 60),
+        3600..=86399 => format!("{}h ago", age / 3600),
+        _ => format!("{}d ago", age / 86400),
+    }
+}
+
 fn format_date(date: NaiveDate) -> String {
     let month = match date.month() {
         1 => "Jan",
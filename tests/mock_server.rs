@@ -0,0 +1,298 @@
+//! Drives the key handlers against the bundled mock server, applying what
+//! comes back over the task channel the way the main loop does.
+
+use std::{
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Once,
+    },
+    time::{Duration, Instant},
+};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde_json::json;
+use todoist::{
+    api_calls::ApiClient,
+    api_error::ApiError,
+    backend::{Backend, RestBackend},
+    handle_result, initial_load,
+    key_handler::{handle_new_tasks, handle_tasks},
+    mock_server,
+    queue::TEMP_ID_PREFIX,
+    store::JsonStore,
+    sync::SyncEngine,
+    App, TaskResult,
+};
+use tokio::sync::Mutex;
+
+struct Session {
+    app: Arc<Mutex<App<'static>>>,
+    client: Backend,
+    sync_engine: SyncEngine,
+    tx: Sender<TaskResult>,
+    rx: Receiver<TaskResult>,
+}
+
+impl Session {
+    /// Starts a mock server with the demo account and loads it, showing the
+    /// inbox.
+    async fn start() -> Session {
+        // Saved project orders, views and folds must come from an empty
+        // config, not the one of whoever runs the tests
+        static CONFIG_DIR: Once = Once::new();
+        CONFIG_DIR.call_once(|| {
+            let dir = std::env::temp_dir().join(format!("todoist-mock-test-{}", std::process::id()));
+            std::env::set_var("TODOIST_CONFIG_DIR", dir);
+        });
+
+        let addr = mock_server::spawn(([127, 0, 0, 1], 0).into(), JsonStore::demo()).unwrap();
+        let base_url = format!("http://{}", addr);
+        let sync_url = format!("{}/sync", base_url);
+        let client: Backend = Arc::new(RestBackend(ApiClient::new("test", base_url, sync_url).unwrap()));
+        let sync_engine = SyncEngine::new(client.clone(), None, false);
+        let (tx, rx) = mpsc::channel();
+
+        let app = Arc::new(Mutex::new(App::new()));
+        initial_load(app.clone(), client.clone(), None, tx.clone()).await;
+        let session = Session {
+            app,
+            client,
+            sync_engine,
+            tx,
+            rx,
+        };
+        assert!(session.app.lock().await.error_message.is_none());
+        session
+    }
+
+    async fn press_in_tasks(&self, code: KeyCode) {
+        let mut app = self.app.lock().await;
+        handle_tasks(&mut app, key(code), self.client.clone(), self.tx.clone());
+    }
+
+    async fn type_new_task(&self, text: &str) {
+        let mut app = self.app.lock().await;
+        for c in text.chars() {
+            handle_new_tasks(&mut app, key(KeyCode::Char(c)), self.client.clone(), self.tx.clone());
+        }
+        handle_new_tasks(&mut app, key(KeyCode::Enter), self.client.clone(), self.tx.clone());
+    }
+
+    /// Applies results from the channel until `done` holds, failing after a
+    /// few seconds.
+    async fn wait_for(&self, what: &str, done: impl Fn(&App) -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            {
+                let mut app = self.app.lock().await;
+                while let Ok(received) = self.rx.try_recv() {
+                    handle_result(&mut app, received, &self.sync_engine, None, &self.tx);
+                }
+                assert!(app.error_message.is_none(), "{:?}", app.error_message);
+                if done(&app) {
+                    return;
+                }
+            }
+            assert!(Instant::now() < deadline, "Timed out waiting for {}", what);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    }
+
+    /// Puts the cursor on the shown task with this content.
+    async fn select(&self, content: &str) {
+        let mut app = self.app.lock().await;
+        let row = shown(&app).iter().position(|shown| shown == content);
+        assert!(row.is_some(), "{} is not shown", content);
+        app.tasks.state.select(row);
+    }
+}
+
+fn key(code: KeyCode) -> KeyEvent {
+    KeyEvent::new(code, KeyModifiers::NONE)
+}
+
+/// The contents of the tasks in the list, in the order shown.
+fn shown(app: &App) -> Vec<String> {
+    app.tasks
+        .display_tasks
+        .iter()
+        .map(|&index| app.tasks.tasks[index].content.clone())
+        .collect()
+}
+
+fn task_id(app: &App, content: &str) -> Option<String> {
+    app.tasks.tasks.iter().find(|task| task.content == content).map(|task| task.id.clone())
+}
+
+#[tokio::test]
+async fn loads_the_inbox() {
+    let session = Session::start().await;
+    let app = session.app.lock().await;
+
+    assert_eq!(app.projects.selected().map(|project| project.name.as_str()), Some("Inbox"));
+    assert_eq!(shown(&app), ["Try out todoist-vim", "Read the help screen"]);
+}
+
+#[tokio::test]
+async fn creates_closes_reopens_and_syncs_a_task() {
+    let session = Session::start().await;
+
+    // Shown at once with a placeholder id, which the server's id replaces
+    session.press_in_tasks(KeyCode::Char('a')).await;
+    assert!(session.app.lock().await.show_new_task);
+    session.type_new_task("Buy milk").await;
+    {
+        let app = session.app.lock().await;
+        assert!(!app.show_new_task);
+        assert!(shown(&app).contains(&"Buy milk".to_string()));
+        assert!(task_id(&app, "Buy milk").unwrap().starts_with(TEMP_ID_PREFIX));
+    }
+    session
+        .wait_for("the task to be created", |app| {
+            task_id(app, "Buy milk").is_some_and(|id| !id.starts_with(TEMP_ID_PREFIX))
+        })
+        .await;
+    let id = task_id(&*session.app.lock().await, "Buy milk").unwrap();
+    let task = session.client.get_task(id.clone()).await.unwrap();
+    assert_eq!(task.content, "Buy milk");
+    assert!(!task.is_completed);
+
+    // Closing hides it right away and the server follows
+    session.select("Buy milk").await;
+    session.press_in_tasks(KeyCode::Char('x')).await;
+    assert!(!shown(&*session.app.lock().await).contains(&"Buy milk".to_string()));
+    session.wait_for("the close to be sent", |app| app.queue.is_empty()).await;
+    let open = session.client.list_tasks().await.unwrap();
+    assert!(open.iter().all(|task| task.id != id));
+
+    // There is no undo key yet; reopening it on the server brings it back
+    // with the next sync
+    session.client.reopen_task(id.clone()).await.unwrap();
+    session.sync_engine.spawn_sync(session.tx.clone(), true);
+    session
+        .wait_for("the reopened task to be synced", |app| {
+            shown(app).contains(&"Buy milk".to_string())
+        })
+        .await;
+
+    // Changes made elsewhere arrive the same way
+    session
+        .client
+        .update_task(id.clone(), json!({ "content": "Buy oat milk" }))
+        .await
+        .unwrap();
+    session.sync_engine.spawn_sync(session.tx.clone(), true);
+    session
+        .wait_for("the rename to be synced", |app| {
+            shown(app).contains(&"Buy oat milk".to_string())
+        })
+        .await;
+    assert_eq!(task_id(&*session.app.lock().await, "Buy oat milk"), Some(id));
+}
+
+#[tokio::test]
+async fn closing_a_task_closes_its_subtasks() {
+    let session = Session::start().await;
+    {
+        let mut app = session.app.lock().await;
+        let work = app
+            .projects
+            .projects
+            .iter()
+            .find(|project| project.name == "Work")
+            .map(|project| project.id.clone());
+        todoist::select_project(&mut app, work);
+        assert!(shown(&app).contains(&"Collect feedback".to_string()));
+    }
+
+    session.select("Draft the roadmap").await;
+    session.press_in_tasks(KeyCode::Char('x')).await;
+    {
+        let app = session.app.lock().await;
+        let shown = shown(&app);
+        assert!(!shown.contains(&"Draft the roadmap".to_string()));
+        assert!(!shown.contains(&"Collect feedback".to_string()));
+        assert!(!shown.contains(&"Write summary".to_string()));
+    }
+    session.wait_for("the closes to be sent", |app| app.queue.is_empty()).await;
+
+    let open: Vec<String> = session
+        .client
+        .list_tasks()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|task| task.content)
+        .collect();
+    assert!(!open.contains(&"Draft the roadmap".to_string()));
+    assert!(!open.contains(&"Collect feedback".to_string()));
+    assert!(open.contains(&"Review open pull requests".to_string()));
+}
+
+#[tokio::test]
+async fn a_repeated_create_makes_one_task() {
+    let session = Session::start().await;
+    let json = json!({ "content": "Call the bank" });
+
+    let first = session.client.create_task(json.clone(), "create-1".to_string()).await.unwrap();
+    let again = session.client.create_task(json, "create-1".to_string()).await.unwrap();
+    assert_eq!(first.id, again.id);
+
+    let tasks = session.client.list_tasks().await.unwrap();
+    assert_eq!(tasks.iter().filter(|task| task.content == "Call the bank").count(), 1);
+}
+
+#[tokio::test]
+async fn a_due_date_off_the_calendar_is_refused() {
+    let session = Session::start().await;
+    let json = json!({ "content": "Someday", "due_string": "in 99999999 days" });
+
+    let refused = session.client.create_task(json, "create-1".to_string()).await;
+    assert!(matches!(refused, Err(ApiError::Client { .. })), "{:?}", refused);
+}
+
+#[tokio::test]
+async fn closing_a_task_reschedules_its_recurring_subtasks() {
+    let session = Session::start().await;
+    let summary = task_id(&*session.app.lock().await, "Write summary").unwrap();
+    session
+        .client
+        .update_task(summary.clone(), json!({ "due_string": "every day" }))
+        .await
+        .unwrap();
+    session.sync_engine.spawn_sync(session.tx.clone(), true);
+    session
+        .wait_for("the subtask to recur", |app| {
+            app.tasks
+                .tasks
+                .iter()
+                .any(|task| task.id == summary && task.due.as_ref().is_some_and(|due| due.is_recurring))
+        })
+        .await;
+    let due = session.client.get_task(summary.clone()).await.unwrap().due.unwrap().local_date();
+
+    {
+        let mut app = session.app.lock().await;
+        let work = app
+            .projects
+            .projects
+            .iter()
+            .find(|project| project.name == "Work")
+            .map(|project| project.id.clone());
+        todoist::select_project(&mut app, work);
+    }
+    session.select("Draft the roadmap").await;
+    session.press_in_tasks(KeyCode::Char('x')).await;
+    {
+        let app = session.app.lock().await;
+        assert!(!shown(&app).contains(&"Draft the roadmap".to_string()));
+        // Kept with its next date, for the views that list subtasks alone
+        let kept = app.tasks.tasks.iter().find(|task| task.id == summary);
+        assert!(kept.and_then(|task| task.due.as_ref()).is_some_and(|kept| kept.local_date() > due));
+    }
+    session.wait_for("the closes to be sent", |app| app.queue.is_empty()).await;
+
+    let summary = session.client.get_task(summary).await.unwrap();
+    assert!(!summary.is_completed);
+    assert!(summary.due.unwrap().local_date() > due);
+}